@@ -0,0 +1,82 @@
+//! The audit log every editor write leaves behind, one entry per changed value.
+
+use crate::db::{query_as, RowError};
+use crate::models::IntValue;
+use rusqlite::*;
+use std::env;
+
+pub struct AuditPackage {
+    pub table_name: &'static str,
+    pub row_key: String,
+    pub column_name: Option<&'static str>,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub entity_id: Option<String>,
+}
+
+pub fn os_user() -> String {
+    match env::var("USERNAME").or_else(|_| env::var("USER")) {
+        Ok(u) => u,
+        Err(_) => String::from("unknown"),
+    }
+}
+
+// run inside the write's savepoint, so a change is never kept without its entry
+pub fn record_audit(db: &Connection, package: AuditPackage) -> Result<()> {
+    db.execute(
+        "INSERT INTO entity_audit_log (
+            os_user, table_name, row_key, column_name, before_value, after_value, entity_base_id
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7
+        );",
+        (
+            os_user(),
+            package.table_name,
+            &package.row_key,
+            package.column_name,
+            &package.before,
+            &package.after,
+            &package.entity_id,
+        ),
+    )?;
+    let audit_id: i64 = db.last_insert_rowid();
+
+    // a temp table is this connection's alone and rolls back with the write
+    ensure_session_log(db)?;
+    db.execute(
+        "INSERT INTO temp.session_audit_ids (entity_audit_log_id) VALUES (?1);",
+        [audit_id],
+    )?;
+
+    Ok(())
+}
+
+// the entries this connection wrote after `after`, oldest first. another program's
+// writes, or another editor run as the same user, never show up here
+pub fn session_audit_ids_after(db: &Connection, after: i64) -> Result<Vec<i64>, RowError> {
+    ensure_session_log(db)?;
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":after": after };
+    let ids: Vec<IntValue> = query_as(
+        db,
+        "SELECT 's'.'entity_audit_log_id' as 'value' FROM temp.session_audit_ids as 's' WHERE 's'.'entity_audit_log_id' > :after ORDER BY 's'.'entity_audit_log_id' ASC;",
+        x,
+    )?;
+
+    Ok(ids.into_iter().map(|id| id.value).collect())
+}
+
+// after a restore the log's ids start over, the ones written before it mean nothing
+pub fn forget_session_audit_ids(db: &Connection) -> Result<()> {
+    ensure_session_log(db)?;
+    db.execute("DELETE FROM temp.session_audit_ids;", ())?;
+
+    Ok(())
+}
+
+fn ensure_session_log(db: &Connection) -> Result<()> {
+    db.execute_batch(
+        "CREATE TEMP TABLE IF NOT EXISTS session_audit_ids (
+            entity_audit_log_id INTEGER PRIMARY KEY
+        );",
+    )
+}
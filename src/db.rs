@@ -0,0 +1,392 @@
+//! The generic row layer every query goes through: rows come back as loosely typed
+//! `SqlData` fields so callers can read any table without declaring its shape first.
+//! Types implementing `FromRecord` can then be decoded out of a row by column name.
+//! BLOBs too large to travel with their row are reached through `BlobRef` instead.
+
+use rusqlite::*;
+use std::fmt;
+use std::io::{Read, Write};
+
+#[derive(Default, Clone)]
+pub struct RecordSet {
+    pub records: Vec<Record>,
+    pub headers: Headers,
+}
+
+#[derive(Default, Clone)]
+pub struct Record {
+    pub fields: Vec<SqlData>,
+}
+
+impl Record {
+    pub fn new() -> Self {
+        Self { fields: Vec::new() }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct Headers {
+    pub column_names: Vec<String>,
+    pub column_count: usize,
+}
+
+#[derive(Clone)]
+pub enum SqlData {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl fmt::Display for SqlData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SqlData::Null => Ok(()),
+            SqlData::Integer(z) => write!(f, "{}", z),
+            SqlData::Real(z) => write!(f, "{}", z),
+            SqlData::Text(z) => f.write_str(z),
+            SqlData::Blob(z) => f.write_str(&blob_summary(z)),
+        }
+    }
+}
+
+impl SqlData {
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            SqlData::Integer(i) => Some(*i),
+            SqlData::Real(r) => Some(*r as i64),
+            SqlData::Text(t) => t.trim().parse::<i64>().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            SqlData::Integer(i) => Some(*i as f64),
+            SqlData::Real(r) => Some(*r),
+            SqlData::Text(t) => t.trim().parse::<f64>().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            SqlData::Null | SqlData::Blob(_) => None,
+            _ => Some(self.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowError {
+    // the statement failed to prepare or a row failed to step
+    Sql(String),
+    MissingColumn(String),
+    WrongType {
+        column: String,
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RowError::Sql(e) => write!(f, "sql error: {}", e),
+            RowError::MissingColumn(c) => write!(f, "column '{}' is not in the result", c),
+            RowError::WrongType { column, expected } => {
+                write!(f, "column '{}' is not {}", column, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RowError {}
+
+impl From<Error> for RowError {
+    fn from(e: Error) -> Self {
+        RowError::Sql(e.to_string())
+    }
+}
+
+// one record paired with the headers of the set it came from, so fields can be read by name
+pub struct Row<'a> {
+    headers: &'a Headers,
+    record: &'a Record,
+}
+
+impl<'a> Row<'a> {
+    pub fn new(headers: &'a Headers, record: &'a Record) -> Self {
+        Self { headers, record }
+    }
+
+    pub fn get(&self, column: &str) -> Result<&'a SqlData, RowError> {
+        self.headers
+            .column_names
+            .iter()
+            .position(|c| c == column)
+            .and_then(|index| self.record.fields.get(index))
+            .ok_or_else(|| RowError::MissingColumn(String::from(column)))
+    }
+
+    pub fn i64(&self, column: &str) -> Result<i64, RowError> {
+        self.get(column)?
+            .as_i64()
+            .ok_or_else(|| wrong_type(column, "an integer"))
+    }
+
+    pub fn f64(&self, column: &str) -> Result<f64, RowError> {
+        self.get(column)?
+            .as_f64()
+            .ok_or_else(|| wrong_type(column, "a number"))
+    }
+
+    pub fn text(&self, column: &str) -> Result<String, RowError> {
+        self.get(column)?
+            .as_text()
+            .ok_or_else(|| wrong_type(column, "text"))
+    }
+
+    pub fn opt_i64(&self, column: &str) -> Result<Option<i64>, RowError> {
+        match self.get(column)? {
+            SqlData::Null => Ok(None),
+            _ => self.i64(column).map(Some),
+        }
+    }
+
+    pub fn opt_f64(&self, column: &str) -> Result<Option<f64>, RowError> {
+        match self.get(column)? {
+            SqlData::Null => Ok(None),
+            _ => self.f64(column).map(Some),
+        }
+    }
+
+    pub fn opt_text(&self, column: &str) -> Result<Option<String>, RowError> {
+        match self.get(column)? {
+            SqlData::Null => Ok(None),
+            _ => self.text(column).map(Some),
+        }
+    }
+}
+
+fn wrong_type(column: &str, expected: &'static str) -> RowError {
+    RowError::WrongType {
+        column: String::from(column),
+        expected,
+    }
+}
+
+pub trait FromRecord: Sized {
+    fn from_row(row: &Row) -> Result<Self, RowError>;
+}
+
+impl RecordSet {
+    pub fn decode<T: FromRecord>(&self) -> Result<Vec<T>, RowError> {
+        self.records
+            .iter()
+            .map(|record| T::from_row(&Row::new(&self.headers, record)))
+            .collect()
+    }
+}
+
+// query and decode every row into T, failing on the first row that doesn't fit
+pub fn query_as<T: FromRecord>(
+    sqlite_connection: &Connection,
+    query_str: &str,
+    params: &[(&str, &dyn ToSql)],
+) -> Result<Vec<T>, RowError> {
+    try_query(sqlite_connection, query_str, params)?.decode()
+}
+
+// the first row decoded into T, or nothing when the query matched no rows
+pub fn query_one_as<T: FromRecord>(
+    sqlite_connection: &Connection,
+    query_str: &str,
+    params: &[(&str, &dyn ToSql)],
+) -> Result<Option<T>, RowError> {
+    Ok(query_as(sqlite_connection, query_str, params)?
+        .into_iter()
+        .next())
+}
+
+pub fn query(
+    sqlite_connection: &Connection,
+    query_str: &str,
+    params: &[(&str, &dyn ToSql)],
+) -> RecordSet {
+    // callers of the untyped layer treat a failed query as an empty one
+    try_query(sqlite_connection, query_str, params).unwrap_or_default()
+}
+
+pub fn try_query(
+    sqlite_connection: &Connection,
+    query_str: &str,
+    params: &[(&str, &dyn ToSql)],
+) -> Result<RecordSet, RowError> {
+    let mut rs: RecordSet = RecordSet::default();
+
+    let mut stmt: CachedStatement = sqlite_connection.prepare_cached(query_str)?;
+    let col_count: usize = stmt.column_count();
+
+    rs.headers.column_count = col_count;
+    for x in stmt.column_names() {
+        rs.headers.column_names.push(String::from(x));
+    }
+
+    let mut rows: Rows = stmt.query(params)?;
+    while let Some(row) = rows.next()? {
+        let mut new_row: Record = Record::new();
+        for ind in 0..col_count {
+            // converting each SQLite value to its SqlData equivalent
+            let field: SqlData = match row.get::<usize, types::Value>(ind)? {
+                types::Value::Null => SqlData::Null,
+                types::Value::Integer(v_i64) => SqlData::Integer(v_i64),
+                types::Value::Real(v_f64) => SqlData::Real(v_f64),
+                types::Value::Text(v_string) => SqlData::Text(v_string),
+                types::Value::Blob(v_vec_u8) => SqlData::Blob(v_vec_u8),
+            };
+            new_row.fields.push(field);
+        }
+        rs.records.push(new_row);
+    }
+
+    Ok(rs)
+}
+
+// runs f inside a savepoint, so everything it writes lands together or not at all. unlike a
+// transaction a savepoint nests, so f can run on its own or as part of a larger write
+pub fn savepoint<T>(sqlite_connection: &Connection, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    sqlite_connection.execute_batch("SAVEPOINT editor_write;")?;
+    match f() {
+        Ok(v) => {
+            sqlite_connection.execute_batch("RELEASE editor_write;")?;
+            Ok(v)
+        }
+        Err(e) => {
+            // rolling back to a savepoint leaves it open, releasing it closes it for good
+            let _ =
+                sqlite_connection.execute_batch("ROLLBACK TO editor_write; RELEASE editor_write;");
+            Err(e)
+        }
+    }
+}
+
+// a read made as part of a write fails that write. rusqlite has no variant for a row that
+// doesn't decode, this one shows the RowError's message as it is
+pub fn read_failed(e: RowError) -> Error {
+    Error::ToSqlConversionFailure(Box::new(e))
+}
+
+pub fn blob_summary(bytes: &[u8]) -> String {
+    summarize_blob(bytes.len(), bytes)
+}
+
+fn summarize_blob(len: usize, head: &[u8]) -> String {
+    let hex: Vec<String> = head.iter().take(16).map(|b| format!("{:02x}", b)).collect();
+    let more: &str = match len > 16 {
+        true => " ...",
+        false => "",
+    };
+
+    String::from_iter([
+        "<",
+        &len.to_string()[..],
+        " bytes: ",
+        &hex.join(" ")[..],
+        more,
+        ">",
+    ])
+}
+
+// a BLOB left where it is in the database. a row carrying one as `SqlData::Blob` brings every
+// byte along, this is read through incremental blob IO and only as far as the caller asks
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlobRef {
+    pub table: String,
+    pub column: String,
+    pub rowid: i64,
+    pub len: usize,
+}
+
+impl BlobRef {
+    pub fn read(&self, sqlite_connection: &Connection) -> Result<Vec<u8>> {
+        self.read_head(sqlite_connection, self.len)
+    }
+
+    // the first `n` bytes, or all of them when the blob is shorter
+    pub fn read_head(&self, sqlite_connection: &Connection, n: usize) -> Result<Vec<u8>> {
+        let mut blob = sqlite_connection.blob_open(
+            DatabaseName::Main,
+            &self.table[..],
+            &self.column[..],
+            self.rowid,
+            true,
+        )?;
+        let mut bytes: Vec<u8> = vec![0; n.min(blob.len())];
+        blob.read_exact(&mut bytes).map_err(blob_io_failed)?;
+
+        Ok(bytes)
+    }
+
+    // same text as `blob_summary`, without reading more than it shows
+    pub fn summary(&self, sqlite_connection: &Connection) -> Result<String> {
+        let head: Vec<u8> = self.read_head(sqlite_connection, 16)?;
+
+        Ok(summarize_blob(self.len, &head))
+    }
+}
+
+// nothing when the row is missing or its value is NULL. length() of a BLOB is answered from
+// the record header, so the bytes themselves aren't read to get it
+pub fn fetch_blob_ref(
+    sqlite_connection: &Connection,
+    table: &str,
+    column: &str,
+    rowid: i64,
+) -> Result<Option<BlobRef>> {
+    let sql: String = String::from_iter([
+        "SELECT length(\"",
+        column,
+        "\") FROM \"",
+        table,
+        "\" WHERE rowid = ?1 AND typeof(\"",
+        column,
+        "\") = 'blob';",
+    ]);
+    let len: Option<i64> = sqlite_connection
+        .query_row(&sql[..], (rowid,), |row| row.get(0))
+        .optional()?;
+
+    Ok(len.map(|len| BlobRef {
+        table: String::from(table),
+        column: String::from(column),
+        rowid,
+        len: len as usize,
+    }))
+}
+
+pub fn write_blob(
+    sqlite_connection: &Connection,
+    table: &str,
+    column: &str,
+    rowid: i64,
+    bytes: &[u8],
+) -> Result<()> {
+    // reserve the space with zeroblob, then stream the bytes into it
+    let sql: String = String::from_iter([
+        "UPDATE \"",
+        table,
+        "\" SET \"",
+        column,
+        "\" = zeroblob(?1) WHERE rowid = ?2;",
+    ]);
+    sqlite_connection.execute(&sql[..], (bytes.len() as i64, rowid))?;
+
+    let mut blob = sqlite_connection.blob_open(DatabaseName::Main, table, column, rowid, false)?;
+    blob.write_all(bytes).map_err(blob_io_failed)
+}
+
+fn blob_io_failed(e: std::io::Error) -> Error {
+    // rusqlite has no variant for a failed blob stream, this one shows the io error as it is
+    Error::ToSqlConversionFailure(Box::new(e))
+}
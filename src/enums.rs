@@ -0,0 +1,66 @@
+//! Builds the GDScript enums file from the lookup tables listed in the `enums` table.
+
+use crate::db::{query, RecordSet, SqlData};
+use rusqlite::*;
+
+pub fn generate_enums(db: &Connection) -> String {
+    let mut enum_package: String = String::new();
+
+    let rs_enum_tables: RecordSet = fetch_enum_tables(db);
+    for mut enum_rows in rs_enum_tables.records {
+        if let Some(f) = enum_rows.fields.pop() {
+            let rs_enum_data: RecordSet = fetch_enum_values_from_table(db, f.to_string());
+            // begin adding new enum type
+            enum_package.push_str("\nenum ");
+            enum_package.push_str(&enum_name(&f.to_string())[..]);
+            enum_package.push_str("{\n");
+
+            for row in rs_enum_data.records {
+                let mut fields: Vec<SqlData> = row.fields;
+                let type_name: String = fields.pop().unwrap().to_string();
+                let type_value: String = fields.pop().unwrap().to_string();
+                enum_package.push('\t');
+                enum_package.push_str(&type_name[..]);
+                enum_package.push_str(" = ");
+                enum_package.push_str(&type_value[..]);
+                enum_package.push_str(",\n");
+            }
+            enum_package.push_str("}\n");
+        }
+    }
+
+    enum_package
+}
+
+pub fn enum_name(table: &str) -> String {
+    // "entity_actions" -> Vec["entity","actions"]
+    let enum_lowercase: Vec<String> = table
+        .split("_")
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+    let mut enum_capitalized: Vec<String> = Vec::new();
+
+    // "entity" -> "Entity"
+    for x in &enum_lowercase {
+        let first: String = x[0..1].to_uppercase();
+        let rest: String = String::from(&x[1..]);
+        enum_capitalized.push(Vec::from([first, rest]).join(""));
+    }
+
+    enum_capitalized.join("")
+}
+
+pub fn fetch_enum_tables(db: &Connection) -> RecordSet {
+    let sql: String = String::from("SELECT 'e'.'table' FROM 'enums' as 'e';");
+    query(db, &sql[..], &[])
+}
+
+pub fn fetch_enum_values_from_table(db: &Connection, t: String) -> RecordSet {
+    let sql: String = Vec::from(&[
+        "SELECT 'x'.'_rowid_', 'x'.'name' FROM '",
+        &t[..],
+        "' as 'x' ORDER BY 'x'.'_rowid_' ASC;",
+    ])
+    .join("");
+    query(db, &sql[..], &[])
+}
@@ -0,0 +1,23 @@
+//! In-memory cold storage databases for tests and headless tools. Nothing here touches the
+//! file system, so every caller gets its own fresh database.
+
+use rusqlite::*;
+
+pub const SCHEMA: &str = include_str!("../test_data/schema.sql");
+pub const FIXTURES: &str = include_str!("../test_data/fixtures.sql");
+
+// the game's tables with no rows in them
+pub fn empty_db() -> Result<Connection, Error> {
+    let db: Connection = Connection::open_in_memory()?;
+    db.execute_batch(SCHEMA)?;
+
+    Ok(db)
+}
+
+// the game's tables seeded with the rows in test_data/fixtures.sql
+pub fn fixture_db() -> Result<Connection, Error> {
+    let db: Connection = empty_db()?;
+    db.execute_batch(FIXTURES)?;
+
+    Ok(db)
+}
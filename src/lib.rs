@@ -0,0 +1,19 @@
+//! Shared data access for the entity cold storage database.
+//!
+//! The editor binary is a GUI on top of this crate. Servers and tools can open the same
+//! database through `Repository` without pulling in any of the FLTK code.
+
+pub mod audit;
+pub mod db;
+pub mod enums;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+pub mod models;
+pub mod repository;
+pub mod schema;
+
+pub use db::{query, query_as, BlobRef, FromRecord, Record, RecordSet, Row, RowError, SqlData};
+pub use models::{
+    ActionRequirement, AttributeValue, CompositionLayer, EntityAction, EntityAttribute, EntityBase,
+};
+pub use repository::Repository;
@@ -1,13 +1,15 @@
 use fltk::{
-    button::{Button, CheckButton},
-    draw,
-    enums::{Color, Event, FrameType, Shortcut},
+    browser::{HoldBrowser, MultiBrowser},
+    button::{Button, CheckButton, RadioRoundButton},
+    dialog, draw,
+    enums::{Align, CallbackTrigger, Color, Cursor, Event, FrameType, Key, Shortcut},
     frame::Frame,
-    group::{Pack, PackType, Scroll},
-    input::Input,
-    menu::{MenuBar, MenuFlag},
+    group::{Flex, Pack, PackType, Scroll, Tile},
+    image::{JpegImage, PngImage},
+    input::{Input, IntInput, MultilineInput},
+    menu::{Choice, MenuBar, MenuFlag},
     prelude::{WidgetBase, *},
-    tree::{Tree, TreeItem},
+    tree::{Tree, TreeItem, TreeReason},
     widget::Widget,
     window::{DoubleWindow, Window},
     {
@@ -16,28 +18,177 @@ use fltk::{
     },
 };
 
+use entity_creator::audit::{
+    forget_session_audit_ids, record_audit, session_audit_ids_after, AuditPackage,
+};
+use entity_creator::db::{
+    fetch_blob_ref, query_as, query_one_as, read_failed, savepoint, write_blob, BlobRef, RowError,
+};
+use entity_creator::enums::generate_enums;
+use entity_creator::models::{
+    ActionCost, ActionLayer, ActionSummary, AttributeValue, AuditEntry, Avatar, CallableLocation,
+    CompositionLayer, CompositionLayerRule, EntityAction, EntityBase, EntityCoreType, EntityState,
+    IdentityName, IdentityType, IntValue, LayerResource, LayerUsage, LinkOption,
+    RequirementDefinition, RuleViolation, StateModifier, TableColumn, Template, TextValue,
+};
+use entity_creator::repository::Repository;
+use entity_creator::schema::{ensure_app_schema, ENTITY_CHILD_TABLES, VERSIONED_TABLES};
 use native_dialog::FileDialog;
 use rusqlite::*;
 use std::env;
 use std::env::current_dir;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SETTINGS_FILE: &str = "entity_creator.cfg";
+// the tree/canvas splitter can't be dragged closer to either edge than these
+const MIN_TREE_WIDTH: i32 = 150;
+const MIN_CANVAS_WIDTH: i32 = 400;
+const MIN_WINDOW_SIZE: (i32, i32) = (800, 500);
+// how many databases File/Open Recent remembers
+const RECENT_FILES_LIMIT: usize = 8;
+// search results are added to the tree this many at a time
+const ENTITY_PAGE_SIZE: usize = 200;
+const LOAD_MORE_LABEL: &str = "Load more...";
+// how often cold storage is checked for commits made by other programs
+const DB_WATCH_INTERVAL_SECS: f64 = 2.0;
+// how long a statement waits for another connection's lock before it gives up as busy
+const DB_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+// blobs larger than this aren't decoded for the avatar preview, they're summarized instead
+const MAX_PREVIEW_BLOB_BYTES: usize = 8 * 1024 * 1024;
 
 struct AppContext {
     fltk_app: fltk::app::App,
     db: Connection,
+    db_path: PathBuf,
+    settings: Settings,
+    backup_timer: Option<app::TimeoutHandle>,
+    selected_entities: Vec<String>,
+    selection_anchor: Option<String>,
     sender: Sender<Message>,
     receiver: Receiver<Message>,
+    // None when the worker thread couldn't be started, jobs then run inline
+    db_jobs: Option<mpsc::Sender<DbJob>>,
+    pending_jobs: usize,
+    // bumped whenever the tree's category items are deleted, search results sent for an
+    // older generation point at items that are gone and are dropped
+    tree_generation: u64,
+    watch: DbWatch,
+    history: EditHistory,
+}
+
+// audit entries written by this session, grouped by the message that wrote them, so
+// Edit/Undo and Edit/Redo can revert a whole step at a time
+struct EditHistory {
+    last_audit_id: i64,
+    undo: Vec<Vec<String>>,
+    redo: Vec<Vec<String>>,
+}
+
+// what the editor last saw of cold storage, so another program's commits can be told
+// apart from its own writes
+struct DbWatch {
+    timer: Option<app::TimeoutHandle>,
+    data_version: i64,
+    // the open entity's compare rows as last read, to name what changed underneath it
+    open_entity: Option<(String, Vec<(String, Vec<String>)>)>,
+}
+
+// reads that can take a while, answered by the worker thread with a Message
+#[derive(Clone)]
+enum DbJob {
+    // category tree item, its core type name, the entity id typed into it, how many
+    // results are already listed and the tree generation the item belongs to
+    SearchEntities(TreeItem, String, String, usize, u64),
+    CompareEntities(Vec<String>),
+    Validate,
+    // the entities to write, every entity when empty, and the file to write them to
+    Export(Vec<String>, PathBuf),
 }
 
 #[derive(Clone)]
 enum Message {
     SearchEntities(TreeItem),
+    LoadMoreEntities(TreeItem),
     ClearEntities(TreeItem),
     EntityFrameClicked(String),
+    EntityFrameToggled(String),
+    EntityFrameRangeSelected(String),
     UpdateCompLayers(CLPackage),
-    NewEntity(String),
+    NewEntity(NEPackage),
+    NewEntityFromSearch,
+    DuplicateEntity,
+    FocusSearch,
+    DeleteEntity,
+    SelectAdjacentResult(i32),
     UpdateEntity,
+    SaveEntityHeader(NPackage, i64),
+    OpenDatabase,
+    OpenRecentDatabase(String),
+    BackupNow,
+    Quit,
+    Undo,
+    Redo,
+    Regen,
+    ImportEntities,
+    ExportEntities,
+    OpenSettings,
+    SaveSettings(Settings),
+    BackupTick,
+    DbWatchTick,
+    OpenRestoreWindow,
+    RestoreBackup(String),
+    // the audit entry, and the values already picked for entries that moved on since
+    RevertAuditEntry(String, Vec<(String, Option<String>)>),
+    ResolveHistoryStep(bool, Vec<(String, Option<String>)>),
+    OpenCompareWindow,
+    CompareEntities(Vec<String>),
+    OpenTemplatesWindow,
+    CreateTemplate(String, String),
+    DeleteTemplate(String),
+    BulkUpdateCompLayers(BCLPackage),
+    BulkUpdateAttribute(BAPackage),
+    AssignAvatar(String, Option<String>),
+    NewAvatar(String),
+    OpenStatesWindow,
+    SelectState(String),
+    CreateState(String),
+    SaveStateModifier(SMPackage),
+    DeleteStateModifier(String, String, Option<i64>),
+    OpenIdentitiesWindow,
+    SelectIdentityType(String),
+    CreateIdentityType(String),
+    AddIdentityNames(String, Vec<String>),
+    DeleteIdentityNames(String, Vec<String>),
+    AssignIdentityType(String, Option<String>),
+    OpenActionsWindow,
+    SelectAction(String),
+    SaveActionRequirement(RQPackage),
+    DeleteActionRequirement(String, String, Option<i64>),
+    UpdateActionLink(ALPackage),
+    OpenResourcePathsWindow,
+    CheckResourcePaths,
+    SaveCallablePath(String, String, Option<i64>),
+    SaveCompLayerResource(String, String, Option<i64>),
+    OpenCompLayersWindow,
+    CreateCompLayer(String, String),
+    SaveCompLayer(String, String, String, Option<i64>),
+    MoveCompLayer(String, i32, Option<i64>),
+    DeleteCompLayer(String, Option<i64>),
+    AddCompLayerRule(String, String, String),
+    DeleteCompLayerRule(String),
+    OpenValidationWindow,
+    SyncActionLayers(String),
+    EntitySearchDone(TreeItem, Vec<EntityBase>, usize, u64),
+    CompareRowsReady(Vec<String>, Vec<(String, Vec<String>)>),
+    ValidationDone(Vec<String>),
+    ExportDone(PathBuf, usize),
+    // a job that failed, with the reason to show
+    DbJobFailed(String),
+    DbWorkerFailed(String),
 }
 
 impl AppContext {
@@ -48,61 +199,347 @@ impl AppContext {
         };
 
         let (a, b) = channel::<Message>();
+        let settings: Settings = Settings::load();
+        let db: Connection = open_connection(&db_path).unwrap();
+
+        // snapshot cold storage before this session gets a chance to write to it
+        let _ = backup_cold_storage(&db, &db_path, settings.backup_retention);
+        // editing against half a schema would skip the checks and history the rest relies on
+        if let Err(e) = ensure_app_schema(&db) {
+            alert_schema_error(e, "The editor will close.");
+            std::process::exit(1);
+        }
+        let db_jobs: Option<mpsc::Sender<DbJob>> = spawn_db_worker(&db_path, a.clone());
+        let watch: DbWatch = DbWatch {
+            timer: None,
+            // unreadable, the first tick reloads everything
+            data_version: fetch_data_version(&db).unwrap_or_default(),
+            open_entity: None,
+        };
+        let history: EditHistory = EditHistory {
+            last_audit_id: 0,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        };
 
         Self {
             fltk_app: app::App::default(),
-            db: Connection::open(db_path).unwrap(),
+            db,
+            db_path,
+            settings,
+            backup_timer: None,
+            selected_entities: Vec::new(),
+            selection_anchor: None,
             sender: a,
             receiver: b,
+            db_jobs,
+            pending_jobs: 0,
+            tree_generation: 0,
+            watch,
+            history,
         }
     }
 
     fn construct(&mut self) -> () {
-        // create main window
-        let main_window: DoubleWindow = Window::default()
-            .with_size(1280, 720)
-            .center_screen()
+        apply_ui_scale(self.settings.ui_scale);
+
+        // create main window where it was last closed, sizes are in FLTK's scaled units
+        let (width, height): (i32, i32) = (
+            self.settings.window_width.max(MIN_WINDOW_SIZE.0),
+            self.settings.window_height.max(MIN_WINDOW_SIZE.1),
+        );
+        let mut main_window: DoubleWindow = Window::default()
+            .with_size(width, height)
             .with_label("Entity Content Creator")
             .with_id("main_window");
+        match self
+            .settings
+            .window_position
+            .filter(|(x, y)| position_on_screen(*x, *y))
+        {
+            Some((x, y)) => main_window.set_pos(x, y),
+            None => main_window = main_window.center_screen(),
+        }
+        main_window.size_range(MIN_WINDOW_SIZE.0, MIN_WINDOW_SIZE.1, 0, 0);
+
+        let mut main_flex: Flex = Flex::default_fill().column();
+        main_flex.set_margin(0);
+        main_flex.set_pad(5);
 
+        let mut top_bar: Flex = Flex::default().row();
+        let mut menu: MenuBar = MenuBar::default().with_id("main_menu");
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "File/Open...",
+            Shortcut::Ctrl | 'o',
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::OpenDatabase);
+            },
+        );
+        // filled from the settings by fill_recent_files_menu
+        menu.add(
+            "File/Open Recent",
+            Shortcut::None,
+            MenuFlag::Submenu,
+            |_| {},
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "File/Backup Now",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::BackupNow);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "File/Restore from Backup...",
+            Shortcut::None,
+            MenuFlag::MenuDivider,
+            move |_| {
+                sender_clone.send(Message::OpenRestoreWindow);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "File/Quit",
+            Shortcut::Ctrl | 'q',
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::Quit);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Edit/Undo",
+            Shortcut::Ctrl | 'z',
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::Undo);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Edit/Redo",
+            Shortcut::Ctrl | 'y',
+            MenuFlag::MenuDivider,
+            move |_| {
+                sender_clone.send(Message::Redo);
+            },
+        );
         let sender_clone: Sender<Message> = self.sender.clone();
-        let mut menu: MenuBar = MenuBar::default().with_size(1280, 35);
-        menu.add("Regen", Shortcut::None, MenuFlag::Normal, menu_regen);
-        menu.add("Settings", Shortcut::None, MenuFlag::Normal, menu_settings);
         menu.add(
-            "Update Entity",
+            "Edit/Settings...",
             Shortcut::None,
             MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::OpenSettings);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Entity/New",
+            Shortcut::Ctrl | 'n',
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::NewEntityFromSearch);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Entity/Duplicate...",
+            Shortcut::Ctrl | 'd',
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::DuplicateEntity);
+            },
+        );
+        // ctrl so a stray delete while the tree has focus doesn't start one
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Entity/Delete",
+            Shortcut::Ctrl | Key::Delete,
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::DeleteEntity);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Entity/Update",
+            Shortcut::Ctrl | 's',
+            MenuFlag::MenuDivider,
             move |_| {
                 sender_clone.send(Message::UpdateEntity);
             },
         );
-
-        let mut main_pack: Pack = Pack::default_fill()
-            .with_type(PackType::Horizontal)
-            .below_of(&menu, 5)
-            .with_id("main_pack");
-        main_pack.set_spacing(5);
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Entity/Compare...",
+            Shortcut::None,
+            MenuFlag::MenuDivider,
+            move |_| {
+                sender_clone.send(Message::OpenCompareWindow);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Entity/Find",
+            Shortcut::Ctrl | 'f',
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::FocusSearch);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Entity/Next Result",
+            Shortcut::from_key(Key::F3),
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::SelectAdjacentResult(1));
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Entity/Previous Result",
+            Shortcut::Shift | Key::F3,
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::SelectAdjacentResult(-1));
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Tools/Regen",
+            Shortcut::Ctrl | 'r',
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::Regen);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Tools/Validate",
+            Shortcut::None,
+            MenuFlag::MenuDivider,
+            move |_| {
+                sender_clone.send(Message::OpenValidationWindow);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Tools/Import Entities...",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::ImportEntities);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Tools/Export Entities...",
+            Shortcut::None,
+            MenuFlag::MenuDivider,
+            move |_| {
+                sender_clone.send(Message::ExportEntities);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Tools/Templates",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::OpenTemplatesWindow);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Tools/States",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::OpenStatesWindow);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Tools/Identities",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::OpenIdentitiesWindow);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Tools/Actions",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::OpenActionsWindow);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Tools/Resource Paths",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::OpenResourcePathsWindow);
+            },
+        );
+        let sender_clone: Sender<Message> = self.sender.clone();
+        menu.add(
+            "Tools/Composition Layers",
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                sender_clone.send(Message::OpenCompLayersWindow);
+            },
+        );
+        fill_recent_files_menu(&self.settings, self.sender.clone());
+
+        // shows while the worker thread has jobs outstanding
+        let mut busy_indicator: Frame = Frame::default().with_id("busy_indicator");
+        busy_indicator.set_frame(FrameType::FlatBox);
+        busy_indicator.set_color(menu.color());
+        top_bar.fixed(&busy_indicator, 150);
+        top_bar.end();
+        main_flex.fixed(&top_bar, 35);
+
+        // tree on the left and canvas on the right, split by a draggable divider
+        let splitter_y: i32 = 35 + 5;
+        let mut splitter: Tile = Tile::new(0, splitter_y, width, height - splitter_y, "");
+        let tree_width: i32 = self
+            .settings
+            .tree_width
+            .clamp(MIN_TREE_WIDTH, width - MIN_CANVAS_WIDTH);
 
         // create a tree on the left to allow selecting creation templates
-        let mut tree_object: Tree = Tree::default()
-            .with_size(300, 0)
-            .with_id("main_window_tree");
+        let mut tree_object: Tree =
+            Tree::new(0, splitter_y, tree_width, splitter.h(), "").with_id("main_window_tree");
 
         tree_object.set_show_root(false);
+        let sender_clone: Sender<Message> = self.sender.clone();
+        tree_object.set_callback(move |t| {
+            on_tree_item_selected(t, &sender_clone);
+        });
         // requires explicit 'end()' call to any 'group' types so we don't nest within this object
         tree_object.end();
 
         // create 2nd window that houses the dynamic rebuildable widgets - right side of GUI
-        let entity_canvas_area: Scroll = Scroll::default()
-            .with_size(main_pack.width() - tree_object.width() - 5, 0)
-            .with_id("sub_pack_scroll");
+        // its contents are laid out from 0,0 and carried along when it's moved into place
+        let mut entity_canvas_area: Scroll =
+            Scroll::new(0, 0, width - tree_width, splitter.h(), "").with_id("sub_pack_scroll");
 
-        let _ = self.load_items_into_tree(fetch_entity_categories(&self.db));
-        build_out_creation_categories(self.sender.clone());
+        let _ = self.load_items_into_tree(fetch_entity_category_names(&self.db));
+        build_out_creation_categories(&self.db, self.sender.clone());
 
-        let hdrs: RecordSet = fetch_pragma_entity_base_defs(&self.db);
-        let mut comps: RecordSet = fetch_entity_comp_layers_general(&self.db);
+        let columns: Vec<TableColumn> = rows_or_report(fetch_pragma_entity_base_defs(&self.db));
 
         // begin building the scroll from scratch
         entity_canvas_area.begin();
@@ -119,16 +556,8 @@ impl AppContext {
         // add to the sub pack "header information" and "comp layers"
         header_info_pack.begin();
 
-        let mut header_index: usize = 0;
-        for header in hdrs.headers.column_names.iter() {
-            if &header[..] == "name" {
-                break;
-            }
-            header_index += 1;
-        }
-
-        for record in hdrs.records.iter() {
-            let header: String = record.fields[header_index].to_string();
+        for column in columns.iter() {
+            let header: &String = &column.name;
             let mut inner_pack: Pack = Pack::default()
                 .with_size(0, 35)
                 .with_type(PackType::Horizontal);
@@ -139,9 +568,16 @@ impl AppContext {
             let mut f: Frame = Frame::default().with_size(150, 0).with_label(&header[..]);
             f.set_frame(FrameType::EngravedBox);
 
-            let _: Input = Input::default()
+            let mut input: Input = Input::default()
                 .with_size(header_info_pack.width(), 0)
                 .with_id(&header[..]);
+            // kept by the db, shown so a save knows which version it was edited from
+            if header == "row_version" {
+                input.set_readonly(true);
+                input.set_color(Color::Light2);
+                // nothing to type here, so tab goes straight past it
+                input.visible_focus(false);
+            }
             inner_pack.end();
         }
         header_info_pack.end();
@@ -153,7 +589,7 @@ impl AppContext {
         let mut comp_headers_pack: Pack = Pack::new(
             entity_canvas_area.x(),
             header_info_pack.y() + 5,
-            300,
+            500,
             80,
             "",
         )
@@ -178,6 +614,8 @@ impl AppContext {
             f.set_frame(FrameType::EngravedBox);
             let mut f: Frame = Frame::default().with_size(50, 0).with_label("Activate");
             f.set_frame(FrameType::EngravedBox);
+            let mut f: Frame = Frame::default().with_size(200, 0).with_label("Required By");
+            f.set_frame(FrameType::EngravedBox);
             inner_pack.end();
         }
 
@@ -187,7 +625,7 @@ impl AppContext {
         let mut comp_pack: Pack = Pack::new(
             entity_canvas_area.x(),
             comp_headers_pack.y() + 5,
-            300,
+            500,
             700,
             "",
         )
@@ -195,922 +633,8896 @@ impl AppContext {
         .with_id("comp_pack")
         .below_of(&comp_headers_pack, 5);
         comp_pack.set_spacing(5);
-        comp_pack.begin();
-
-        // getting all composition layer names for layout
-        for record in comps.records.iter_mut() {
-            // composition layer info
-            let comp_name: String = match record.fields.pop() {
-                Some(s) => s.to_string(),
-                None => String::new(),
-            };
-            let comp_id: String = match record.fields.pop() {
-                Some(i) => i.to_string(),
-                None => String::new(),
-            };
-            // and entity_base_id to be passed into checkbuttons
-
-            let mut inner_pack: Pack = Pack::default().with_size(0, 35).with_id(&comp_id[..]);
-            inner_pack.set_type(PackType::Horizontal);
+        comp_pack.end();
+        fill_comp_pack(&self.db, self.sender.clone());
+        // Done filling out comp layers
 
-            inner_pack.begin();
-            let mut f: Frame = Frame::default().with_size(50, 0).with_label(&comp_id[..]);
-            f.set_frame(FrameType::EngravedBox);
+        // sections that sit beside the composition layers, stacked top to bottom
+        let mut side_pack: Pack = Pack::new(
+            comp_headers_pack.x() + comp_headers_pack.width() + 10,
+            comp_headers_pack.y(),
+            600,
+            1020,
+            "",
+        )
+        .with_type(PackType::Vertical)
+        .with_id("canvas_side_pack");
+        side_pack.set_spacing(5);
+        side_pack.begin();
 
-            let mut f: Frame = Frame::default()
-                .with_size(200, 0)
-                .with_label(&comp_name[..]);
-            f.set_frame(FrameType::EngravedBox);
+        build_avatar_section(self.sender.clone());
+        build_identity_section(self.sender.clone());
+        build_action_costs_section(self.sender.clone());
+        build_template_section();
+        build_history_section(self.sender.clone());
 
-            let sender_clone: Sender<Message> = self.sender.clone();
-            let mut c: CheckButton = CheckButton::default().with_size(50, 0);
-            c.handle(move |b, event| {
-                match event {
-                    Event::Released => {
-                        let eid: String =
-                            widget_from_id::<Input>("entity_base_id").unwrap().value();
-                        let c: CLPackage = CLPackage {
-                            cb_state: b.is_checked(),
-                            comp_id: comp_id.clone(),
-                            entity_id: eid,
-                        };
-                        // checkbox pushed, update the entity comp layer listings in coldstorage
-                        sender_clone.send(Message::UpdateCompLayers(c));
-                        // inform FLTK that we handled the event as 'TRUE'
-                        true
-                    }
-                    _ => false,
-                }
-            });
-            inner_pack.end();
-        }
-        comp_pack.end();
-        comp_pack.redraw();
-        // Done filling out comp layers
+        side_pack.end();
 
         entity_canvas_area.end();
+        entity_canvas_area.resize(tree_width, splitter_y, width - tree_width, splitter.h());
+        entity_canvas_area.resize_callback(|s, _, _, _, _| {
+            fit_canvas_to_width(s);
+        });
+        fit_canvas_to_width(&entity_canvas_area);
+
+        // the window's extra width goes to the canvas, and the divider stays inside this box
+        let splitter_limits: Frame = Frame::new(
+            MIN_TREE_WIDTH,
+            splitter_y,
+            width - MIN_TREE_WIDTH - MIN_CANVAS_WIDTH,
+            splitter.h(),
+            "",
+        );
+        splitter.resizable(&splitter_limits);
+        splitter.end();
+        main_flex.end();
 
         // done adding to the main window
+        main_window.resizable(&main_flex);
         main_window.end();
+
+        self.schedule_backups();
+        self.watch_cold_storage();
     }
 
-    fn event_loop(&mut self) -> Result<(), ()> {
-        while self.fltk_app.wait() {
-            match self.receiver.recv() {
-                Some(Message::SearchEntities(t)) => {
-                    fetch_and_fill_entity_search(&self, t);
-                }
-                Some(Message::ClearEntities(mut t)) => {
-                    clear_entities_from_tree(&mut t);
-                }
-                Some(Message::EntityFrameClicked(eid)) => {
-                    fill_scroll_gui(&eid, &self.db);
-                }
-                Some(Message::UpdateCompLayers(c)) => {
-                    update_entity_comp_layers(&self.db, c);
-                }
-                Some(Message::NewEntity(eid)) => {
-                    new_entity(&self.db, &eid);
-                }
-                Some(Message::UpdateEntity) => {
-                    update_entity(&self.db);
-                }
-                None => {}
-            }
-        }
+    fn submit_db_job(&mut self, job: DbJob) -> () {
+        self.pending_jobs += 1;
+        show_busy(self.pending_jobs);
 
-        Ok(())
+        let unsent: Option<DbJob> = match &self.db_jobs {
+            Some(jobs) => jobs.send(job).err().map(|e| e.0),
+            None => Some(job),
+        };
+        // no worker to take it, so answer on this thread through the same message
+        if let Some(job) = unsent {
+            self.db_jobs = None;
+            self.sender.send(run_db_job(&self.db, job));
+        }
     }
 
-    fn load_items_into_tree(&self, items: Vec<String>) -> Option<()> {
-        // load items into root tree item and close them by pathname
-        if let Some(mut t_mut) = widget_from_id::<Tree>("main_window_tree") {
-            for x in items {
-                let tree_item: TreeItem = t_mut.add(&x[..])?;
-                let pn: String = get_pathname(&t_mut, &tree_item)?;
-                close_treeitem(&mut t_mut, pn);
-            }
-        }
-        Some(())
+    fn db_job_finished(&mut self) -> () {
+        self.pending_jobs = self.pending_jobs.saturating_sub(1);
+        show_busy(self.pending_jobs);
     }
-}
 
-#[derive(Default, Clone)]
-struct RecordSet {
-    records: Vec<Record>,
-    headers: Headers,
-}
+    fn schedule_backups(&mut self) -> () {
+        // drop the previous timer so a changed interval doesn't stack a second one
+        if let Some(handle) = self.backup_timer.take() {
+            app::remove_timeout3(handle);
+        }
 
-#[derive(Clone)]
-struct Record {
-    fields: Vec<SqlData>,
-}
+        if self.settings.backup_interval_minutes == 0 {
+            return;
+        }
 
-impl Record {
-    fn new() -> Self {
-        Self { fields: Vec::new() }
+        let interval: f64 = self.settings.backup_interval_minutes as f64 * 60.0;
+        let sender_clone: Sender<Message> = self.sender.clone();
+        self.backup_timer = Some(app::add_timeout3(interval, move |handle| {
+            sender_clone.send(Message::BackupTick);
+            app::repeat_timeout3(interval, handle);
+        }));
     }
-}
 
-#[derive(Default, Clone)]
-struct Headers {
-    column_names: Vec<String>,
-    column_count: usize,
-}
+    fn watch_cold_storage(&mut self) -> () {
+        if let Some(handle) = self.watch.timer.take() {
+            app::remove_timeout3(handle);
+        }
 
-#[derive(Clone)]
-enum SqlData {
-    Null,
-    Integer(i64),
-    Real(f64),
-    Text(String),
-    Blob(()),
-}
+        let sender_clone: Sender<Message> = self.sender.clone();
+        self.watch.timer = Some(app::add_timeout3(DB_WATCH_INTERVAL_SECS, move |handle| {
+            sender_clone.send(Message::DbWatchTick);
+            app::repeat_timeout3(DB_WATCH_INTERVAL_SECS, handle);
+        }));
+    }
 
-impl ToString for SqlData {
-    fn to_string(&self) -> String {
-        match self {
-            SqlData::Null => String::new(),
-            SqlData::Integer(z) => z.to_string(),
-            SqlData::Real(z) => z.to_string(),
-            SqlData::Text(z) => z.to_string(),
-            SqlData::Blob(_) => String::new(),
+    fn remember_db_state(&mut self) -> () {
+        // an external commit not yet picked up by the tick keeps the old state, so it isn't
+        // mistaken for one of our own writes
+        if fetch_data_version(&self.db).ok() != Some(self.watch.data_version) {
+            return;
         }
+
+        self.watch.open_entity = match widget_from_id::<Input>("entity_base_id") {
+            Some(i) if !i.value().is_empty() => {
+                // when it can't be read there is nothing to compare a later change against
+                build_compare_rows(&self.db, &[i.value()])
+                    .ok()
+                    .map(|rows| (i.value(), rows))
+            }
+            _ => None,
+        };
     }
-}
 
-#[derive(Clone)]
+    fn check_for_external_changes(&mut self) -> () {
+        // data_version only moves for other connections' commits, and reading it is cheap
+        // enough for every tick. the file's mtime can't be trusted for this, in WAL mode a
+        // commit may not touch the main file until the next checkpoint
+        // a failed read is tried again on the next tick
+        let data_version: i64 = match fetch_data_version(&self.db) {
+            Ok(v) if v != self.watch.data_version => v,
+            _ => return,
+        };
+        self.watch.data_version = data_version;
+
+        self.reload_categories();
+        fill_comp_layer_manager(&self.db, self.sender.clone());
+        fill_comp_layer_rules(&self.db);
+        fill_resource_paths(&self.db, &self.settings, self.sender.clone());
+        fill_comp_pack(&self.db, self.sender.clone());
+        self.reload_open_entity();
+
+        self.remember_db_state();
+    }
+
+    fn reload_categories(&mut self) -> Option<()> {
+        let categories: Vec<String> = fetch_entity_category_names(&self.db);
+        let tree: Tree = widget_from_id::<Tree>("main_window_tree")?;
+        let root: TreeItem = tree.root()?;
+        let listed: Vec<String> = (0..root.children())
+            .filter_map(|index| root.child(index))
+            .filter_map(|child| child.label())
+            .collect();
+
+        // same categories, so only the searches with results showing are run again
+        if listed == categories {
+            for t in quick_search_items() {
+                if listed_entity_count(&t) > 0 {
+                    if let Some(job) = entity_search_job(t, 0, self.tree_generation) {
+                        self.submit_db_job(job);
+                    }
+                }
+            }
+            return Some(());
+        }
+
+        self.rebuild_tree(categories)
+    }
+
+    fn rebuild_tree(&mut self, categories: Vec<String>) -> Option<()> {
+        let mut tree: Tree = widget_from_id::<Tree>("main_window_tree")?;
+        let root: TreeItem = tree.root()?;
+        self.tree_generation += 1;
+
+        // the embedded search packs aren't owned by their items, so they go first
+        for t in quick_search_items() {
+            if let Some(w) = t.try_widget() {
+                app::delete_widget(w);
+            }
+        }
+        tree.clear_children(&root);
+        self.load_items_into_tree(categories);
+        build_out_creation_categories(&self.db, self.sender.clone());
+
+        Some(tree.redraw())
+    }
+
+    fn reload_open_entity(&mut self) -> () {
+        if self.selected_entities.len() > 1 {
+            build_bulk_edit_window(&self.db, &self.selected_entities, None, self.sender.clone());
+            return;
+        }
+
+        let eid: String = match widget_from_id::<Input>("entity_base_id") {
+            Some(i) if !i.value().is_empty() => i.value(),
+            _ => return,
+        };
+
+        if let Ok(None) = parse_entity_id(&eid).and_then(|id| Repository::new(&self.db).entity(id))
+        {
+            dialog::alert_default(&format!("Entity {} was deleted by another program", eid)[..]);
+            self.selected_entities.retain(|x| *x != eid);
+            self.selection_changed();
+            return;
+        }
+
+        let changed: Vec<String> = match self.watch.open_entity.take() {
+            Some((seen, rows)) if seen == eid => {
+                match build_compare_rows(&self.db, &[eid.clone()]) {
+                    Ok(now) => changed_compare_rows(&rows, &now),
+                    Err(e) => vec![format!("it could not be re-read: {}", e)],
+                }
+            }
+            _ => Vec::new(),
+        };
+
+        // the header inputs may hold unsaved edits, so they are only replaced when asked to
+        if changed.is_empty() {
+            fill_entity_sections(&eid, &self.db);
+            return;
+        }
+        let question: String = format!(
+            "Entity {} was changed by another program ({}).\nReload it? Unsaved header edits will be lost.",
+            eid,
+            changed.join(", ")
+        );
+        match dialog::choice2_default(&question[..], "Reload", "Keep my edits", "") {
+            Some(0) => fill_scroll_gui(&eid, &self.db),
+            _ => fill_entity_sections(&eid, &self.db),
+        }
+    }
+
+    fn open_cold_storage(&mut self, db_path: PathBuf) -> () {
+        let db: Connection = match open_connection(&db_path) {
+            Ok(c) => c,
+            Err(e) => {
+                dialog::alert_default(
+                    &format!("{} could not be opened: {}", db_path.display(), e)[..],
+                );
+                return;
+            }
+        };
+
+        // same as on startup: snapshot first, then make sure our own tables are there
+        let _ = backup_cold_storage(&db, &db_path, self.settings.backup_retention);
+        if let Err(e) = ensure_app_schema(&db) {
+            return alert_schema_error(e, &format!("{} was not opened.", db_path.display())[..]);
+        }
+
+        self.db = db;
+        self.db_path = db_path;
+        self.db_jobs = spawn_db_worker(&self.db_path, self.sender.clone());
+        self.pending_jobs = 0;
+        show_busy(0);
+        self.show_fresh_cold_storage();
+
+        self.settings.remember_recent_file(&self.db_path);
+        if let Err(e) = self.settings.save() {
+            alert_settings_not_saved(e);
+        }
+        fill_recent_files_menu(&self.settings, self.sender.clone());
+    }
+
+    fn show_fresh_cold_storage(&mut self) -> () {
+        // after opening or restoring, nothing on screen or in the history belongs to it
+        self.watch.data_version = fetch_data_version(&self.db).unwrap_or_default();
+        self.watch.open_entity = None;
+        self.history = EditHistory {
+            // not forgotten, nothing is undoable rather than the old database's entries
+            last_audit_id: match forget_session_audit_ids(&self.db) {
+                Ok(()) => 0,
+                Err(_) => i64::MAX,
+            },
+            undo: Vec::new(),
+            redo: Vec::new(),
+        };
+
+        // windows showing the old database's rows
+        for id in [
+            "bulk_edit_window",
+            "compare_window",
+            "merge_window",
+            "restore_window",
+            "templates_window",
+            "states_window",
+            "identities_window",
+            "actions_window",
+            "resource_paths_window",
+            "comp_layers_window",
+            "validation_window",
+        ] {
+            close_window_by_id(id);
+        }
+
+        self.selected_entities.clear();
+        self.selection_anchor = None;
+        clear_scroll_gui();
+        self.rebuild_tree(fetch_entity_category_names(&self.db));
+        fill_comp_pack(&self.db, self.sender.clone());
+    }
+
+    fn take_new_audit_ids(&mut self) -> Vec<String> {
+        let ids: Vec<i64> = rows_or_report(session_audit_ids_after(
+            &self.db,
+            self.history.last_audit_id,
+        ));
+        if let Some(last) = ids.last() {
+            self.history.last_audit_id = *last;
+        }
+
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    fn remember_edits(&mut self) -> () {
+        let written: Vec<String> = self.take_new_audit_ids();
+        // a fresh edit branches off, whatever was undone before it can't be redone
+        if !written.is_empty() {
+            self.history.undo.push(written);
+            self.history.redo.clear();
+        }
+    }
+
+    fn step_history(&mut self, undo: bool, resolved: Vec<(String, Option<String>)>) -> () {
+        let step: Vec<String> = match undo {
+            true => self.history.undo.pop(),
+            false => self.history.redo.pop(),
+        }
+        .unwrap_or_default();
+
+        // nothing of a step is written while a value it reverts moved on since, the step goes
+        // back on its stack until the merge window settles every one of them
+        let mut conflicts: Vec<RevertConflict> = Vec::new();
+        let mut unreadable: bool = false;
+        for audit_id in step.iter().rev() {
+            match value_or_report(find_revert_conflict(&self.db, audit_id, &resolved)) {
+                Some(Some(conflict)) => conflicts.push(conflict),
+                Some(None) => {}
+                None => unreadable = true,
+            }
+        }
+        if unreadable || !conflicts.is_empty() {
+            match undo {
+                true => self.history.undo.push(step),
+                false => self.history.redo.push(step),
+            }
+            if !unreadable {
+                build_revert_merge_window(
+                    "What this step reverts",
+                    conflicts,
+                    move |picked| Message::ResolveHistoryStep(undo, picked),
+                    self.sender.clone(),
+                );
+            }
+            return;
+        }
+
+        // reverting writes audit entries of its own, those are what the opposite step reverts
+        let mut touched: Vec<String> = Vec::new();
+        for audit_id in step.iter().rev() {
+            if let Some(eid) = revert_audit_entry(&self.db, audit_id, &resolved) {
+                touched.push(eid);
+            }
+        }
+        let reverted: Vec<String> = self.take_new_audit_ids();
+        match (undo, reverted.is_empty()) {
+            (_, true) if !step.is_empty() => {
+                dialog::alert_default("That step can't be reverted, nothing was changed")
+            }
+            (_, true) => {}
+            (true, false) => self.history.redo.push(reverted),
+            (false, false) => self.history.undo.push(reverted),
+        }
+
+        for eid in touched.iter() {
+            match fetch_row_version(&self.db, "entity_base_definitions", eid) {
+                Ok(Some(_)) => {}
+                Ok(None) => {
+                    remove_entity_from_tree(eid);
+                    self.selected_entities.retain(|x| x != eid);
+                }
+                Err(e) => alert_read_error(eid, e),
+            }
+        }
+        if let Some(open) = widget_from_id::<Input>("entity_base_id") {
+            if touched.contains(&open.value()) {
+                self.selection_changed();
+            }
+        }
+    }
+
+    fn duplicate_open_entity(&mut self) -> () {
+        let eid: String = match widget_from_id::<Input>("entity_base_id") {
+            Some(i) if !i.value().is_empty() => i.value(),
+            _ => return,
+        };
+        let new_eid: String = match dialog::input_default(
+            &format!("Id for the copy of entity {}", eid)[..],
+            &value_or_report(fetch_next_entity_id(&self.db)).unwrap_or_default()[..],
+        ) {
+            Some(id) if !id.trim().is_empty() => String::from(id.trim()),
+            _ => return,
+        };
+
+        match duplicate_entity(&self.db, &eid, &new_eid) {
+            Some(reason) => dialog::alert_default(&reason[..]),
+            None => {
+                // the copy has to be in the tree before it can be selected there
+                self.reload_categories();
+                self.selected_entities = vec![new_eid.clone()];
+                self.selection_anchor = Some(new_eid);
+                self.selection_changed();
+            }
+        }
+    }
+
+    fn export_entities(&mut self) -> () {
+        let path: PathBuf = match FileDialog::new()
+            .set_location(&current_dir().unwrap_or_default())
+            .add_filter("Entities", &["entities"])
+            .show_save_single_file()
+        {
+            Ok(Some(p)) => p,
+            _ => return,
+        };
+
+        // the selection when there is one, otherwise everything
+        self.submit_db_job(DbJob::Export(self.selected_entities.clone(), path));
+    }
+
+    fn import_entities(&mut self) -> () {
+        let path: PathBuf = match FileDialog::new()
+            .set_location(&current_dir().unwrap_or_default())
+            .add_filter("Entities", &["entities"])
+            .show_open_single_file()
+        {
+            Ok(Some(p)) => p,
+            _ => return,
+        };
+        let packages: Vec<EXPackage> = match fs::read_to_string(&path) {
+            Ok(contents) => parse_entity_export(&contents),
+            Err(e) => {
+                dialog::alert_default(&format!("That file could not be read: {}", e)[..]);
+                return;
+            }
+        };
+        if packages.is_empty() {
+            dialog::alert_default("No entities were found in that file");
+            return;
+        }
+
+        let existing: usize = packages
+            .iter()
+            // one that can't be read is counted too, so the question is still asked
+            .filter(|p| !matches!(fetch_entity_header(&self.db, &p.header.eid), Ok(None)))
+            .count();
+        if existing > 0 {
+            let question: String = format!(
+                "{} of the {} entities already exist and will be overwritten. Import anyway?",
+                existing,
+                packages.len()
+            );
+            if dialog::choice2_default(&question[..], "Import", "Cancel", "") != Some(0) {
+                return;
+            }
+        }
+
+        if let Some(reason) = import_entity_exports(&self.db, &packages) {
+            dialog::alert_default(&reason[..]);
+            return;
+        }
+
+        self.reload_categories();
+        if let Some(open) = widget_from_id::<Input>("entity_base_id") {
+            if packages.iter().any(|p| p.header.eid == open.value()) {
+                fill_scroll_gui(&open.value(), &self.db);
+            }
+        }
+    }
+
+    fn delete_open_entity(&mut self) -> () {
+        let eid: String = match widget_from_id::<Input>("entity_base_id") {
+            Some(i) if !i.value().is_empty() => i.value(),
+            _ => return,
+        };
+        let question: String = format!(
+            "Delete entity {} along with its layers, attributes, actions, avatar, identity and template links?",
+            eid
+        );
+        if dialog::choice2_default(&question[..], "Delete", "Cancel", "") != Some(0) {
+            return;
+        }
+
+        let seen: Option<i64> =
+            widget_from_id::<Input>("row_version").and_then(|i| i.value().parse::<i64>().ok());
+        if let Some(reason) = delete_entity(&self.db, &eid, seen) {
+            dialog::alert_default(&reason[..]);
+            return;
+        }
+
+        remove_entity_from_tree(&eid);
+        self.selected_entities.retain(|x| *x != eid);
+        self.selection_changed();
+    }
+
+    fn select_adjacent_result(&mut self, step: i32) -> () {
+        let listed: Vec<String> = listed_entity_ids();
+        if listed.is_empty() {
+            return;
+        }
+
+        // steps from the last result clicked, or starts at whichever end it's heading from
+        let last: i32 = listed.len() as i32 - 1;
+        let index: i32 = match self
+            .selection_anchor
+            .as_ref()
+            .and_then(|anchor| listed.iter().position(|x| x == anchor))
+        {
+            Some(i) => (i as i32 + step).clamp(0, last),
+            None if step > 0 => 0,
+            None => last,
+        };
+        let eid: String = listed[index as usize].clone();
+
+        self.selected_entities = vec![eid.clone()];
+        self.selection_anchor = Some(eid.clone());
+        self.selection_changed();
+        show_entity_in_tree(&eid);
+    }
+
+    fn event_loop(&mut self) -> Result<(), ()> {
+        while self.fltk_app.wait() {
+            // tabbing through the canvas can land on a widget scrolled out of sight
+            if app::event() == Event::KeyDown {
+                scroll_focus_into_view();
+            }
+
+            let message: Option<Message> = self.receiver.recv();
+            // anything handled here may have written, which mustn't look external later on
+            let handled: bool = !matches!(message, None | Some(Message::DbWatchTick));
+
+            match message {
+                Some(Message::SearchEntities(t)) => {
+                    if let Some(job) = entity_search_job(t, 0, self.tree_generation) {
+                        self.submit_db_job(job);
+                    }
+                }
+                Some(Message::LoadMoreEntities(t)) => {
+                    let listed: usize = listed_entity_count(&t);
+                    if let Some(job) = entity_search_job(t, listed, self.tree_generation) {
+                        self.submit_db_job(job);
+                    }
+                }
+                Some(Message::EntitySearchDone(t, entities, offset, generation)) => {
+                    self.db_job_finished();
+                    // the tree was rebuilt while the search ran, `t` no longer exists
+                    if generation == self.tree_generation {
+                        fill_tree_with_entity_data(entities, t, offset);
+                        highlight_selected_entities(&self.selected_entities);
+                    }
+                }
+                Some(Message::ClearEntities(mut t)) => {
+                    clear_entities_from_tree(&mut t);
+                }
+                Some(Message::EntityFrameClicked(eid)) => {
+                    self.selected_entities = vec![eid.clone()];
+                    self.selection_anchor = Some(eid);
+                    self.selection_changed();
+                }
+                Some(Message::EntityFrameToggled(eid)) => {
+                    match self.selected_entities.iter().position(|x| *x == eid) {
+                        Some(i) => {
+                            self.selected_entities.remove(i);
+                        }
+                        None => self.selected_entities.push(eid.clone()),
+                    }
+                    self.selection_anchor = Some(eid);
+                    self.selection_changed();
+                }
+                Some(Message::EntityFrameRangeSelected(eid)) => {
+                    let listed: Vec<String> = listed_entity_ids();
+                    let anchor: String = self.selection_anchor.clone().unwrap_or(eid.clone());
+                    self.selected_entities = match (
+                        listed.iter().position(|x| *x == anchor),
+                        listed.iter().position(|x| *x == eid),
+                    ) {
+                        (Some(a), Some(b)) => listed[a.min(b)..=a.max(b)].to_vec(),
+                        _ => vec![eid],
+                    };
+                    self.selection_changed();
+                }
+                Some(Message::UpdateCompLayers(c)) => {
+                    let eid: String = c.entity_id.clone();
+                    // asked up front so the prerequisites can be offered, the write checks again
+                    let go_ahead: bool =
+                        match value_or_report(evaluate_comp_layer_change(&self.db, &c)) {
+                            Some(CompLayerCheck::Allowed) => true,
+                            Some(CompLayerCheck::NeedsPrerequisites(_, question)) => {
+                                dialog::choice2_default(&question[..], "Enable", "Cancel", "")
+                                    == Some(0)
+                            }
+                            Some(CompLayerCheck::Refused(reason)) => {
+                                dialog::alert_default(&reason[..]);
+                                false
+                            }
+                            None => false,
+                        };
+                    if go_ahead {
+                        write_open_entity(&self.db, &eid, || {
+                            match write_comp_layer_change(&self.db, c)? {
+                                Some(reason) => Err(layer_rules_refused(&eid, reason)),
+                                None => Ok(()),
+                            }
+                        });
+                    }
+                    // the checkbox already flipped, put every row back in line with the db
+                    fill_comp_checks(&self.db, &eid);
+                    fill_action_driven_layers(&self.db, &eid);
+                    fill_identity_section(&self.db, &eid);
+                    fill_history_browser(&self.db, &eid);
+                }
+                Some(Message::NewEntity(package)) => match new_entity(&self.db, &package) {
+                    Ok(_) => fill_scroll_gui(&package.eid, &self.db),
+                    Err(e) => alert_write_error(e),
+                },
+                Some(Message::NewEntityFromSearch) => {
+                    // with no id typed yet there's nothing to create, so go and type one
+                    if let Some(t) = active_quick_search() {
+                        if new_entity_from_tree(&t, &self.sender).is_none() {
+                            focus_quick_search(&t);
+                        }
+                    }
+                }
+                Some(Message::DuplicateEntity) => {
+                    self.duplicate_open_entity();
+                }
+                Some(Message::FocusSearch) => {
+                    if let Some(t) = active_quick_search() {
+                        focus_quick_search(&t);
+                    }
+                }
+                Some(Message::DeleteEntity) => {
+                    self.delete_open_entity();
+                }
+                Some(Message::SelectAdjacentResult(step)) => {
+                    self.select_adjacent_result(step);
+                }
+                Some(Message::UpdateEntity) => {
+                    update_entity(&self.db, self.sender.clone());
+                    if let Some(eid) = widget_from_id::<Input>("entity_base_id") {
+                        fill_history_browser(&self.db, &eid.value());
+                    }
+                }
+                Some(Message::SaveEntityHeader(package, version)) => {
+                    let eid: String = package.eid.clone();
+                    match write_entity_header(&self.db, package.clone(), Some(version)) {
+                        Ok(Some(current)) => build_entity_merge_window(
+                            &self.db,
+                            package,
+                            current,
+                            self.sender.clone(),
+                        ),
+                        Err(e) => alert_write_error(e),
+                        Ok(None) => {
+                            if let Some(open) = widget_from_id::<Input>("entity_base_id") {
+                                if open.value() == eid {
+                                    fill_scroll_gui(&eid, &self.db);
+                                }
+                            }
+                        }
+                    }
+                }
+                Some(Message::OpenDatabase) => {
+                    if let Some(path) = locate_cold_storage() {
+                        self.open_cold_storage(path);
+                    }
+                }
+                Some(Message::OpenRecentDatabase(path)) => {
+                    self.open_cold_storage(PathBuf::from(path));
+                }
+                Some(Message::BackupNow) => {
+                    match backup_cold_storage(
+                        &self.db,
+                        &self.db_path,
+                        self.settings.backup_retention,
+                    ) {
+                        Some(snapshot) => dialog::message_default(
+                            &format!("Backed up to {}", snapshot.display())[..],
+                        ),
+                        None => dialog::alert_default("The backup could not be written"),
+                    }
+                }
+                Some(Message::Quit) => {
+                    self.fltk_app.quit();
+                }
+                Some(Message::Undo) => {
+                    self.step_history(true, Vec::new());
+                }
+                Some(Message::Redo) => {
+                    self.step_history(false, Vec::new());
+                }
+                Some(Message::Regen) => {
+                    if let Err(e) = regen_enums(&self.db) {
+                        dialog::alert_default(&format!("enums.gd could not be written: {}", e)[..]);
+                    }
+                }
+                Some(Message::ImportEntities) => {
+                    self.import_entities();
+                }
+                Some(Message::ExportEntities) => {
+                    self.export_entities();
+                }
+                Some(Message::OpenSettings) => {
+                    build_settings_window(&self.settings, self.sender.clone());
+                }
+                Some(Message::SaveSettings(mut s)) => {
+                    // the window's copy predates any database opened while it was up
+                    s.recent_files = self.settings.recent_files.clone();
+                    if s.ui_scale != self.settings.ui_scale {
+                        apply_ui_scale(s.ui_scale);
+                    }
+                    self.settings = s;
+                    if let Err(e) = self.settings.save() {
+                        alert_settings_not_saved(e);
+                    }
+                    self.schedule_backups();
+                }
+                Some(Message::DbWatchTick) => {
+                    self.check_for_external_changes();
+                }
+                Some(Message::BackupTick) => {
+                    let _ = backup_cold_storage(
+                        &self.db,
+                        &self.db_path,
+                        self.settings.backup_retention,
+                    );
+                }
+                Some(Message::OpenRestoreWindow) => {
+                    let snapshots: Vec<String> = fetch_backup_listing(&self.db, &self.db_path);
+                    build_restore_window(snapshots, self.sender.clone());
+                }
+                Some(Message::RestoreBackup(stamp)) => {
+                    match restore_cold_storage(
+                        &mut self.db,
+                        &self.db_path,
+                        &stamp,
+                        self.settings.backup_retention,
+                    ) {
+                        Some(reason) => dialog::alert_default(&reason[..]),
+                        // every row on screen may predate the snapshot
+                        None => match ensure_app_schema(&self.db) {
+                            Ok(_) => {
+                                self.show_fresh_cold_storage();
+                                dialog::message_default("Cold storage restored from backup");
+                            }
+                            Err(e) => {
+                                alert_schema_error(
+                                    e,
+                                    "The state before the restore was backed up, the editor will close.",
+                                );
+                                self.fltk_app.quit();
+                            }
+                        },
+                    }
+                }
+                Some(Message::ResolveHistoryStep(undo, resolved)) => {
+                    self.step_history(undo, resolved);
+                }
+                Some(Message::RevertAuditEntry(audit_id, resolved)) => {
+                    match value_or_report(find_revert_conflict(&self.db, &audit_id, &resolved)) {
+                        Some(Some(conflict)) => build_revert_merge_window(
+                            "What this change reverts",
+                            vec![conflict],
+                            move |picked| Message::RevertAuditEntry(audit_id.clone(), picked),
+                            self.sender.clone(),
+                        ),
+                        Some(None) => {
+                            if let Some(eid) = revert_audit_entry(&self.db, &audit_id, &resolved) {
+                                // reverting a creation removes the entity altogether
+                                match fetch_row_version(&self.db, "entity_base_definitions", &eid) {
+                                    Ok(Some(_)) => fill_scroll_gui(&eid, &self.db),
+                                    Ok(None) => {
+                                        remove_entity_from_tree(&eid);
+                                        self.selected_entities.retain(|x| *x != eid);
+                                        self.selection_changed();
+                                    }
+                                    Err(e) => alert_read_error(&eid, e),
+                                }
+                            }
+                        }
+                        None => {}
+                    }
+                }
+                Some(Message::OpenCompareWindow) => {
+                    build_compare_window(
+                        rows_or_report(Repository::new(&self.db).entities()),
+                        self.sender.clone(),
+                    );
+                }
+                Some(Message::CompareEntities(eids)) => {
+                    self.submit_db_job(DbJob::CompareEntities(eids));
+                }
+                Some(Message::CompareRowsReady(eids, rows)) => {
+                    self.db_job_finished();
+                    fill_compare_grid(&eids, rows);
+                }
+                Some(Message::OpenTemplatesWindow) => {
+                    build_templates_window(
+                        rows_or_report(fetch_templates(&self.db)),
+                        self.sender.clone(),
+                    );
+                }
+                Some(Message::CreateTemplate(eid, name)) => {
+                    if let Err(e) = create_template_from_entity(&self.db, &eid, &name) {
+                        alert_write_error(e);
+                    }
+                    fill_template_choices(&self.db);
+                }
+                Some(Message::DeleteTemplate(tid)) => {
+                    if let Err(e) = delete_template(&self.db, &tid) {
+                        alert_write_error(e);
+                    }
+                    fill_template_choices(&self.db);
+                    if let Some(eid) = widget_from_id::<Input>("entity_base_id") {
+                        fill_template_status(&self.db, &eid.value());
+                    }
+                }
+                Some(Message::BulkUpdateCompLayers(package)) => {
+                    let problem: Option<String> = bulk_update_comp_layers(&self.db, &package)
+                        .err()
+                        .map(describe_bulk_error);
+                    build_bulk_edit_window(
+                        &self.db,
+                        &self.selected_entities,
+                        problem,
+                        self.sender.clone(),
+                    );
+                }
+                Some(Message::BulkUpdateAttribute(package)) => {
+                    let problem: Option<String> = bulk_update_attribute(&self.db, &package)
+                        .err()
+                        .map(describe_bulk_error);
+                    if let Some(eid) = widget_from_id::<Input>("entity_base_id") {
+                        fill_action_costs_section(&self.db, &eid.value());
+                    }
+                    build_bulk_edit_window(
+                        &self.db,
+                        &self.selected_entities,
+                        problem,
+                        self.sender.clone(),
+                    );
+                }
+                Some(Message::AssignAvatar(eid, aid)) => {
+                    write_open_entity(&self.db, &eid, || update_entity_avatar(&self.db, &eid, aid));
+                    fill_avatar_section(&self.db, &eid);
+                    fill_history_browser(&self.db, &eid);
+                }
+                Some(Message::NewAvatar(path)) => {
+                    let eid: String = match widget_from_id::<Input>("entity_base_id") {
+                        Some(i) => i.value(),
+                        None => String::new(),
+                    };
+                    // a new avatar is assigned straight away when an entity is open
+                    match create_avatar(&self.db, &path) {
+                        Ok(aid) if !eid.is_empty() => {
+                            write_open_entity(&self.db, &eid, || {
+                                update_entity_avatar(&self.db, &eid, Some(aid))
+                            });
+                            fill_history_browser(&self.db, &eid);
+                        }
+                        Ok(_) => {}
+                        Err(reason) => dialog::alert_default(&reason[..]),
+                    }
+                    fill_avatar_section(&self.db, &eid);
+                }
+                Some(Message::OpenStatesWindow) => {
+                    build_states_window(
+                        rows_or_report(fetch_states(&self.db)),
+                        self.sender.clone(),
+                    );
+                }
+                Some(Message::SelectState(sid)) => {
+                    fill_state_modifiers(&self.db, &sid, self.sender.clone());
+                }
+                Some(Message::CreateState(name)) => {
+                    if let Err(e) = create_state(&self.db, &name) {
+                        alert_write_error(e);
+                    }
+                    fill_states_browser(&self.db);
+                }
+                Some(Message::SaveStateModifier(mut package)) => {
+                    if package.attribute_id.is_empty() {
+                        package.attribute_id = first_attribute_id(&self.db);
+                    }
+                    match save_state_modifier(&self.db, &package) {
+                        Ok(Some(current)) => {
+                            build_state_modifier_merge_window(
+                                &self.db,
+                                package.clone(),
+                                current,
+                                self.sender.clone(),
+                            );
+                        }
+                        Ok(None) => {}
+                        Err(e) => alert_write_error(e),
+                    }
+                    fill_state_modifiers(&self.db, &package.state_id, self.sender.clone());
+                }
+                Some(Message::DeleteStateModifier(index, sid, seen)) => {
+                    match delete_state_modifier(&self.db, &index, seen) {
+                        Ok(Some(_)) => alert_stale_delete(&format!("State modifier {}", index)),
+                        Ok(None) => {}
+                        Err(e) => alert_write_error(e),
+                    }
+                    fill_state_modifiers(&self.db, &sid, self.sender.clone());
+                }
+                Some(Message::OpenIdentitiesWindow) => {
+                    build_identities_window(
+                        rows_or_report(fetch_identity_types(&self.db)),
+                        self.sender.clone(),
+                    );
+                }
+                Some(Message::SelectIdentityType(tid)) => {
+                    fill_identity_names_browser(&self.db, &tid);
+                }
+                Some(Message::CreateIdentityType(name)) => {
+                    if let Err(e) = create_identity_type(&self.db, &name) {
+                        alert_write_error(e);
+                    }
+                    fill_identity_types_browser(&self.db);
+                }
+                Some(Message::AddIdentityNames(tid, names)) => {
+                    if let Err(e) = add_identity_names(&self.db, &tid, &names) {
+                        alert_write_error(e);
+                    }
+                    fill_identity_types_browser(&self.db);
+                    fill_identity_names_browser(&self.db, &tid);
+                }
+                Some(Message::DeleteIdentityNames(tid, indexes)) => {
+                    if let Err(e) = delete_identity_names(&self.db, &indexes) {
+                        alert_write_error(e);
+                    }
+                    fill_identity_types_browser(&self.db);
+                    fill_identity_names_browser(&self.db, &tid);
+                }
+                Some(Message::AssignIdentityType(eid, tid)) => {
+                    write_open_entity(&self.db, &eid, || {
+                        update_entity_identity_type(&self.db, &eid, tid)
+                    });
+                    fill_identity_section(&self.db, &eid);
+                    fill_history_browser(&self.db, &eid);
+                }
+                Some(Message::OpenActionsWindow) => {
+                    build_actions_window(
+                        rows_or_report(fetch_actions(&self.db)),
+                        self.sender.clone(),
+                    );
+                }
+                Some(Message::SelectAction(aid)) => {
+                    fill_action_workspace(&self.db, &aid, self.sender.clone());
+                }
+                Some(Message::SaveActionRequirement(mut package)) => {
+                    if package.attribute_id.is_empty() {
+                        package.attribute_id = first_attribute_id(&self.db);
+                    }
+                    match save_action_requirement(&self.db, &package) {
+                        Ok(Some(current)) => {
+                            build_action_requirement_merge_window(
+                                &self.db,
+                                package.clone(),
+                                current,
+                                self.sender.clone(),
+                            );
+                        }
+                        Ok(None) => {}
+                        Err(e) => alert_write_error(e),
+                    }
+                    fill_action_workspace(&self.db, &package.action_id, self.sender.clone());
+                    if let Some(eid) = widget_from_id::<Input>("entity_base_id") {
+                        fill_action_costs_section(&self.db, &eid.value());
+                    }
+                }
+                Some(Message::DeleteActionRequirement(rid, aid, seen)) => {
+                    match delete_action_requirement(&self.db, &rid, seen) {
+                        Ok(Some(_)) => alert_stale_delete(&format!("Requirement {}", rid)),
+                        Ok(None) => {}
+                        Err(e) => alert_write_error(e),
+                    }
+                    fill_action_workspace(&self.db, &aid, self.sender.clone());
+                    if let Some(eid) = widget_from_id::<Input>("entity_base_id") {
+                        fill_action_costs_section(&self.db, &eid.value());
+                    }
+                }
+                Some(Message::UpdateActionLink(package)) => {
+                    if let Err(e) = update_action_link(&self.db, &package) {
+                        alert_write_error(e);
+                    }
+                    fill_action_workspace(&self.db, &package.action_id, self.sender.clone());
+                }
+                Some(Message::OpenResourcePathsWindow) => {
+                    build_resource_paths_window(self.sender.clone());
+                    fill_resource_paths(&self.db, &self.settings, self.sender.clone());
+                }
+                Some(Message::CheckResourcePaths) => {
+                    fill_resource_paths(&self.db, &self.settings, self.sender.clone());
+                }
+                Some(Message::SaveCallablePath(aid, path, seen)) => {
+                    match update_callable_location(&self.db, &aid, &path, seen) {
+                        Ok(Some(current)) => {
+                            // theirs can't be shown when it can't be read, nothing is merged
+                            if let Some(theirs) =
+                                value_or_report(fetch_callable_location(&self.db, &aid))
+                            {
+                                build_merge_window(
+                                    &format!("Action {} callable", aid),
+                                    vec![("callable_location", path, theirs.unwrap_or_default())],
+                                    move |values| {
+                                        Message::SaveCallablePath(
+                                            aid.clone(),
+                                            values[0].clone(),
+                                            Some(current),
+                                        )
+                                    },
+                                    self.sender.clone(),
+                                );
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => alert_write_error(e),
+                    }
+                    fill_resource_paths(&self.db, &self.settings, self.sender.clone());
+                }
+                Some(Message::SaveCompLayerResource(cid, path, seen)) => {
+                    match update_comp_layer_resource(&self.db, &cid, &path, seen) {
+                        Ok(Some(current)) => {
+                            // theirs can't be shown when it can't be read, nothing is merged
+                            if let Some(theirs) =
+                                value_or_report(fetch_comp_layer_resource(&self.db, &cid))
+                            {
+                                build_merge_window(
+                                    &format!("Composition layer {}", cid),
+                                    vec![("resource", path, theirs.unwrap_or_default())],
+                                    move |values| {
+                                        Message::SaveCompLayerResource(
+                                            cid.clone(),
+                                            values[0].clone(),
+                                            Some(current),
+                                        )
+                                    },
+                                    self.sender.clone(),
+                                );
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => alert_write_error(e),
+                    }
+                    fill_resource_paths(&self.db, &self.settings, self.sender.clone());
+                }
+                Some(Message::OpenCompLayersWindow) => {
+                    build_comp_layers_window(self.sender.clone());
+                    fill_comp_layer_manager(&self.db, self.sender.clone());
+                    fill_comp_layer_rules(&self.db);
+                }
+                Some(Message::CreateCompLayer(name, resource)) => {
+                    if let Err(e) = create_comp_layer(&self.db, &name, &resource) {
+                        alert_write_error(e);
+                    }
+                    self.comp_layers_changed();
+                }
+                Some(Message::SaveCompLayer(cid, name, resource, seen)) => {
+                    match save_comp_layer(&self.db, &cid, &name, &resource, seen) {
+                        Ok(Some(current)) => {
+                            build_comp_layer_merge_window(
+                                &self.db,
+                                cid,
+                                name,
+                                resource,
+                                current,
+                                self.sender.clone(),
+                            );
+                        }
+                        Ok(None) => {}
+                        Err(e) => alert_write_error(e),
+                    }
+                    self.comp_layers_changed();
+                }
+                Some(Message::MoveCompLayer(cid, offset, seen)) => {
+                    if let Some(reason) = move_comp_layer(&self.db, &cid, offset, seen) {
+                        dialog::alert_default(&reason[..]);
+                    }
+                    self.comp_layers_changed();
+                }
+                Some(Message::DeleteCompLayer(cid, seen)) => {
+                    if let Some(reason) = delete_comp_layer(&self.db, &cid, seen) {
+                        dialog::alert_default(&reason[..]);
+                    }
+                    self.comp_layers_changed();
+                }
+                Some(Message::AddCompLayerRule(cid, relation, related_id)) => {
+                    if let Err(e) = add_comp_layer_rule(&self.db, &cid, &relation, &related_id) {
+                        alert_write_error(e);
+                    }
+                    fill_comp_layer_rules(&self.db);
+                }
+                Some(Message::DeleteCompLayerRule(rule_id)) => {
+                    if let Err(e) = delete_comp_layer_rule(&self.db, &rule_id) {
+                        alert_write_error(e);
+                    }
+                    fill_comp_layer_rules(&self.db);
+                }
+                Some(Message::OpenValidationWindow) => {
+                    self.submit_db_job(DbJob::Validate);
+                }
+                Some(Message::ValidationDone(problems)) => {
+                    self.db_job_finished();
+                    build_validation_window(problems);
+                }
+                Some(Message::ExportDone(path, count)) => {
+                    self.db_job_finished();
+                    dialog::message_default(
+                        &format!("Exported {} entities to {}", count, path.display())[..],
+                    );
+                }
+                Some(Message::DbJobFailed(reason)) => {
+                    self.db_job_finished();
+                    dialog::alert_default(&reason[..]);
+                }
+                Some(Message::DbWorkerFailed(reason)) => {
+                    // jobs sent to a worker that is gone fall back to this thread
+                    dialog::alert_default(
+                        &format!(
+                            "Background reads could not start and will run here instead: {}",
+                            reason
+                        )[..],
+                    );
+                }
+                Some(Message::SyncActionLayers(eid)) => {
+                    let mut problems: Vec<String> = Vec::new();
+                    write_open_entity(&self.db, &eid, || {
+                        problems = sync_action_layers(&self.db, &eid);
+                        Ok(())
+                    });
+                    if !problems.is_empty() {
+                        dialog::alert_default(&problems.join("\n")[..]);
+                    }
+                    fill_comp_checks(&self.db, &eid);
+                    fill_action_driven_layers(&self.db, &eid);
+                    fill_history_browser(&self.db, &eid);
+                }
+                None => {}
+            }
+
+            if handled {
+                self.remember_edits();
+                self.remember_db_state();
+            }
+        }
+
+        self.remember_layout();
+        if let Err(e) = self.settings.save() {
+            alert_settings_not_saved(e);
+        }
+
+        Ok(())
+    }
+
+    fn remember_layout(&mut self) -> Option<()> {
+        let main_window: DoubleWindow = widget_from_id::<DoubleWindow>("main_window")?;
+        let tree: Tree = widget_from_id::<Tree>("main_window_tree")?;
+
+        self.settings.window_position = Some((main_window.x(), main_window.y()));
+        self.settings.window_width = main_window.w();
+        self.settings.window_height = main_window.h();
+        self.settings.tree_width = tree.w();
+
+        Some(())
+    }
+
+    fn comp_layers_changed(&mut self) -> () {
+        fill_comp_layer_manager(&self.db, self.sender.clone());
+        fill_comp_layer_rules(&self.db);
+        fill_resource_paths(&self.db, &self.settings, self.sender.clone());
+        fill_comp_pack(&self.db, self.sender.clone());
+        // the rebuilt rows come back unchecked, so re-read the open entity
+        self.selection_changed();
+    }
+
+    fn selection_changed(&mut self) -> () {
+        highlight_selected_entities(&self.selected_entities);
+
+        // a single entity opens in the canvas, several open the bulk editor instead
+        match self.selected_entities.len() {
+            0 => {
+                close_window_by_id("bulk_edit_window");
+                clear_scroll_gui();
+            }
+            1 => {
+                close_window_by_id("bulk_edit_window");
+                fill_scroll_gui(&self.selected_entities[0], &self.db);
+            }
+            _ => {
+                build_bulk_edit_window(&self.db, &self.selected_entities, None, self.sender.clone())
+            }
+        }
+    }
+
+    fn load_items_into_tree(&self, items: Vec<String>) -> Option<()> {
+        // load items into root tree item and close them by pathname
+        if let Some(mut t_mut) = widget_from_id::<Tree>("main_window_tree") {
+            for x in items {
+                let tree_item: TreeItem = t_mut.add(&x[..])?;
+                let pn: String = get_pathname(&t_mut, &tree_item)?;
+                close_treeitem(&mut t_mut, pn);
+            }
+        }
+        Some(())
+    }
+}
+
+#[derive(Clone)]
 struct CLPackage {
     cb_state: bool,
     comp_id: String,
     entity_id: String,
 }
-#[derive(Clone)]
-struct NPackage {
-    eid: String,
-    name: String,
-    ecid: String,
-    esid: String,
+#[derive(Clone)]
+struct NPackage {
+    eid: String,
+    name: String,
+    ecid: String,
+    esid: String,
+}
+// everything that makes up one entity, as written by Tools/Export Entities
+#[derive(Clone)]
+struct EXPackage {
+    header: NPackage,
+    comp_layers: Vec<String>,
+    // (attribute id, value)
+    attributes: Vec<(String, String)>,
+    actions: Vec<String>,
+    avatar: Option<String>,
+    identity_type: Option<String>,
+    template: Option<String>,
+}
+
+#[derive(Clone)]
+struct NEPackage {
+    eid: String,
+    template_id: Option<String>,
+}
+
+#[derive(Clone)]
+struct EAPackage {
+    cb_state: bool,
+    action_id: String,
+    entity_id: String,
+}
+
+#[derive(Clone)]
+struct BCLPackage {
+    cb_state: bool,
+    comp_id: String,
+    entity_ids: Vec<String>,
+    // each entity's row_version when the bulk window was filled, in the same order
+    row_versions: Vec<Option<i64>>,
+}
+
+#[derive(Clone)]
+struct BAPackage {
+    attribute_id: String,
+    value: Option<String>,
+    entity_ids: Vec<String>,
+    row_versions: Vec<Option<i64>>,
+}
+
+#[derive(Clone)]
+struct SMPackage {
+    // None for a modifier that hasn't been written yet
+    index: Option<String>,
+    state_id: String,
+    attribute_id: String,
+    magnitude: String,
+    duration: String,
+    // the row_version the modifier was read at
+    row_version: Option<i64>,
+}
+
+#[derive(Clone)]
+struct RQPackage {
+    // None for a requirement not written yet
+    requirement_id: Option<String>,
+    action_id: String,
+    attribute_id: String,
+    cost: String,
+    row_version: Option<i64>,
+}
+
+// an action-to-type link table and the lookup table its ids come from
+#[derive(Clone, Copy)]
+struct ActionLink {
+    label: &'static str,
+    table: &'static str,
+    column: &'static str,
+    types_table: &'static str,
+}
+
+const ACTION_TARGETS: ActionLink = ActionLink {
+    label: "Targets",
+    table: "entity_actions_targets_definitions",
+    column: "entity_actions_targets_types_id",
+    types_table: "entity_actions_targets_types",
+};
+
+const ACTION_MODALS: ActionLink = ActionLink {
+    label: "Modals",
+    table: "entity_actions_modals_definitions",
+    column: "entity_actions_modals_id",
+    types_table: "entity_actions_modals",
+};
+
+#[derive(Clone)]
+struct ALPackage {
+    link: ActionLink,
+    cb_state: bool,
+    action_id: String,
+    type_id: String,
+}
+
+enum CompLayerCheck {
+    Allowed,
+    // layer ids to enable first, and the question to ask before doing so
+    NeedsPrerequisites(Vec<String>, String),
+    Refused(String),
+}
+
+// a value an audit entry changed that moved on since, mine is what the revert writes and
+// theirs what it holds now, each as (shown, written)
+struct RevertConflict {
+    audit_id: String,
+    field: &'static str,
+    mine: (String, Option<String>),
+    theirs: (String, Option<String>),
+}
+
+#[derive(Clone)]
+struct TPackage {
+    tid: String,
+    name: String,
+    ecid: String,
+    esid: String,
+}
+
+#[derive(Clone)]
+struct Settings {
+    // 0 disables the periodic snapshot, the one taken on open always happens
+    backup_interval_minutes: u64,
+    backup_retention: usize,
+    // root that res:// paths resolve against, empty until configured
+    godot_project_dir: String,
+    // most recently opened first
+    recent_files: Vec<String>,
+    // main window geometry and splitter position as last closed
+    window_position: Option<(i32, i32)>,
+    window_width: i32,
+    window_height: i32,
+    tree_width: i32,
+    // 0 leaves scaling to FLTK, which follows the desktop's HiDPI setting
+    ui_scale: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            backup_interval_minutes: 15,
+            backup_retention: 20,
+            godot_project_dir: String::new(),
+            recent_files: Vec::new(),
+            window_position: None,
+            window_width: 1280,
+            window_height: 720,
+            tree_width: 300,
+            ui_scale: 0.0,
+        }
+    }
+}
+
+impl Settings {
+    fn load() -> Self {
+        let mut settings: Settings = Settings::default();
+        let contents: String = match fs::read_to_string(settings_path()) {
+            Ok(s) => s,
+            Err(_) => return settings,
+        };
+
+        // plain "key=value" lines, unknown keys and bad values are ignored
+        for line in contents.lines() {
+            if let Some((key, value)) = line.split_once('=') {
+                let value: &str = value.trim();
+                match key.trim() {
+                    "backup_interval_minutes" => {
+                        if let Ok(v) = value.parse::<u64>() {
+                            settings.backup_interval_minutes = v;
+                        }
+                    }
+                    "backup_retention" => {
+                        if let Ok(v) = value.parse::<usize>() {
+                            settings.backup_retention = v;
+                        }
+                    }
+                    "godot_project_dir" => settings.godot_project_dir = String::from(value),
+                    "recent_file" => settings.recent_files.push(String::from(value)),
+                    "window_position" => {
+                        if let Some((x, y)) = value.split_once(',') {
+                            if let (Ok(x), Ok(y)) =
+                                (x.trim().parse::<i32>(), y.trim().parse::<i32>())
+                            {
+                                settings.window_position = Some((x, y));
+                            }
+                        }
+                    }
+                    "window_width" => {
+                        if let Ok(v) = value.parse::<i32>() {
+                            settings.window_width = v;
+                        }
+                    }
+                    "window_height" => {
+                        if let Ok(v) = value.parse::<i32>() {
+                            settings.window_height = v;
+                        }
+                    }
+                    "tree_width" => {
+                        if let Ok(v) = value.parse::<i32>() {
+                            settings.tree_width = v;
+                        }
+                    }
+                    "ui_scale" => {
+                        if let Ok(v) = value.parse::<f32>() {
+                            settings.ui_scale = v;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        settings
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let mut contents: String = String::new();
+        contents.push_str("backup_interval_minutes=");
+        contents.push_str(&self.backup_interval_minutes.to_string()[..]);
+        contents.push('\n');
+        contents.push_str("backup_retention=");
+        contents.push_str(&self.backup_retention.to_string()[..]);
+        contents.push('\n');
+        contents.push_str("godot_project_dir=");
+        contents.push_str(&self.godot_project_dir[..]);
+        contents.push('\n');
+        for path in self.recent_files.iter() {
+            contents.push_str("recent_file=");
+            contents.push_str(&path[..]);
+            contents.push('\n');
+        }
+        if let Some((x, y)) = self.window_position {
+            contents.push_str(&format!("window_position={},{}\n", x, y)[..]);
+        }
+        contents.push_str("window_width=");
+        contents.push_str(&self.window_width.to_string()[..]);
+        contents.push('\n');
+        contents.push_str("window_height=");
+        contents.push_str(&self.window_height.to_string()[..]);
+        contents.push('\n');
+        contents.push_str("tree_width=");
+        contents.push_str(&self.tree_width.to_string()[..]);
+        contents.push('\n');
+        contents.push_str("ui_scale=");
+        contents.push_str(&self.ui_scale.to_string()[..]);
+        contents.push('\n');
+
+        fs::write(settings_path(), contents.as_bytes())
+    }
+
+    fn remember_recent_file(&mut self, path: &Path) -> () {
+        let path: String = path.display().to_string();
+        self.recent_files.retain(|p| *p != path);
+        self.recent_files.insert(0, path);
+        self.recent_files.truncate(RECENT_FILES_LIMIT);
+    }
+}
+
+fn settings_path() -> PathBuf {
+    // next to the executable, so launching from another directory finds the same settings
+    match env::current_exe() {
+        Ok(exe) => exe.with_file_name(SETTINGS_FILE),
+        Err(_) => PathBuf::from(SETTINGS_FILE),
+    }
+}
+
+fn alert_settings_not_saved(e: std::io::Error) -> () {
+    dialog::alert_default(
+        &format!(
+            "Settings could not be saved to {}: {}",
+            settings_path().display(),
+            e
+        )[..],
+    );
+}
+
+fn main() -> Result<(), ()> {
+    unsafe {
+        env::set_var("RUST_BACKTRACE", "1");
+    };
+    entry_point()?;
+    Ok(())
+}
+
+/*
+fn load_db_into_tree(
+    tree: &mut Tree,
+) -> Result<()> {
+    let rs = query(
+        &Connection::open(DB_PATH).unwrap(),
+        "SELECT name FROM sqlite_schema WHERE type ='table' AND name NOT LIKE 'sqlite_%';",
+        &[]);
+    for x in rs.records {
+        for y in x.fields {
+            tree.add(&y.to_string()[..]);
+        }
+    }
+    Ok(())
+}
+ */
+
+fn entry_point() -> Result<(), ()> {
+    let mut f: AppContext = AppContext::new();
+    f.construct();
+
+    match widget_from_id::<DoubleWindow>("main_window") {
+        Some(mut mainwindow) => {
+            mainwindow.show();
+            f.event_loop()
+        }
+        None => Ok(()),
+    }
+}
+
+fn apply_ui_scale(scale: f32) -> () {
+    // going back to 0 only takes effect on the next start, FLTK keeps the last scale set
+    if scale <= 0.0 {
+        return;
+    }
+
+    for screen in 0..app::screen_count() {
+        app::set_screen_scale(screen, scale);
+    }
+}
+
+fn position_on_screen(x: i32, y: i32) -> bool {
+    // a saved position can point at a monitor that has since been unplugged
+    let (sx, sy, sw, sh): (i32, i32, i32, i32) = app::screen_work_area(app::screen_num(x, y));
+    x >= sx && y >= sy && x < sx + sw && y < sy + sh
+}
+
+fn fit_canvas_to_width(scroll: &Scroll) -> Option<()> {
+    // the header inputs and the side sections stretch to the canvas, the layer table doesn't
+    let content_width: i32 = scroll.w() - app::scrollbar_size() - 10;
+
+    let mut header_info_pack: Pack = widget_from_id::<Pack>("header_info_pack")?;
+    let header_width: i32 = content_width.max(600);
+    header_info_pack.set_size(header_width, header_info_pack.h());
+    for i in 0..header_info_pack.children() {
+        let mut inner_pack: Pack = WidgetBase::from_dyn_widget(&header_info_pack.child(i)?)?;
+        inner_pack.set_size(header_width, inner_pack.h());
+        // label frame is 150 wide with 5 of spacing before the input
+        let mut input: Widget = inner_pack.child(1)?;
+        input.set_size(header_width - 155, input.h());
+    }
+
+    let mut side_pack: Pack = widget_from_id::<Pack>("canvas_side_pack")?;
+    let side_offset: i32 = side_pack.x() - scroll.x() + scroll.xposition();
+    side_pack.set_size((content_width - side_offset).max(600), side_pack.h());
+
+    Some(())
+}
+
+fn scroll_focus_into_view() -> Option<()> {
+    let mut scroll: Scroll = widget_from_id::<Scroll>("sub_pack_scroll")?;
+    let focus = app::focus()?;
+    if !focus.inside(&scroll) {
+        return None;
+    }
+
+    // how far the focused widget sticks out past each edge, leaving room for the scrollbars
+    let bar: i32 = app::scrollbar_size();
+    let offset = |start: i32, size: i32, view_start: i32, view_size: i32| -> i32 {
+        if start < view_start {
+            start - view_start
+        } else if start + size > view_start + view_size - bar {
+            (start + size - (view_start + view_size - bar)).min(start - view_start)
+        } else {
+            0
+        }
+    };
+    let dx: i32 = offset(focus.x(), focus.w(), scroll.x(), scroll.w());
+    let dy: i32 = offset(focus.y(), focus.h(), scroll.y(), scroll.h());
+    if dx == 0 && dy == 0 {
+        return Some(());
+    }
+
+    scroll.scroll_to(scroll.xposition() + dx, scroll.yposition() + dy);
+    Some(scroll.redraw())
+}
+
+fn clear_scroll_gui() -> () {
+    let header_info_pack = widget_from_id::<Pack>("header_info_pack").unwrap();
+    let comp_pack = widget_from_id::<Pack>("comp_pack").unwrap();
+    println!("clearing scroll GUI!");
+    for _ in 0..header_info_pack.children() {
+        let sub_pack_widget = header_info_pack.child(0).unwrap();
+        let wi: Option<Pack> = fltk::prelude::WidgetBase::from_dyn_widget(&sub_pack_widget);
+        let input_widget = wi.unwrap().child(1).unwrap();
+        let wi: Option<Input> = fltk::prelude::WidgetBase::from_dyn_widget(&input_widget);
+        wi.unwrap().set_value("");
+    }
+
+    for i in 0..comp_pack.children() {
+        let sub_pack: Option<Pack> =
+            fltk::prelude::WidgetBase::from_dyn_widget(&comp_pack.child(i).unwrap());
+        let sub_pack: Pack = sub_pack.unwrap();
+        let checkbutton = sub_pack.child(2).unwrap();
+        let wi: Option<CheckButton> = fltk::prelude::WidgetBase::from_dyn_widget(&checkbutton);
+        let mut c: CheckButton = wi.unwrap();
+        c.set_checked(false);
+        c.activate();
+        if let Some(required_by) = sub_pack.child(3) {
+            let wi: Option<Frame> = fltk::prelude::WidgetBase::from_dyn_widget(&required_by);
+            if let Some(mut f) = wi {
+                f.set_label("");
+                f.set_frame(FrameType::EngravedBox);
+            }
+        }
+    }
+
+    if let Some(mut history) = widget_from_id::<HoldBrowser>("history_browser") {
+        history.clear();
+    }
+    if let Some(mut action_costs) = widget_from_id::<HoldBrowser>("action_costs_browser") {
+        action_costs.clear();
+    }
+
+    if let Some(mut avatar_choice) = widget_from_id::<Choice>("avatar_choice") {
+        avatar_choice.set_value(0);
+    }
+    if let Some(mut avatar_preview) = widget_from_id::<Frame>("avatar_preview") {
+        avatar_preview.set_image(None::<PngImage>);
+        avatar_preview.set_label("");
+        avatar_preview.redraw();
+    }
+
+    if let Some(mut template_browser) = widget_from_id::<HoldBrowser>("template_browser") {
+        template_browser.clear();
+    }
+    if let Some(mut template_frame) = widget_from_id::<Frame>("template_frame") {
+        template_frame.set_label("Template: none");
+    }
+}
+
+fn fill_scroll_gui(eid: &String, db: &Connection) -> () {
+    clear_scroll_gui();
+
+    match parse_entity_id(&eid).and_then(|id| Repository::new(db).entity(id)) {
+        Ok(Some(entity)) => {
+            // the header inputs are named after the columns they show
+            for (column, value) in entity_base_columns(&entity) {
+                if let Some(mut input_box) = widget_from_id::<Input>(column) {
+                    input_box.set_value(&value[..]);
+                }
+            }
+        }
+        Ok(None) => (),
+        Err(e) => alert_read_error(eid, e),
+    }
+    fill_entity_row_version(db, eid);
+
+    fill_entity_sections(eid, db);
+}
+
+// everything on the canvas below the header inputs
+fn fill_entity_sections(eid: &String, db: &Connection) -> () {
+    fill_comp_checks(db, eid);
+    fill_avatar_section(db, eid);
+    fill_identity_section(db, eid);
+    fill_action_costs_section(db, eid);
+    fill_action_driven_layers(db, eid);
+    fill_template_status(db, eid);
+    fill_history_browser(db, eid);
+
+    widget_from_id::<Scroll>("sub_pack_scroll")
+        .unwrap()
+        .redraw();
+}
+
+fn slice_end_of_string(s: String, delim: &str) -> String {
+    let mut x: Vec<&str> = s.split(delim).collect();
+
+    match x.pop() {
+        Some(y) => y.to_string(),
+        None => String::new(),
+    }
+}
+
+fn slice_beginning_of_string(s: String, delim: &str) -> String {
+    let mut x: Vec<&str> = s.split(delim).collect();
+    x.reverse();
+    match x.pop() {
+        Some(y) => y.to_string(),
+        None => String::new(),
+    }
+}
+
+fn locate_cold_storage() -> Option<PathBuf> {
+    let current_directory: PathBuf = match current_dir() {
+        Ok(p) => p,
+        Err(_) => PathBuf::new(),
+    };
+
+    match FileDialog::new()
+        .set_location(&current_directory)
+        .add_filter("DB", &["db"])
+        .show_open_single_file()
+    {
+        Ok(p) => p,
+        Err(_) => None,
+    }
+}
+
+fn fill_recent_files_menu(settings: &Settings, app_sender: Sender<Message>) -> Option<()> {
+    let mut menu: MenuBar = widget_from_id::<MenuBar>("main_menu")?;
+    let index: i32 = menu.find_index("File/Open Recent");
+    if index < 0 {
+        return None;
+    }
+    menu.clear_submenu(index).ok()?;
+
+    for path in settings.recent_files.iter() {
+        // slashes would be read as submenus, backslashes as escapes and '&' as a shortcut marker
+        let label: String = path
+            .replace('\\', "\\\\")
+            .replace('/', "\\/")
+            .replace('&', "&&");
+        let path: String = path.clone();
+        let app_sender_clone: Sender<Message> = app_sender.clone();
+        menu.add(
+            &format!("File/Open Recent/{}", label)[..],
+            Shortcut::None,
+            MenuFlag::Normal,
+            move |_| {
+                app_sender_clone.send(Message::OpenRecentDatabase(path.clone()));
+            },
+        );
+    }
+
+    Some(())
+}
+
+#[allow(dead_code)]
+fn print_tree_items(tree: &mut Tree) -> () {
+    match tree.get_items() {
+        Some(v) => {
+            for i in v {
+                match tree.item_pathname(&i) {
+                    Ok(s) => println!("item pathname: {}", s),
+                    Err(_) => println!("Can't find item pathname"),
+                }
+            }
+        }
+        None => {
+            println!("No items in tree");
+        }
+    };
+}
+
+#[allow(dead_code)]
+fn print_all_tree_items() -> () {
+    let tree_object: Tree = widget_from_id::<Tree>("main_window_tree").unwrap();
+    match tree_object.find_item("Mob") {
+        Some(ti_mob) => {
+            for i in 0..=ti_mob.children() {
+                match ti_mob.child(i) {
+                    Some(ti) => {
+                        println!("tree_item: x: {}, y: {}", ti.x(), ti.y());
+                        match ti.try_widget() {
+                            Some(b) => println!(
+                                "input pos: x:{}, y:{} -- input size: w: {}, h: {}",
+                                b.x(),
+                                b.y(),
+                                b.w(),
+                                b.h()
+                            ),
+                            None => {
+                                println!("no button inner widget found")
+                            }
+                        }
+                    }
+                    None => {}
+                }
+            }
+        }
+        None => {}
+    }
+}
+
+fn _on_search_fill_category_with_items() -> () {
+    let mut tree_object: Tree = widget_from_id::<Tree>("main_window_tree").unwrap();
+    for x in 0..=20 {
+        let mut s: String = String::from("Mob");
+        s.push_str("/Mob");
+        s.push_str(&x.to_string()[..]);
+
+        let tree_item = TreeItem::new(&tree_object, &x.to_string()[..]);
+
+        tree_object.add_item(&s[..], &tree_item);
+        /*
+                let mut b: Input = Input::default()
+                    .with_size(120, tree_item.label_h())
+                    .with_label("test")
+                    .with_id(&s[..]);
+
+                b.set_value("WHERE AM I");
+        */
+    }
+}
+
+fn build_out_creation_categories(db: &Connection, app_sender: Sender<Message>) -> () {
+    match widget_from_id::<Tree>("main_window_tree") {
+        Some(mut tree) => {
+            tree.begin();
+            let tree_root: TreeItem = tree.root().unwrap();
+            let count_of_children: i32 = tree_root.children();
+            for x in 0..count_of_children {
+                let child: TreeItem = tree_root.child(x).unwrap();
+                let child_pathname: String = tree.item_pathname(&child).unwrap();
+                let mut new_tree_item: TreeItem = TreeItem::new(&tree, "quick_search");
+
+                new_tree_item.draw_item_content(|ti, render| {
+                    let dims: (i32, i32, i32, i32) =
+                        (ti.label_x(), ti.label_y(), ti.label_w(), ti.label_h());
+                    // If the widget is visible 'render'
+                    if render {
+                        if let Some(pack) = ti.try_widget() {
+                            // fetch the nested widget out of TreeItem and cast it to a Pack
+                            let mut pack: Pack = unsafe { pack.into_widget::<Pack>() };
+                            pack.set_pos(dims.0, dims.1);
+                            pack.set_size(dims.2, dims.3);
+
+                            let mut dims: (i32, i32, i32, i32) =
+                                (pack.x(), pack.y(), pack.w(), pack.h());
+                            pack.set_color(Color::Gray0);
+
+                            for i in 0..pack.children() {
+                                if let Some(mut child) = pack.child(i) {
+                                    child.set_pos(dims.0, dims.1);
+                                    child.set_size(dims.2 - 100, dims.3 / pack.children());
+                                    dims.1 += dims.3;
+                                }
+                            }
+                        }
+                    }
+                    let (label_w, _): (i32, i32) = draw::measure(&ti.label().unwrap()[..], true);
+                    return dims.0 + label_w;
+                });
+
+                let mut new_path: String = child.label().unwrap();
+                new_path.push_str("/");
+                new_path.push_str("quick_search");
+
+                match tree.add_item(&new_path, &new_tree_item) {
+                    Some(mut ti) => {
+                        ti.set_label_size(ti.label_size() * 5);
+
+                        let mut hg: Pack = Pack::new(
+                            ti.label_x(),
+                            ti.label_h(),
+                            ti.label_w(),
+                            ti.label_h() * 2,
+                            "",
+                        );
+
+                        hg.set_frame(FrameType::ThinUpFrame);
+
+                        hg.add(&Frame::new(
+                            hg.x(),
+                            hg.y(),
+                            hg.width(),
+                            hg.height(),
+                            "Quick Lookup",
+                        ));
+
+                        // enter in the lookup runs the search, same as the button
+                        let mut lookup: Input =
+                            Input::new(hg.x(), hg.y(), hg.width(), hg.height(), "");
+                        lookup.set_trigger(CallbackTrigger::EnterKeyAlways);
+
+                        let tree_item: TreeItem = ti.clone();
+                        let app_sender_clone: Sender<Message> = app_sender.clone();
+
+                        lookup.set_callback(move |_| {
+                            app_sender_clone.send(Message::SearchEntities(tree_item.clone()));
+                        });
+
+                        hg.add(&lookup);
+                        let mut button_id: String = String::from(child_pathname.clone());
+                        button_id.push_str("_search_button");
+
+                        let mut button: Button =
+                            Button::new(hg.x(), hg.y(), hg.width(), hg.height(), "Search Entity")
+                                .with_id(&button_id[..]);
+
+                        let tree_item: TreeItem = ti.clone();
+                        let app_sender_clone: Sender<Message> = app_sender.clone();
+
+                        button.set_callback(move |_| {
+                            let tree_item: TreeItem = tree_item.clone();
+                            app_sender_clone.send(Message::SearchEntities(tree_item));
+                        });
+
+                        hg.add(&button);
+
+                        button_id = String::from(child_pathname.clone());
+                        button_id.push_str("_clear_button");
+
+                        button = Button::new(hg.x(), hg.y(), hg.width(), hg.height(), "Clear List");
+
+                        let tree_item: TreeItem = ti.clone();
+                        let app_sender_clone: Sender<Message> = app_sender.clone();
+
+                        button.set_callback(move |_| {
+                            let tree_item: TreeItem = tree_item.clone();
+                            app_sender_clone.send(Message::ClearEntities(tree_item));
+                        });
+
+                        hg.add(&button);
+
+                        button_id = String::from(child_pathname.clone());
+                        button_id.push_str("_add_button");
+
+                        button = Button::new(hg.x(), hg.y(), hg.width(), hg.height(), "New Entity");
+
+                        let tree_item: TreeItem = ti.clone();
+                        let app_sender_clone: Sender<Message> = app_sender.clone();
+
+                        button.set_callback(move |_| {
+                            new_entity_from_tree(&tree_item, &app_sender_clone);
+                        });
+
+                        hg.add(&button);
+
+                        // template picker used by "New Entity", filtered to this core type
+                        let mut choice_id: String = String::from(child_pathname.clone());
+                        choice_id.push_str("_template_choice");
+
+                        let mut template_choice: Choice =
+                            Choice::new(hg.x(), hg.y(), hg.width(), hg.height(), "")
+                                .with_id(&choice_id[..]);
+                        fill_template_choice(db, &mut template_choice, child.label().unwrap());
+
+                        hg.add(&template_choice);
+
+                        hg.end();
+                        ti.set_widget(&hg);
+                    }
+                    None => {
+                        println!("Failed to add tree item");
+                    }
+                }
+            }
+            tree.end();
+        }
+        None => {}
+    }
+}
+
+fn get_entity_id_from_tree(tree_item: &TreeItem) -> Option<String> {
+    let parent_pack: Pack = WidgetBase::from_dyn_widget(&tree_item.try_widget()?)?;
+    let input: Input = WidgetBase::from_dyn_widget(&parent_pack.child(1)?)?;
+
+    Some(input.value())
+}
+
+fn new_entity_from_tree(tree_item: &TreeItem, app_sender: &Sender<Message>) -> Option<()> {
+    let eid: String = get_entity_id_from_tree(tree_item)?;
+    if eid.is_empty() {
+        return None;
+    }
+
+    app_sender.send(Message::NewEntity(NEPackage {
+        eid,
+        template_id: get_template_id_from_tree(tree_item),
+    }));
+    Some(())
+}
+
+fn get_template_id_from_tree(tree_item: &TreeItem) -> Option<String> {
+    // the picker is registered under its category's pathname when the quick search is built
+    let tree: Tree = widget_from_id::<Tree>("main_window_tree")?;
+    let mut choice_id: String = tree.item_pathname(&tree_item.parent()?).ok()?;
+    choice_id.push_str("_template_choice");
+    let choice: Choice = widget_from_id::<Choice>(&choice_id[..])?;
+
+    // index 0 is "none"
+    match choice.value() {
+        i if i > 0 => Some(slice_beginning_of_string(choice.choice()?, ":")),
+        _ => None,
+    }
+}
+
+fn entity_search_job(t: TreeItem, offset: usize, generation: u64) -> Option<DbJob> {
+    let input_value: String = get_entity_id_from_tree(&t)?;
+    let parent: TreeItem = t.parent()?;
+    let selected_string: String = parent.label().unwrap_or(String::new());
+
+    let x: String = slice_beginning_of_string(selected_string, "/");
+
+    Some(DbJob::SearchEntities(t, x, input_value, offset, generation))
+}
+
+fn spawn_db_worker(db_path: &Path, app_sender: Sender<Message>) -> Option<mpsc::Sender<DbJob>> {
+    let (job_sender, job_receiver) = mpsc::channel::<DbJob>();
+    let db_path: PathBuf = db_path.to_path_buf();
+
+    let spawned = thread::Builder::new()
+        .name(String::from("db_worker"))
+        .spawn(move || {
+            // a connection can't be shared across threads, so the worker opens its own
+            let db: Connection = match open_connection(&db_path) {
+                Ok(c) => c,
+                Err(e) => {
+                    app_sender.send(Message::DbWorkerFailed(e.to_string()));
+                    app::awake();
+                    return;
+                }
+            };
+
+            for job in job_receiver {
+                app_sender.send(run_db_job(&db, job));
+                app::awake();
+            }
+        });
+
+    match spawned {
+        Ok(_) => Some(job_sender),
+        Err(e) => {
+            app_sender.send(Message::DbWorkerFailed(e.to_string()));
+            None
+        }
+    }
+}
+
+fn open_connection(db_path: &Path) -> Result<Connection, Error> {
+    // the gui and the worker each hold a connection, and other programs may be writing too
+    let db: Connection = Connection::open(db_path)?;
+    db.busy_timeout(DB_BUSY_TIMEOUT)?;
+
+    Ok(db)
+}
+
+fn run_db_job(db: &Connection, job: DbJob) -> Message {
+    // only reads happen here, every write stays on the gui thread's connection
+    match job {
+        DbJob::SearchEntities(t, category, entity_id, offset, generation) => {
+            let entities: Result<Vec<EntityBase>, RowError> = match entity_id.len() {
+                0 => fetch_all_entity_base_data(db, category, offset),
+                _ => fetch_specific_entity_base_data(entity_id, db, category),
+            };
+            match entities {
+                Ok(entities) => Message::EntitySearchDone(t, entities, offset, generation),
+                Err(e) => Message::DbJobFailed(format!("The search failed: {}", e)),
+            }
+        }
+        DbJob::CompareEntities(eids) => match build_compare_rows(db, &eids) {
+            Ok(rows) => Message::CompareRowsReady(eids, rows),
+            Err(e) => Message::DbJobFailed(format!("The entities could not be compared: {}", e)),
+        },
+        DbJob::Validate => match run_validation(db) {
+            Ok(problems) => Message::ValidationDone(problems),
+            Err(e) => Message::DbJobFailed(format!("Validation could not finish: {}", e)),
+        },
+        DbJob::Export(eids, path) => match export_entities_to_file(db, &eids, &path) {
+            Ok(count) => Message::ExportDone(path, count),
+            Err(e) => Message::DbJobFailed(e),
+        },
+    }
+}
+
+fn show_busy(pending: usize) -> Option<()> {
+    let mut indicator: Frame = widget_from_id::<Frame>("busy_indicator")?;
+    let mut main_window: DoubleWindow = widget_from_id::<DoubleWindow>("main_window")?;
+
+    match pending {
+        0 => {
+            indicator.set_label("");
+            main_window.set_cursor(Cursor::Default);
+        }
+        n => {
+            indicator.set_label(&format!("Working... ({})", n)[..]);
+            main_window.set_cursor(Cursor::Wait);
+        }
+    }
+
+    Some(indicator.redraw())
+}
+
+fn rows_or_report<T>(rows: Result<Vec<T>, RowError>) -> Vec<T> {
+    // a row that doesn't fit its model is shown and that part of the gui is left empty.
+    // gui thread only, worker jobs hand their errors back in a Message instead
+    match rows {
+        Ok(v) => v,
+        Err(e) => {
+            dialog::alert_default(&format!("Some data could not be read: {}", e)[..]);
+            Vec::new()
+        }
+    }
+}
+
+fn value_or_report<T>(value: Result<T, RowError>) -> Option<T> {
+    // same as rows_or_report for a single value, an error is shown and read as nothing
+    match value {
+        Ok(v) => Some(v),
+        Err(e) => {
+            dialog::alert_default(&format!("Some data could not be read: {}", e)[..]);
+            None
+        }
+    }
+}
+
+fn fetch_entity_categories(conn: &Connection) -> Result<Vec<EntityCoreType>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = &[];
+    query_as(
+        conn,
+        "SELECT 'c'.'entity_core_type_id', 'c'.'name' FROM 'entity_core_types' as 'c';",
+        &x,
+    )
+}
+
+fn fetch_entity_category_names(conn: &Connection) -> Vec<String> {
+    rows_or_report(fetch_entity_categories(conn))
+        .into_iter()
+        .map(|category| category.name)
+        .collect()
+}
+
+fn fetch_pragma_entity_base_defs(conn: &Connection) -> Result<Vec<TableColumn>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = &[];
+    query_as(
+        conn,
+        r#"PRAGMA "main".TABLE_INFO("entity_base_definitions");"#,
+        &x,
+    )
+}
+
+fn entity_base_columns(entity: &EntityBase) -> [(&'static str, String); 4] {
+    [
+        ("entity_base_id", entity.id.to_string()),
+        ("name", entity.name.clone()),
+        ("entity_core_type_id", entity.core_type_id.to_string()),
+        ("entity_sub_type_id", entity.sub_type_id.to_string()),
+    ]
+}
+
+fn fetch_all_entity_base_data(
+    conn: &Connection,
+    ect: String,
+    offset: usize,
+) -> Result<Vec<EntityBase>, RowError> {
+    // one row past the page so the caller can tell whether another page follows
+    let limit: i64 = ENTITY_PAGE_SIZE as i64 + 1;
+    let offset: i64 = offset as i64;
+    let x: &[(&str, &dyn ToSql)] =
+        named_params! { ":ect": ect, ":limit": limit, ":offset": offset };
+    query_as(
+        conn,
+        "SELECT 'e'.'entity_base_id', 'e'.'name', 'e'.'entity_core_type_id', 'e'.'entity_sub_type_id' FROM 'entity_base_definitions' as 'e' WHERE 'e'.'entity_core_type_id' IN (SELECT 'e'.'entity_core_type_id' FROM 'entity_core_types' as 'e' WHERE 'e'.'name' = :ect) ORDER BY 'e'.'entity_base_id' ASC LIMIT :limit OFFSET :offset;",
+        &x,
+    )
+}
+
+fn fetch_specific_entity_base_data(
+    v: String,
+    conn: &Connection,
+    ect: String,
+) -> Result<Vec<EntityBase>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":ead": v, ":ect": ect };
+    query_as(
+        conn,
+        "SELECT 'e'.'entity_base_id', 'e'.'name', 'e'.'entity_core_type_id', 'e'.'entity_sub_type_id' FROM 'entity_base_definitions' as 'e' WHERE 'e'.'entity_base_id' = :ead AND 'e'.'entity_core_type_id' IN (SELECT 'e'.'entity_core_type_id' FROM 'entity_core_types' as 'e' WHERE 'e'.'name' = :ect)",
+        &x,
+    )
+}
+
+fn fetch_entity_comp_layers_general(conn: &Connection) -> Result<Vec<CompositionLayer>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = &[];
+    query_as(
+        conn,
+        "SELECT entity_composition_layer_id, name, resource FROM entity_composition_layers ORDER BY COALESCE(sort_order, entity_composition_layer_id) ASC, entity_composition_layer_id ASC;",
+        &x,
+    )
+}
+
+fn fetch_entity_comp_layer_ids(conn: &Connection, eid: &String) -> Vec<String> {
+    rows_or_report(
+        parse_entity_id(eid).and_then(|id| Repository::new(conn).entity_composition_layers(id)),
+    )
+    .into_iter()
+    .map(|layer| layer.id.to_string())
+    .collect()
+}
+
+fn fill_tree_with_entity_data(
+    mut entities: Vec<EntityBase>,
+    mut ti: TreeItem,
+    offset: usize,
+) -> Option<()> {
+    let mut t: Tree = ti.tree()?;
+    match offset {
+        0 => clear_entities_from_tree(&mut ti)?,
+        _ => remove_load_more_item(&mut t, &ti),
+    }
+
+    let more: bool = entities.len() > ENTITY_PAGE_SIZE;
+    entities.truncate(ENTITY_PAGE_SIZE);
+
+    let mut ti_path: String = t.item_pathname(&ti).ok()?;
+    ti_path.push('/');
+
+    // plain labels, clicks are picked up by the tree's callback in on_tree_item_selected
+    for entity in entities {
+        let label: String = String::from_iter([&entity.id.to_string()[..], ":", &entity.name[..]]);
+        let new_tree_item: TreeItem = TreeItem::new(&t, &label[..]);
+        t.add_item(&ti_path[..], &new_tree_item);
+    }
+
+    if more {
+        let mut load_more: TreeItem = TreeItem::new(&t, LOAD_MORE_LABEL);
+        load_more.set_label_fgcolor(Color::DarkBlue);
+        t.add_item(&ti_path[..], &load_more);
+    }
+
+    Some(t.redraw())
+}
+
+fn remove_load_more_item(t: &mut Tree, ti: &TreeItem) -> () {
+    for index in (0..ti.children()).rev() {
+        if let Some(child) = ti.child(index) {
+            if child.label() == Some(String::from(LOAD_MORE_LABEL)) {
+                let _ = t.remove(&child);
+            }
+        }
+    }
+}
+
+fn listed_entity_count(ti: &TreeItem) -> usize {
+    (0..ti.children())
+        .filter_map(|index| ti.child(index))
+        .filter(|child| child.label() != Some(String::from(LOAD_MORE_LABEL)))
+        .count()
+}
+
+fn on_tree_item_selected(t: &mut Tree, sender: &Sender<Message>) -> Option<()> {
+    match t.callback_reason() {
+        TreeReason::Selected => (),
+        _ => return None,
+    }
+
+    // only search results react, they sit under a category's "quick_search" item
+    let item: TreeItem = t.callback_item()?;
+    let parent: TreeItem = item.parent()?;
+    if parent.label() != Some(String::from("quick_search")) {
+        return None;
+    }
+
+    // the selection is drawn by highlight_selected_entities, so the tree's own is dropped
+    let _ = t.deselect_all(None, false);
+
+    let label: String = item.label()?;
+    if label == LOAD_MORE_LABEL {
+        sender.send(Message::LoadMoreEntities(parent));
+        return Some(());
+    }
+
+    let eid: String = slice_beginning_of_string(label, ":");
+    // ctrl toggles one result in or out, shift extends from the last click
+    if app::is_event_ctrl() {
+        sender.send(Message::EntityFrameToggled(eid));
+    } else if app::is_event_shift() {
+        sender.send(Message::EntityFrameRangeSelected(eid));
+    } else {
+        sender.send(Message::EntityFrameClicked(eid));
+    }
+
+    Some(())
+}
+
+fn clear_entities_from_tree(ti: &mut TreeItem) -> Option<()> {
+    let mut t: Tree = ti.tree()?;
+    ti.clear_children();
+
+    Some(t.redraw())
+}
+
+fn new_entity(db: &Connection, package: &NEPackage) -> Result<(), Error> {
+    let template: Option<TPackage> = match &package.template_id {
+        Some(tid) => fetch_template_header(db, tid),
+        None => None,
+    };
+    let eid: i64 = parse_id("entity_base_id", &package.eid).map_err(read_failed)?;
+
+    // the entity and everything it inherits land together or not at all
+    savepoint(db, || {
+        let repository: Repository = Repository::new(db);
+        let created: bool = match &template {
+            Some(t) => repository.create_entity(&EntityBase {
+                id: eid,
+                name: String::from_iter([&t.name[..], " ", &package.eid[..]]),
+                core_type_id: parse_id("entity_core_type_id", &t.ecid).map_err(read_failed)?,
+                sub_type_id: parse_id("entity_sub_type_id", &t.esid).map_err(read_failed)?,
+            })?,
+            None => repository.create_blank_entity(eid)?,
+        };
+
+        match (created, &template) {
+            (true, Some(t)) => apply_template(db, &package.eid, &t.tid),
+            _ => Ok(()),
+        }
+    })
+}
+
+fn delete_entity(db: &Connection, eid: &String, seen: Option<i64>) -> Option<String> {
+    // returns why the entity was kept
+    let before: Option<String> = parse_entity_id(eid)
+        .and_then(|id| Repository::new(db).entity(id))
+        .ok()
+        .flatten()
+        .map(|e| e.name);
+
+    // the entity and every row hanging off it go together or not at all. the version is
+    // checked up front since the child deletes move it on
+    let result: Result<(), Error> = savepoint(db, || {
+        check_entity_version(db, eid, seen)?;
+        for table in ENTITY_CHILD_TABLES {
+            db.execute(
+                &format!("DELETE FROM {} WHERE entity_base_id = ?1;", table)[..],
+                (eid,),
+            )?;
+        }
+
+        match db.execute(
+            "DELETE FROM entity_base_definitions WHERE entity_base_id = ?1;",
+            (eid,),
+        )? {
+            0 => Err(Error::StatementChangedRows(0)),
+            _ => record_audit(
+                db,
+                AuditPackage {
+                    table_name: "entity_base_definitions",
+                    row_key: eid.clone(),
+                    column_name: Some("name"),
+                    before,
+                    after: None,
+                    entity_id: Some(eid.clone()),
+                },
+            ),
+        }
+    });
+
+    match result {
+        Ok(_) => None,
+        Err(Error::StatementChangedRows(0)) => {
+            match fetch_row_version(db, "entity_base_definitions", eid) {
+                Ok(Some(_)) => Some(format!(
+                    "Entity {} was changed by someone else since it was loaded, reload it and delete again",
+                    eid
+                )),
+                Ok(None) => Some(format!("Entity {} no longer exists", eid)),
+                Err(e) => Some(format!("Entity {} could not be read: {}", eid, e)),
+            }
+        }
+        Err(e) => Some(format!("error with code {:?}", e)),
+    }
+}
+
+fn fetch_next_entity_id(conn: &Connection) -> Result<String, RowError> {
+    let x: &[(&str, &dyn ToSql)] = &[];
+    let next: Option<IntValue> = query_one_as(
+        conn,
+        "SELECT COALESCE(MAX('e'.'entity_base_id'), 0) + 1 as 'value' FROM 'entity_base_definitions' as 'e';",
+        &x,
+    )?;
+
+    Ok(next.map_or(1, |n| n.value).to_string())
+}
+
+fn fetch_entity_name_taken(conn: &Connection, name: &String) -> Result<bool, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":name": name };
+    let taken: Option<IntValue> = query_one_as(
+        conn,
+        "SELECT 'e'.'entity_base_id' as 'value' FROM 'entity_base_definitions' as 'e' WHERE 'e'.'name' = :name;",
+        &x,
+    )?;
+
+    Ok(taken.is_some())
+}
+
+fn fetch_entity_export(conn: &Connection, eid: &String) -> Result<Option<EXPackage>, RowError> {
+    let repository: Repository = Repository::new(conn);
+    let id: i64 = parse_entity_id(eid)?;
+    let entity: EntityBase = match repository.entity(id)? {
+        Some(entity) => entity,
+        None => return Ok(None),
+    };
+
+    Ok(Some(EXPackage {
+        header: entity_header(entity),
+        comp_layers: repository
+            .entity_composition_layers(id)?
+            .iter()
+            .map(|l| l.id.to_string())
+            .collect(),
+        attributes: repository
+            .entity_attribute_values(id)?
+            .iter()
+            .map(|a| (a.attribute_id.to_string(), a.value.to_string()))
+            .collect(),
+        actions: repository
+            .entity_actions(id)?
+            .iter()
+            .map(|a| a.id.to_string())
+            .collect(),
+        avatar: fetch_entity_avatar(conn, eid)?,
+        identity_type: fetch_entity_identity_type(conn, eid)?,
+        template: fetch_entity_template_link(conn, eid)?,
+    }))
+}
+
+fn export_entities_to_file(db: &Connection, eids: &[String], path: &Path) -> Result<usize, String> {
+    // runs on the worker, so failures come back as the message to show
+    let unreadable = |e: RowError| format!("The entities could not be read: {}", e);
+    let eids: Vec<String> = match eids.is_empty() {
+        true => Repository::new(db)
+            .entities()
+            .map_err(unreadable)?
+            .iter()
+            .map(|e| e.id.to_string())
+            .collect(),
+        false => eids.to_vec(),
+    };
+    let mut packages: Vec<EXPackage> = Vec::new();
+    for eid in eids.iter() {
+        if let Some(package) = fetch_entity_export(db, eid).map_err(unreadable)? {
+            packages.push(package);
+        }
+    }
+
+    match fs::write(path, format_entity_export(&packages).as_bytes()) {
+        Ok(_) => Ok(packages.len()),
+        Err(e) => Err(format!("The export could not be written: {}", e)),
+    }
+}
+
+fn write_entity_export(db: &Connection, package: &EXPackage) -> Result<(), Error> {
+    // goes through the normal write paths, so only what differs is written and audited
+    let header: &NPackage = &package.header;
+    let eid: &String = &header.eid;
+
+    let created: usize = db.execute(
+        "INSERT INTO entity_base_definitions (
+            entity_base_id, name, entity_core_type_id, entity_sub_type_id
+        ) VALUES (
+            ?1, ?2, ?3, ?4
+        ) ON CONFLICT (entity_base_id) DO NOTHING;",
+        (eid, &header.name, &header.ecid, &header.esid),
+    )?;
+    match created {
+        0 => {
+            let unchanged: bool = match fetch_entity_header(db, eid).map_err(read_failed)? {
+                Some(h) => h.name == header.name && h.ecid == header.ecid && h.esid == header.esid,
+                None => false,
+            };
+            if !unchanged {
+                write_entity_header(db, header.clone(), None)?;
+            }
+        }
+        _ => record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_base_definitions",
+                row_key: eid.clone(),
+                column_name: None,
+                before: None,
+                after: Some(String::from("created")),
+                entity_id: Some(eid.clone()),
+            },
+        )?,
+    }
+
+    let current: Option<EXPackage> = fetch_entity_export(db, eid).map_err(read_failed)?;
+    if let Some(current) = &current {
+        // a layer still required by another one on its way out is disabled after that one,
+        // a round that disables nothing means the rules keep what's left
+        let mut leaving: Vec<String> = current
+            .comp_layers
+            .iter()
+            .filter(|comp_id| !package.comp_layers.contains(comp_id))
+            .cloned()
+            .collect();
+        while !leaving.is_empty() {
+            let mut kept: Vec<(String, String)> = Vec::new();
+            for comp_id in leaving.iter() {
+                let refused: Option<String> = write_comp_layer_change(
+                    db,
+                    CLPackage {
+                        cb_state: false,
+                        comp_id: comp_id.clone(),
+                        entity_id: eid.clone(),
+                    },
+                )?;
+                if let Some(reason) = refused {
+                    kept.push((comp_id.clone(), reason));
+                }
+            }
+            if kept.len() == leaving.len() {
+                return Err(layer_rules_refused(eid, kept.remove(0).1));
+            }
+            leaving = kept.into_iter().map(|(comp_id, _)| comp_id).collect();
+        }
+        for (attribute_id, _) in current.attributes.iter() {
+            if !package.attributes.iter().any(|(a, _)| a == attribute_id) {
+                write_entity_attribute(db, eid, attribute_id, None)?;
+            }
+        }
+        for action_id in current.actions.iter() {
+            if !package.actions.contains(action_id) {
+                update_entity_actions(
+                    db,
+                    EAPackage {
+                        cb_state: false,
+                        action_id: action_id.clone(),
+                        entity_id: eid.clone(),
+                    },
+                )?;
+            }
+        }
+    }
+
+    for comp_id in package.comp_layers.iter() {
+        let refused: Option<String> = write_comp_layer_change(
+            db,
+            CLPackage {
+                cb_state: true,
+                comp_id: comp_id.clone(),
+                entity_id: eid.clone(),
+            },
+        )?;
+        if let Some(reason) = refused {
+            return Err(layer_rules_refused(eid, reason));
+        }
+    }
+    for (attribute_id, value) in package.attributes.iter() {
+        write_entity_attribute(db, eid, attribute_id, Some(value.clone()))?;
+    }
+    for action_id in package.actions.iter() {
+        update_entity_actions(
+            db,
+            EAPackage {
+                cb_state: true,
+                action_id: action_id.clone(),
+                entity_id: eid.clone(),
+            },
+        )?;
+    }
+    update_entity_avatar(db, eid, package.avatar.clone())?;
+    update_entity_identity_type(db, eid, package.identity_type.clone())?;
+    if let Some(tid) = &package.template {
+        link_entity_template(db, eid, tid)?;
+    }
+
+    Ok(())
+}
+
+fn duplicate_entity(db: &Connection, eid: &String, new_eid: &String) -> Option<String> {
+    // returns why no copy was made
+    match fetch_row_version(db, "entity_base_definitions", new_eid) {
+        Ok(None) => {}
+        Ok(Some(_)) => return Some(format!("Entity {} already exists", new_eid)),
+        Err(e) => return Some(format!("Entity {} could not be read: {}", new_eid, e)),
+    }
+    let mut package: EXPackage = match fetch_entity_export(db, eid) {
+        Ok(Some(p)) => p,
+        Ok(None) => return Some(format!("Entity {} no longer exists", eid)),
+        Err(e) => return Some(format!("Entity {} could not be read: {}", eid, e)),
+    };
+    package.header.eid = new_eid.clone();
+
+    // names are unique, so a second copy of the same entity becomes "copy 2" and so on
+    let base: String = String::from_iter([&package.header.name[..], " copy"]);
+    let mut name: String = base.clone();
+    let mut n: u32 = 1;
+    loop {
+        match fetch_entity_name_taken(db, &name) {
+            Ok(false) => break,
+            Ok(true) => {
+                n += 1;
+                name = format!("{} {}", base, n);
+            }
+            Err(e) => return Some(format!("Entity names could not be read: {}", e)),
+        }
+    }
+    package.header.name = name;
+
+    import_entity_exports(db, &[package])
+}
+
+fn import_entity_exports(db: &Connection, packages: &[EXPackage]) -> Option<String> {
+    // all of the entities land or none of them do
+    let tx: Transaction = match db.unchecked_transaction() {
+        Ok(t) => t,
+        Err(e) => return Some(format!("error with code {:?}", e)),
+    };
+
+    for package in packages {
+        if let Err(e) = write_entity_export(&tx, package) {
+            return Some(format!(
+                "Entity {} could not be imported, nothing was: {}",
+                package.header.eid, e
+            ));
+        }
+    }
+
+    match tx.commit() {
+        Ok(_) => None,
+        Err(e) => Some(format!("error with code {:?}", e)),
+    }
+}
+
+fn format_entity_export(packages: &[EXPackage]) -> String {
+    // "key=value" lines like the settings file, each entity starts at its entity_base_id
+    let mut contents: String = String::new();
+    let mut line = |key: &str, value: &String| {
+        contents.push_str(key);
+        contents.push('=');
+        contents.push_str(&escape_export_value(value)[..]);
+        contents.push('\n');
+    };
+
+    for package in packages {
+        line("entity_base_id", &package.header.eid);
+        line("name", &package.header.name);
+        line("entity_core_type_id", &package.header.ecid);
+        line("entity_sub_type_id", &package.header.esid);
+        for comp_id in package.comp_layers.iter() {
+            line("composition_layer", comp_id);
+        }
+        for (attribute_id, value) in package.attributes.iter() {
+            line(
+                "attribute",
+                &String::from_iter([&attribute_id[..], ":", &value[..]]),
+            );
+        }
+        for action_id in package.actions.iter() {
+            line("action", action_id);
+        }
+        for (key, value) in [
+            ("avatar", &package.avatar),
+            ("identity_type", &package.identity_type),
+            ("template", &package.template),
+        ] {
+            if let Some(value) = value {
+                line(key, value);
+            }
+        }
+    }
+
+    contents
+}
+
+fn escape_export_value(value: &str) -> String {
+    // a name with a line break or '=' in it must not turn into a second line or key
+    let mut escaped: String = String::new();
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '=' => escaped.push_str("\\="),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn unescape_export_value(value: &str) -> String {
+    let mut unescaped: String = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('r') => unescaped.push('\r'),
+                Some(other) => unescaped.push(other),
+                None => unescaped.push('\\'),
+            },
+            c => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+fn parse_entity_export(contents: &str) -> Vec<EXPackage> {
+    let mut packages: Vec<EXPackage> = Vec::new();
+
+    // unknown keys and lines before the first entity are ignored
+    for line in contents.lines() {
+        // keys never hold an '=', so the first one always ends the key
+        let (key, value): (&str, String) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), unescape_export_value(value.trim())),
+            None => continue,
+        };
+
+        if key == "entity_base_id" {
+            packages.push(EXPackage {
+                header: NPackage {
+                    eid: value,
+                    name: String::new(),
+                    ecid: String::new(),
+                    esid: String::new(),
+                },
+                comp_layers: Vec::new(),
+                attributes: Vec::new(),
+                actions: Vec::new(),
+                avatar: None,
+                identity_type: None,
+                template: None,
+            });
+            continue;
+        }
+        let package: &mut EXPackage = match packages.last_mut() {
+            Some(p) => p,
+            None => continue,
+        };
+
+        match key {
+            "name" => package.header.name = value,
+            "entity_core_type_id" => package.header.ecid = value,
+            "entity_sub_type_id" => package.header.esid = value,
+            "composition_layer" => package.comp_layers.push(value),
+            "attribute" => {
+                if let Some((attribute_id, v)) = value.split_once(':') {
+                    package
+                        .attributes
+                        .push((String::from(attribute_id), String::from(v)));
+                }
+            }
+            "action" => package.actions.push(value),
+            // an empty value means the entity has none, not one with an empty id
+            "avatar" => package.avatar = Some(value).filter(|v| !v.is_empty()),
+            "identity_type" => package.identity_type = Some(value).filter(|v| !v.is_empty()),
+            "template" => package.template = Some(value).filter(|v| !v.is_empty()),
+            _ => {}
+        }
+    }
+
+    packages
+}
+
+fn update_entity(db: &Connection, app_sender: Sender<Message>) -> Option<()> {
+    let eid: String = widget_from_id::<Input>("entity_base_id")?.value();
+    let package: NPackage = fetch_header_data_from_gui(&eid)?;
+    let seen: Option<i64> = widget_from_id::<Input>("row_version")?
+        .value()
+        .parse::<i64>()
+        .ok();
+
+    match write_entity_header(db, package.clone(), seen) {
+        Ok(Some(current)) => build_entity_merge_window(db, package, current, app_sender),
+        Ok(None) => fill_entity_row_version(db, &eid),
+        Err(e) => alert_write_error(e),
+    }
+
+    Some(())
+}
+
+fn write_entity_header(
+    db: &Connection,
+    package: NPackage,
+    seen: Option<i64>,
+) -> Result<Option<i64>, Error> {
+    // returns the row's current version when it moved on from `seen`, nothing is written then
+    let entity: EntityBase = entity_base(&package).map_err(read_failed)?;
+
+    Repository::new(db).update_entity(&entity, seen)
+}
+
+fn fill_entity_row_version(db: &Connection, eid: &String) -> () {
+    if let Some(mut input) = widget_from_id::<Input>("row_version") {
+        match fetch_row_version(db, "entity_base_definitions", eid) {
+            Ok(Some(v)) => input.set_value(&v.to_string()[..]),
+            Ok(None) => input.set_value(""),
+            Err(e) => {
+                input.set_value("");
+                alert_read_error(eid, e);
+            }
+        }
+    }
+}
+
+fn build_entity_merge_window(
+    db: &Connection,
+    mine: NPackage,
+    version: i64,
+    app_sender: Sender<Message>,
+) -> () {
+    let theirs: NPackage = match fetch_entity_header(db, &mine.eid) {
+        Ok(Some(t)) => t,
+        Ok(None) => return alert_stale_delete(&format!("Entity {}", mine.eid)),
+        Err(e) => return alert_read_error(&mine.eid, e),
+    };
+    let eid: String = mine.eid.clone();
+
+    build_merge_window(
+        &format!("Entity {}", eid),
+        vec![
+            ("name", mine.name, theirs.name),
+            ("entity_core_type_id", mine.ecid, theirs.ecid),
+            ("entity_sub_type_id", mine.esid, theirs.esid),
+        ],
+        move |values| {
+            Message::SaveEntityHeader(
+                NPackage {
+                    eid: eid.clone(),
+                    name: values[0].clone(),
+                    ecid: values[1].clone(),
+                    esid: values[2].clone(),
+                },
+                version,
+            )
+        },
+        app_sender,
+    );
+}
+
+fn fetch_entity_header(db: &Connection, eid: &String) -> Result<Option<NPackage>, RowError> {
+    Ok(Repository::new(db)
+        .entity(parse_entity_id(eid)?)?
+        .map(entity_header))
+}
+
+fn entity_header(entity: EntityBase) -> NPackage {
+    NPackage {
+        eid: entity.id.to_string(),
+        name: entity.name,
+        ecid: entity.core_type_id.to_string(),
+        esid: entity.sub_type_id.to_string(),
+    }
+}
+
+fn entity_base(package: &NPackage) -> Result<EntityBase, RowError> {
+    Ok(EntityBase {
+        id: parse_id("entity_base_id", &package.eid)?,
+        name: package.name.clone(),
+        core_type_id: parse_id("entity_core_type_id", &package.ecid)?,
+        sub_type_id: parse_id("entity_sub_type_id", &package.esid)?,
+    })
+}
+
+fn parse_id(column: &str, value: &String) -> Result<i64, RowError> {
+    // ids are handled as text in the gui and as integers by the repository
+    value
+        .trim()
+        .parse::<i64>()
+        .map_err(|_| RowError::WrongType {
+            column: String::from(column),
+            expected: "an integer",
+        })
+}
+
+fn parse_entity_id(eid: &String) -> Result<i64, RowError> {
+    parse_id("entity_base_id", eid)
+}
+
+fn regen_enums(db: &Connection) -> std::io::Result<()> {
+    write_enums_to_file(generate_enums(db))
+}
+
+fn write_enums_to_file(s: String) -> std::io::Result<()> {
+    fs::write("enums.gd", s.as_bytes())?;
+    Ok(())
+}
+
+fn close_treeitem(t: &mut Tree, pn: String) -> () {
+    let _ = t.close(&pn[..], false);
+}
+
+fn get_pathname(t: &Tree, ti: &TreeItem) -> Option<String> {
+    match t.item_pathname(&ti) {
+        Ok(pn) => Some(pn),
+        Err(_) => None,
+    }
+}
+
+fn update_entity_comp_layers(db: &Connection, package: CLPackage) -> Result<(), Error> {
+    let eid: i64 = parse_id("entity_base_id", &package.entity_id).map_err(read_failed)?;
+    let cid: i64 =
+        parse_id("entity_composition_layer_id", &package.comp_id).map_err(read_failed)?;
+
+    Repository::new(db).set_entity_composition_layer(eid, cid, package.cb_state)?;
+    Ok(())
+}
+
+fn fetch_header_data_from_gui(eid: &String) -> Option<NPackage> {
+    Some(NPackage {
+        eid: eid.clone(),
+        name: widget_from_id::<Input>("name")?.value(),
+        ecid: widget_from_id::<Input>("entity_core_type_id")?.value(),
+        esid: widget_from_id::<Input>("entity_sub_type_id")?.value(),
+    })
+}
+
+fn close_window_by_id(id: &str) -> () {
+    // secondary windows are rebuilt on every open, so drop the stale one and its widget ids
+    if let Some(w) = widget_from_id::<DoubleWindow>(id) {
+        DoubleWindow::delete(w);
+    }
+}
+
+fn build_settings_row(label: &str, id: &str, value: String) -> Input {
+    let mut inner_pack: Pack = Pack::default()
+        .with_size(0, 35)
+        .with_type(PackType::Horizontal);
+    inner_pack.set_spacing(5);
+    inner_pack.begin();
+
+    let mut f: Frame = Frame::default().with_size(200, 0).with_label(label);
+    f.set_frame(FrameType::EngravedBox);
+
+    let mut i: Input = Input::default().with_size(250, 0).with_id(id);
+    i.set_value(&value[..]);
+    inner_pack.end();
+
+    i
+}
+
+fn build_settings_window(settings: &Settings, app_sender: Sender<Message>) -> () {
+    close_window_by_id("settings_window");
+
+    let mut settings_window: DoubleWindow = Window::default()
+        .with_size(470, 250)
+        .center_screen()
+        .with_label("Settings")
+        .with_id("settings_window");
+
+    let mut settings_pack: Pack = Pack::new(5, 5, 460, 240, "").with_type(PackType::Vertical);
+    settings_pack.set_spacing(5);
+    settings_pack.begin();
+
+    let interval_input: Input = build_settings_row(
+        "Backup interval (minutes)",
+        "settings_backup_interval_minutes",
+        settings.backup_interval_minutes.to_string(),
+    );
+    let retention_input: Input = build_settings_row(
+        "Backups kept",
+        "settings_backup_retention",
+        settings.backup_retention.to_string(),
+    );
+    let project_input: Input = build_settings_row(
+        "Godot project directory",
+        "settings_godot_project_dir",
+        settings.godot_project_dir.clone(),
+    );
+    let scale_input: Input = build_settings_row(
+        "Interface scale (0 follows the screen)",
+        "settings_ui_scale",
+        settings.ui_scale.to_string(),
+    );
+
+    let mut browse_button: Button = Button::default()
+        .with_size(0, 35)
+        .with_label("Browse for Godot Project...");
+    let mut project_input_clone: Input = project_input.clone();
+    browse_button.set_callback(move |_| {
+        let current_directory: PathBuf = match current_dir() {
+            Ok(p) => p,
+            Err(_) => PathBuf::new(),
+        };
+        if let Ok(Some(path)) = FileDialog::new()
+            .set_location(&current_directory)
+            .show_open_single_dir()
+        {
+            project_input_clone.set_value(&path.to_string_lossy()[..]);
+        }
+    });
+
+    let mut button: Button = Button::default().with_size(0, 35).with_label("Save");
+    let current: Settings = settings.clone();
+    let mut window_clone: DoubleWindow = settings_window.clone();
+
+    button.set_callback(move |_| {
+        let mut s: Settings = current.clone();
+        if let Ok(v) = interval_input.value().trim().parse::<u64>() {
+            s.backup_interval_minutes = v;
+        }
+        if let Ok(v) = retention_input.value().trim().parse::<usize>() {
+            s.backup_retention = v;
+        }
+        s.godot_project_dir = String::from(project_input.value().trim());
+        if let Ok(v) = scale_input.value().trim().parse::<f32>() {
+            s.ui_scale = match v {
+                v if v <= 0.0 => 0.0,
+                v => v.clamp(0.5, 4.0),
+            };
+        }
+        app_sender.send(Message::SaveSettings(s));
+        window_clone.hide();
+    });
+
+    settings_pack.end();
+    settings_window.end();
+    settings_window.show();
+}
+
+fn backups_directory(db_path: &Path) -> Option<PathBuf> {
+    let mut dir: PathBuf = db_path.parent()?.to_path_buf();
+    dir.push("backups");
+
+    Some(dir)
+}
+
+fn list_backups(db_path: &Path) -> Vec<(String, u64, PathBuf)> {
+    // snapshots are named "<db file stem>_<unix seconds>.db", with a "_<n>" counter after the
+    // seconds when more than one is taken in the same second, newest first
+    let mut snapshots: Vec<(String, u64, PathBuf)> = Vec::new();
+    let dir: PathBuf = match backups_directory(db_path) {
+        Some(d) => d,
+        None => return snapshots,
+    };
+    let mut prefix: String = match db_path.file_stem() {
+        Some(s) => s.to_string_lossy().to_string(),
+        None => return snapshots,
+    };
+    prefix.push('_');
+
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let file_name: String = entry.file_name().to_string_lossy().to_string();
+            if let Some(stamp) = file_name
+                .strip_prefix(&prefix[..])
+                .and_then(|f| f.strip_suffix(".db"))
+            {
+                if let Some((secs, _)) = parse_snapshot_stamp(stamp) {
+                    snapshots.push((String::from(stamp), secs, entry.path()));
+                }
+            }
+        }
+    }
+
+    snapshots.sort_by_key(|(stamp, _, _)| std::cmp::Reverse(parse_snapshot_stamp(stamp)));
+    snapshots
+}
+
+fn parse_snapshot_stamp(stamp: &str) -> Option<(u64, u32)> {
+    match stamp.split_once('_') {
+        Some((secs, n)) => Some((secs.parse::<u64>().ok()?, n.parse::<u32>().ok()?)),
+        None => Some((stamp.parse::<u64>().ok()?, 0)),
+    }
+}
+
+fn snapshot_cold_storage(db: &Connection, db_path: &Path) -> Option<PathBuf> {
+    let dir: PathBuf = backups_directory(db_path)?;
+    fs::create_dir_all(&dir).ok()?;
+
+    let stamp: u64 = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let stem: String = db_path.file_stem()?.to_string_lossy().to_string();
+
+    // claim a name nothing else holds yet, so two snapshots in one second never overwrite
+    let mut counter: u32 = 0;
+    let snapshot: PathBuf = loop {
+        let mut name: String = String::from_iter([&stem[..], "_", &stamp.to_string()[..]]);
+        if counter > 0 {
+            name.push('_');
+            name.push_str(&counter.to_string()[..]);
+        }
+        name.push_str(".db");
+
+        let candidate: PathBuf = dir.join(name);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(_) => break candidate,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => counter += 1,
+            Err(_) => return None,
+        }
+    };
+
+    // the online backup API copies a consistent image even while our connection is open
+    match db.backup(DatabaseName::Main, &snapshot, None) {
+        Ok(_) => Some(snapshot),
+        Err(_) => {
+            let _ = fs::remove_file(&snapshot);
+            None
+        }
+    }
+}
+
+fn rotate_backups(db_path: &Path, retention: usize) -> () {
+    for (_, _, old) in list_backups(db_path).into_iter().skip(retention.max(1)) {
+        // one that can't be removed now is tried again at the next rotation
+        let _ = fs::remove_file(&old);
+        // cold storage runs in WAL mode, so snapshots can leave sidecar files behind
+        for suffix in ["-wal", "-shm"] {
+            let mut sidecar = old.clone().into_os_string();
+            sidecar.push(suffix);
+            let _ = fs::remove_file(sidecar);
+        }
+    }
+}
+
+fn fetch_data_version(db: &Connection) -> Result<i64, RowError> {
+    // changes whenever another connection commits, never for this connection's own writes
+    let x: &[(&str, &dyn ToSql)] = &[];
+    let version: Option<IntValue> = query_one_as(
+        db,
+        "SELECT data_version as 'value' FROM pragma_data_version();",
+        &x,
+    )?;
+
+    Ok(version.map_or(0, |v| v.value))
+}
+
+fn changed_compare_rows(
+    before: &[(String, Vec<String>)],
+    after: &[(String, Vec<String>)],
+) -> Vec<String> {
+    // labels of rows whose values differ, section titles have no values and are skipped
+    let mut changed: Vec<String> = Vec::new();
+
+    for (label, values) in after.iter().filter(|(_, v)| !v.is_empty()) {
+        match before.iter().find(|(l, _)| l == label) {
+            Some((_, old)) if old == values => (),
+            _ => changed.push(label.clone()),
+        }
+    }
+    for (label, _) in before.iter().filter(|(_, v)| !v.is_empty()) {
+        if !after.iter().any(|(l, _)| l == label) {
+            changed.push(label.clone());
+        }
+    }
+
+    changed
+}
+
+fn backup_cold_storage(db: &Connection, db_path: &Path, retention: usize) -> Option<PathBuf> {
+    let snapshot: PathBuf = snapshot_cold_storage(db, db_path)?;
+    rotate_backups(db_path, retention);
+
+    Some(snapshot)
+}
+
+fn restore_cold_storage(
+    db: &mut Connection,
+    db_path: &Path,
+    stamp: &String,
+    retention: usize,
+) -> Option<String> {
+    // returns why nothing was restored
+    let snapshot: PathBuf = match list_backups(db_path)
+        .into_iter()
+        .find(|(s, _, _)| s == stamp)
+    {
+        Some((_, _, p)) => p,
+        None => return Some(format!("Snapshot {} no longer exists", stamp)),
+    };
+
+    // keep the state we are about to throw away, rotating only once the restore is done
+    // so the snapshot being restored can't be pruned out from under us
+    if snapshot_cold_storage(db, db_path).is_none() {
+        return Some(String::from(
+            "The current state could not be backed up, nothing was restored",
+        ));
+    }
+
+    let result = db.restore(DatabaseName::Main, &snapshot, None::<fn(backup::Progress)>);
+    rotate_backups(db_path, retention);
+
+    match result {
+        Ok(_) => None,
+        Err(e) => Some(format!(
+            "{} could not be restored: {}",
+            snapshot.display(),
+            e
+        )),
+    }
+}
+
+fn fetch_backup_entity_count(snapshot: &Path) -> String {
+    // only shown in the restore list, a snapshot that can't be read is listed with a "?"
+    let count = |conn: &Connection| -> Result<Option<IntValue>, RowError> {
+        let x: &[(&str, &dyn ToSql)] = &[];
+        query_one_as(
+            conn,
+            "SELECT COUNT(*) as 'value' FROM entity_base_definitions;",
+            &x,
+        )
+    };
+    match Connection::open_with_flags(snapshot, OpenFlags::SQLITE_OPEN_READ_ONLY) {
+        Ok(conn) => match count(&conn) {
+            Ok(Some(c)) => c.value.to_string(),
+            _ => String::from("?"),
+        },
+        Err(_) => String::from("?"),
+    }
+}
+
+fn fetch_backup_listing(db: &Connection, db_path: &Path) -> Vec<String> {
+    let mut listing: Vec<String> = Vec::new();
+
+    for (stamp, secs, snapshot) in list_backups(db_path) {
+        // let SQLite do the local time formatting rather than pulling in a date crate
+        let secs_i64: i64 = secs as i64;
+        let x: &[(&str, &dyn ToSql)] = named_params! { ":secs": secs_i64 };
+        let when: Result<Option<TextValue>, RowError> = query_one_as(
+            db,
+            "SELECT datetime(:secs, 'unixepoch', 'localtime') as 'value';",
+            x,
+        );
+        let when: String = match when {
+            Ok(Some(TextValue { value: Some(w) })) => w,
+            _ => String::from("?"),
+        };
+        let count: String = fetch_backup_entity_count(&snapshot);
+
+        // "<stamp>:<local time>" so the stamp can be sliced back off like tree labels
+        listing.push(String::from_iter([
+            &stamp[..],
+            ":",
+            &when[..],
+            "  (",
+            &count[..],
+            " entities)",
+        ]));
+    }
+
+    listing
+}
+
+fn build_restore_window(snapshots: Vec<String>, app_sender: Sender<Message>) -> () {
+    close_window_by_id("restore_window");
+
+    let mut restore_window: DoubleWindow = Window::default()
+        .with_size(500, 400)
+        .center_screen()
+        .with_label("Restore from Backup")
+        .with_id("restore_window");
+
+    let mut browser: HoldBrowser = HoldBrowser::new(5, 5, 490, 345, "");
+    for line in snapshots.iter() {
+        browser.add(&line[..]);
+    }
+
+    let mut button: Button = Button::new(5, 355, 490, 40, "Restore Selected");
+    let mut window_clone: DoubleWindow = restore_window.clone();
+
+    button.set_callback(move |_| {
+        let line: String = match browser.selected_text() {
+            Some(l) => l,
+            None => return,
+        };
+        let confirmed: Option<i32> = dialog::choice2_default(
+            "Replace cold storage with this snapshot? The current state is backed up first.",
+            "Cancel",
+            "Restore",
+            "",
+        );
+        if confirmed == Some(1) {
+            app_sender.send(Message::RestoreBackup(slice_beginning_of_string(line, ":")));
+            window_clone.hide();
+        }
+    });
+
+    restore_window.end();
+    restore_window.show();
+}
+
+fn alert_schema_error((step, e): (&str, Error), outcome: &str) -> () {
+    // the steps before the failed one stay done and the next open carries on from there
+    dialog::alert_default(
+        &format!(
+            "The editor's own tables could not be set up, the {} step failed: {}\n\n{}",
+            step, e, outcome
+        )[..],
+    );
+}
+
+fn fetch_row_version(
+    conn: &Connection,
+    table: &str,
+    key: &String,
+) -> Result<Option<i64>, RowError> {
+    // nothing when the row is gone, an error when it couldn't be looked at
+    let key_column: &str = match VERSIONED_TABLES.iter().find(|(t, _)| *t == table) {
+        Some((_, key_column)) => key_column,
+        None => return Err(RowError::Sql(format!("{} has no row_version", table))),
+    };
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":key": key };
+    let version: Option<IntValue> = query_one_as(
+        conn,
+        &format!(
+            "SELECT 'v'.'row_version' as 'value' FROM '{table}' as 'v' WHERE 'v'.'{key_column}' = :key;",
+            table = table,
+            key_column = key_column,
+        )[..],
+        &x,
+    )?;
+
+    Ok(version.map(|v| v.value))
+}
+
+fn stale_row_version(
+    conn: &Connection,
+    table: &str,
+    key: &String,
+    seen: Option<i64>,
+) -> Result<Option<i64>, Error> {
+    // a versioned write that matched nothing: the row is either gone or no longer at `seen`
+    match (
+        seen,
+        fetch_row_version(conn, table, key).map_err(read_failed)?,
+    ) {
+        (Some(seen), Some(current)) if seen != current => Ok(Some(current)),
+        _ => Ok(None),
+    }
+}
+
+fn check_entity_version(db: &Connection, eid: &String, seen: Option<i64>) -> Result<(), Error> {
+    // rows hanging off an entity bump its row_version, so this covers its layers, attributes,
+    // actions, avatar, identity type and template link as well as the header
+    let seen: i64 = match seen {
+        Some(seen) => seen,
+        None => return Ok(()),
+    };
+    match fetch_row_version(db, "entity_base_definitions", eid).map_err(read_failed)? {
+        Some(current) if current == seen => Ok(()),
+        _ => Err(Error::StatementChangedRows(0)),
+    }
+}
+
+fn write_open_entity(
+    db: &Connection,
+    eid: &String,
+    write: impl FnOnce() -> Result<(), Error>,
+) -> () {
+    // checked against the version the canvas was filled at when it shows this entity
+    let open: bool =
+        widget_from_id::<Input>("entity_base_id").map(|i| i.value()) == Some(eid.clone());
+    let seen: Option<i64> = match open {
+        true => widget_from_id::<Input>("row_version").and_then(|i| i.value().parse::<i64>().ok()),
+        false => None,
+    };
+
+    match savepoint(db, || {
+        check_entity_version(db, eid, seen)?;
+        write()
+    }) {
+        Ok(_) if open => fill_entity_row_version(db, eid),
+        Ok(_) => {}
+        Err(Error::StatementChangedRows(0)) => {
+            dialog::alert_default(
+                &format!(
+                    "Entity {} was changed by someone else since it was loaded, it has been reloaded so check it and try again",
+                    eid
+                )[..],
+            );
+            fill_scroll_gui(eid, db);
+        }
+        Err(e) => alert_write_error(e),
+    }
+}
+
+fn alert_write_error(e: Error) -> () {
+    // the write's savepoint was rolled back, so nothing of it was kept
+    dialog::alert_default(&format!("The change could not be saved: {}", e)[..]);
+}
+
+fn alert_read_error(eid: &String, e: RowError) -> () {
+    dialog::alert_default(&format!("Entity {} could not be read: {}", eid, e)[..]);
+}
+
+fn alert_stale_delete(what: &str) -> () {
+    dialog::alert_default(
+        &format!(
+            "{} was changed or removed by someone else since it was loaded, check it and try again",
+            what
+        )[..],
+    );
+}
+
+fn build_merge_window(
+    title: &str,
+    fields: Vec<(&'static str, String, String)>,
+    resolve: impl Fn(Vec<String>) -> Message + 'static,
+    app_sender: Sender<Message>,
+) -> () {
+    // one row per field with mine and theirs side by side, the picked values are handed to
+    // `resolve` to build the message that writes them against the version theirs came from
+    close_window_by_id("merge_window");
+
+    let height: i32 = 140 + 40 * fields.len() as i32;
+    let mut merge_window: DoubleWindow = Window::default()
+        .with_size(700, height)
+        .center_screen()
+        .with_label("Resolve Conflict")
+        .with_id("merge_window");
+
+    let mut merge_pack: Pack = Pack::new(5, 5, 690, height - 10, "").with_type(PackType::Vertical);
+    merge_pack.set_spacing(5);
+    merge_pack.begin();
+
+    let _: Frame = Frame::default().with_size(0, 50).with_label(
+        &format!(
+            "{} was changed by someone else while you were editing it.\nPick the value to keep for each field.",
+            title
+        )[..],
+    );
+
+    {
+        let inner_pack: Pack = Pack::default()
+            .with_size(0, 30)
+            .with_type(PackType::Horizontal);
+        inner_pack.begin();
+        for (label, width) in [("Field", 150), ("Mine", 265), ("Theirs", 265)] {
+            let mut f: Frame = Frame::default().with_size(width, 0).with_label(label);
+            f.set_frame(FrameType::EngravedBox);
+        }
+        inner_pack.end();
+    }
+
+    let mut picks: Vec<(RadioRoundButton, String, String)> = Vec::new();
+    for (label, mine, theirs) in fields {
+        // each row is its own group so its two radio buttons only exclude each other
+        let mut inner_pack: Pack = Pack::default()
+            .with_size(0, 35)
+            .with_type(PackType::Horizontal);
+        inner_pack.set_spacing(5);
+        inner_pack.begin();
+
+        let mut f: Frame = Frame::default().with_size(145, 0).with_label(label);
+        f.set_frame(FrameType::EngravedBox);
+        let mut mine_button: RadioRoundButton = RadioRoundButton::default()
+            .with_size(265, 0)
+            .with_label(&mine[..]);
+        let mut theirs_button: RadioRoundButton = RadioRoundButton::default()
+            .with_size(265, 0)
+            .with_label(&theirs[..]);
+        mine_button.set_value(true);
+        match mine == theirs {
+            true => {
+                mine_button.deactivate();
+                theirs_button.deactivate();
+            }
+            false => f.set_label_color(Color::Red),
+        }
+
+        inner_pack.end();
+        picks.push((mine_button, mine, theirs));
+    }
+
+    let mut apply_button: Button = Button::default().with_size(0, 35).with_label("Apply");
+    let mut window_clone: DoubleWindow = merge_window.clone();
+    apply_button.set_callback(move |_| {
+        let values: Vec<String> = picks
+            .iter()
+            .map(|(mine_button, mine, theirs)| match mine_button.value() {
+                true => mine.clone(),
+                false => theirs.clone(),
+            })
+            .collect();
+        app_sender.send(resolve(values));
+        window_clone.hide();
+    });
+
+    merge_pack.end();
+    merge_window.end();
+    merge_window.show();
+}
+
+fn fetch_entity_history(conn: &Connection, eid: &String) -> Result<Vec<AuditEntry>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": eid };
+    query_as(
+        conn,
+        "SELECT 'a'.'entity_audit_log_id', datetime('a'.'timestamp', 'localtime') as 'timestamp', 'a'.'os_user', 'a'.'table_name', 'a'.'row_key', 'a'.'column_name', 'a'.'before_value', 'a'.'after_value', 'a'.'entity_base_id' FROM 'entity_audit_log' as 'a' WHERE 'a'.'entity_base_id' = :eid ORDER BY 'a'.'entity_audit_log_id' DESC;",
+        &x,
+    )
+}
+
+fn fetch_audit_entry(conn: &Connection, audit_id: &String) -> Result<Option<AuditEntry>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":aid": audit_id };
+    let mut entries: Vec<AuditEntry> = query_as(
+        conn,
+        "SELECT 'a'.'entity_audit_log_id', 'a'.'timestamp', 'a'.'os_user', 'a'.'table_name', 'a'.'row_key', 'a'.'column_name', 'a'.'before_value', 'a'.'after_value', 'a'.'entity_base_id' FROM 'entity_audit_log' as 'a' WHERE 'a'.'entity_audit_log_id' = :aid;",
+        &x,
+    )?;
+
+    Ok(entries.pop())
+}
+
+fn describe_audit_value(value: &Option<String>) -> String {
+    // NULL means there was no value at all, which is not the same as an empty one
+    match value {
+        Some(v) => String::from_iter(["'", &v[..], "'"]),
+        None => String::from("nothing"),
+    }
+}
+
+fn describe_audit_entry(entry: &AuditEntry) -> String {
+    let (before, after): (String, String) = (
+        describe_audit_value(&entry.before),
+        describe_audit_value(&entry.after),
+    );
+    let value: &str = match (&entry.before, &entry.after) {
+        (Some(v), _) | (None, Some(v)) => &v[..],
+        (None, None) => "",
+    };
+
+    match (&entry.table_name[..], entry.column_name.as_deref()) {
+        ("entity_base_definitions", None) => String::from("created entity"),
+        ("entity_composition_layers_definitions", _) => match entry.before.is_some() {
+            false => String::from_iter(["enabled comp layer ", value]),
+            true => String::from_iter(["disabled comp layer ", value]),
+        },
+        ("entity_actions_definitions", _) => match entry.before.is_some() {
+            false => String::from_iter(["added action ", value]),
+            true => String::from_iter(["removed action ", value]),
+        },
+        ("entity_attributes_definitions", _) => String::from_iter([
+            "attribute ",
+            &slice_end_of_string(entry.row_key.clone(), ":")[..],
+            ": ",
+            &before[..],
+            " -> ",
+            &after[..],
+        ]),
+        ("entity_templates_definitions", _) => {
+            String::from_iter(["linked to template ", &after[..]])
+        }
+        ("entity_avatars_definitions", _) => {
+            String::from_iter(["avatar: ", &before[..], " -> ", &after[..]])
+        }
+        ("entity_identity_types_definitions", _) => {
+            String::from_iter(["identity type: ", &before[..], " -> ", &after[..]])
+        }
+        (_, column) => String::from_iter([
+            column.unwrap_or(&entry.table_name[..]),
+            ": ",
+            &before[..],
+            " -> ",
+            &after[..],
+        ]),
+    }
+}
+
+fn fill_history_browser(db: &Connection, eid: &String) -> Option<()> {
+    let mut browser: HoldBrowser = widget_from_id::<HoldBrowser>("history_browser")?;
+    browser.clear();
+
+    for entry in rows_or_report(fetch_entity_history(db, eid)) {
+        // "<audit id>:<when> | <who> | <what>" so the id slices off like tree labels
+        browser.add(&String::from_iter([
+            &entry.id.to_string()[..],
+            ":",
+            &entry.timestamp[..],
+            " | ",
+            &entry.os_user[..],
+            " | ",
+            &describe_audit_entry(&entry)[..],
+        ]));
+    }
+
+    Some(browser.redraw())
+}
+
+fn build_history_section(app_sender: Sender<Message>) -> () {
+    let _: Frame = Frame::default().with_size(0, 35).with_label("History");
+    let _: HoldBrowser = HoldBrowser::default()
+        .with_size(0, 200)
+        .with_id("history_browser");
+
+    let mut button: Button = Button::default()
+        .with_size(0, 35)
+        .with_label("Revert Selected Change");
+    button.set_callback(move |_| {
+        if let Some(browser) = widget_from_id::<HoldBrowser>("history_browser") {
+            if let Some(line) = browser.selected_text() {
+                app_sender.send(Message::RevertAuditEntry(
+                    slice_beginning_of_string(line, ":"),
+                    Vec::new(),
+                ));
+            }
+        }
+    });
+}
+
+fn fetch_audited_value(
+    db: &Connection,
+    entry: &AuditEntry,
+    eid: &String,
+) -> Result<Option<String>, RowError> {
+    // what the audited value holds now, in the form the log keeps it in. layers and actions
+    // are logged by their id while on, so theirs is the id when it's still on
+    let logged_id: Option<String> = entry.before.clone().or(entry.after.clone());
+    let repository: Repository = Repository::new(db);
+
+    match (&entry.table_name[..], entry.column_name.as_deref()) {
+        ("entity_base_definitions", Some(column @ "name"))
+        | ("entity_base_definitions", Some(column @ "entity_core_type_id"))
+        | ("entity_base_definitions", Some(column @ "entity_sub_type_id")) => {
+            Ok(fetch_entity_header(db, eid)?.map(|header| match column {
+                "name" => header.name,
+                "entity_core_type_id" => header.ecid,
+                _ => header.esid,
+            }))
+        }
+        ("entity_composition_layers_definitions", _) => {
+            let on: bool = repository
+                .entity_composition_layers(parse_entity_id(eid)?)?
+                .iter()
+                .any(|layer| Some(layer.id.to_string()) == logged_id);
+            Ok(logged_id.filter(|_| on))
+        }
+        ("entity_actions_definitions", _) => {
+            let on: bool = repository
+                .entity_actions(parse_entity_id(eid)?)?
+                .iter()
+                .any(|action| Some(action.id.to_string()) == logged_id);
+            Ok(logged_id.filter(|_| on))
+        }
+        ("entity_attributes_definitions", _) => {
+            let attribute_id: String = slice_end_of_string(entry.row_key.clone(), ":");
+            fetch_entity_attribute_value(db, eid, &attribute_id)
+        }
+        ("entity_avatars_definitions", _) => fetch_entity_avatar(db, eid),
+        ("entity_identity_types_definitions", _) => fetch_entity_identity_type(db, eid),
+        // nothing to compare, revert_audit_entry turns these down
+        _ => Ok(entry.after.clone()),
+    }
+}
+
+fn describe_revert_value(entry: &AuditEntry, value: &Option<String>) -> String {
+    let logged_id: &str = entry
+        .before
+        .as_deref()
+        .or(entry.after.as_deref())
+        .unwrap_or_default();
+
+    match &entry.table_name[..] {
+        "entity_composition_layers_definitions" => match value.is_some() {
+            true => format!("layer {} enabled", logged_id),
+            false => format!("layer {} disabled", logged_id),
+        },
+        "entity_actions_definitions" => match value.is_some() {
+            true => format!("action {} added", logged_id),
+            false => format!("action {} removed", logged_id),
+        },
+        "entity_attributes_definitions" => format!(
+            "attribute {}: {}",
+            slice_end_of_string(entry.row_key.clone(), ":"),
+            describe_audit_value(value)
+        ),
+        _ => describe_audit_value(value),
+    }
+}
+
+fn find_revert_conflict(
+    db: &Connection,
+    audit_id: &String,
+    resolved: &[(String, Option<String>)],
+) -> Result<Option<RevertConflict>, RowError> {
+    // a value is only reverted while it still holds what the entry left behind, anything
+    // else would throw a later change away unasked
+    if resolved.iter().any(|(id, _)| id == audit_id) {
+        return Ok(None);
+    }
+    let entry: AuditEntry = match fetch_audit_entry(db, audit_id)? {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+    let eid: String = match &entry.entity_id {
+        Some(eid) => eid.clone(),
+        None => return Ok(None),
+    };
+    // a creation is reverted by deleting, which asks first. rows of a deleted entity aren't
+    // reverted at all
+    if entry.column_name.is_none() && entry.table_name == "entity_base_definitions" {
+        return Ok(None);
+    }
+    if fetch_row_version(db, "entity_base_definitions", &eid)?.is_none() {
+        return Ok(None);
+    }
+
+    let theirs: Option<String> = fetch_audited_value(db, &entry, &eid)?;
+    if theirs == entry.after {
+        return Ok(None);
+    }
+    let field: &'static str = match (&entry.table_name[..], entry.column_name.as_deref()) {
+        ("entity_base_definitions", Some("name")) => "name",
+        ("entity_base_definitions", Some("entity_core_type_id")) => "core type",
+        ("entity_base_definitions", _) => "sub type",
+        ("entity_composition_layers_definitions", _) => "comp layer",
+        ("entity_actions_definitions", _) => "action",
+        ("entity_attributes_definitions", _) => "attribute",
+        ("entity_avatars_definitions", _) => "avatar",
+        ("entity_identity_types_definitions", _) => "identity type",
+        _ => "value",
+    };
+
+    Ok(Some(RevertConflict {
+        audit_id: audit_id.clone(),
+        field,
+        mine: (
+            describe_revert_value(&entry, &entry.before),
+            entry.before.clone(),
+        ),
+        theirs: (describe_revert_value(&entry, &theirs), theirs),
+    }))
+}
+
+fn build_revert_merge_window(
+    title: &str,
+    conflicts: Vec<RevertConflict>,
+    resolve: impl Fn(Vec<(String, Option<String>)>) -> Message + 'static,
+    app_sender: Sender<Message>,
+) -> () {
+    let fields: Vec<(&'static str, String, String)> = conflicts
+        .iter()
+        .map(|c| (c.field, c.mine.0.clone(), c.theirs.0.clone()))
+        .collect();
+
+    build_merge_window(
+        title,
+        fields,
+        move |values| {
+            // keeping theirs reverts to what is there already, which writes nothing
+            resolve(
+                conflicts
+                    .iter()
+                    .zip(values.iter())
+                    .map(|(c, picked)| match *picked == c.mine.0 {
+                        true => (c.audit_id.clone(), c.mine.1.clone()),
+                        false => (c.audit_id.clone(), c.theirs.1.clone()),
+                    })
+                    .collect(),
+            )
+        },
+        app_sender,
+    );
+}
+
+fn revert_audit_entry(
+    db: &Connection,
+    audit_id: &String,
+    resolved: &[(String, Option<String>)],
+) -> Option<String> {
+    // writes the entry's "before" back, or the value picked for it in the merge window.
+    // find_revert_conflict is asked first, this doesn't check the value moved on
+    let entry: AuditEntry = fetch_audit_entry(db, audit_id).ok().flatten()?;
+    let eid: String = entry.entity_id.clone()?;
+    let before: Option<String> = match resolved.iter().find(|(id, _)| id == audit_id) {
+        Some((_, picked)) => picked.clone(),
+        None => entry.before.clone(),
+    };
+
+    // rows of a deleted entity stay deleted, bringing them back would leave orphans
+    let created: bool = entry.table_name == "entity_base_definitions"
+        && entry.column_name.is_none()
+        && entry.after.as_deref() == Some("created");
+    if !created {
+        match fetch_row_version(db, "entity_base_definitions", &eid) {
+            Ok(Some(_)) => {}
+            Ok(None) => return None,
+            Err(e) => {
+                alert_read_error(&eid, e);
+                return None;
+            }
+        }
+    }
+
+    // reverting goes back through the normal write paths so the revert is audited too
+    match (&entry.table_name[..], entry.column_name.as_deref()) {
+        ("entity_base_definitions", None) if created => {
+            let question: String = format!("Reverting this deletes entity {}, go ahead?", eid);
+            if dialog::choice2_default(&question[..], "Delete", "Cancel", "") != Some(0) {
+                return None;
+            }
+            if let Some(reason) = delete_entity(db, &eid, None) {
+                dialog::alert_default(&reason[..]);
+                return None;
+            }
+        }
+        ("entity_base_definitions", Some(column @ "name"))
+        | ("entity_base_definitions", Some(column @ "entity_core_type_id"))
+        | ("entity_base_definitions", Some(column @ "entity_sub_type_id")) => {
+            let mut package: NPackage = match fetch_entity_header(db, &eid) {
+                Ok(package) => package?,
+                Err(e) => {
+                    alert_read_error(&eid, e);
+                    return None;
+                }
+            };
+            let before: String = before.unwrap_or_default();
+            match column {
+                "name" => package.name = before,
+                "entity_core_type_id" => package.ecid = before,
+                _ => package.esid = before,
+            }
+            // a revert is made against the row as it is now, whatever its version
+            if let Err(e) = write_entity_header(db, package, None) {
+                alert_write_error(e);
+                return None;
+            }
+        }
+        ("entity_composition_layers_definitions", _) => {
+            // the layer id sits in "before" when it was enabled before the change
+            let enabled_before: bool = before.is_some();
+            let result: Result<Option<String>, Error> = write_comp_layer_change(
+                db,
+                CLPackage {
+                    cb_state: enabled_before,
+                    comp_id: before.or(entry.after)?,
+                    entity_id: eid.clone(),
+                },
+            );
+            match result {
+                Ok(None) => {}
+                Ok(Some(reason)) => {
+                    dialog::alert_default(&reason[..]);
+                    return None;
+                }
+                Err(e) => {
+                    alert_write_error(e);
+                    return None;
+                }
+            }
+        }
+        ("entity_actions_definitions", _) => {
+            let enabled_before: bool = before.is_some();
+            let result: Result<(), Error> = update_entity_actions(
+                db,
+                EAPackage {
+                    cb_state: enabled_before,
+                    action_id: before.or(entry.after)?,
+                    entity_id: eid.clone(),
+                },
+            );
+            if let Err(e) = result {
+                alert_write_error(e);
+                return None;
+            }
+        }
+        ("entity_attributes_definitions", _) => {
+            let attribute_id: String = slice_end_of_string(entry.row_key.clone(), ":");
+            if let Err(e) = write_entity_attribute(db, &eid, &attribute_id, before) {
+                alert_write_error(e);
+                return None;
+            }
+        }
+        ("entity_avatars_definitions", _) => {
+            if let Err(e) = update_entity_avatar(db, &eid, before) {
+                alert_write_error(e);
+                return None;
+            }
+        }
+        ("entity_identity_types_definitions", _) => {
+            if let Err(e) = update_entity_identity_type(db, &eid, before) {
+                alert_write_error(e);
+                return None;
+            }
+        }
+        _ => {
+            dialog::alert_default("This change can't be reverted automatically");
+            return None;
+        }
+    }
+
+    Some(eid)
+}
+
+fn build_compare_window(entities: Vec<EntityBase>, app_sender: Sender<Message>) -> () {
+    close_window_by_id("compare_window");
+
+    let mut compare_window: DoubleWindow = Window::default()
+        .with_size(1000, 600)
+        .center_screen()
+        .with_label("Compare Entities")
+        .with_id("compare_window");
+
+    // pick two or more on the left, ctrl/shift click for several
+    let mut browser: MultiBrowser = MultiBrowser::new(5, 5, 250, 545, "");
+    for entity in entities {
+        browser.add(&String::from_iter([&entity.id.to_string()[..], ":", &entity.name[..]])[..]);
+    }
+
+    let mut button: Button = Button::new(5, 555, 250, 40, "Compare Selected");
+    button.set_callback(move |_| {
+        let mut eids: Vec<String> = Vec::new();
+        for line in 1..=browser.size() {
+            if browser.selected(line) {
+                if let Some(text) = browser.text(line) {
+                    eids.push(slice_beginning_of_string(text, ":"));
+                }
+            }
+        }
+        match eids.len() {
+            0 | 1 => dialog::alert_default("Select at least two entities to compare"),
+            _ => app_sender.send(Message::CompareEntities(eids)),
+        }
+    });
+
+    let compare_scroll: Scroll = Scroll::new(260, 5, 735, 590, "").with_id("compare_scroll");
+    compare_scroll.begin();
+    let mut grid_pack: Pack = Pack::new(260, 5, 715, 0, "")
+        .with_type(PackType::Vertical)
+        .with_id("compare_grid_pack");
+    grid_pack.set_spacing(2);
+    grid_pack.end();
+    compare_scroll.end();
+
+    compare_window.end();
+    compare_window.show();
+}
+
+fn push_keyed_compare_section(
+    rows: &mut Vec<(String, Vec<String>)>,
+    title: &str,
+    per_entity: Vec<Vec<(String, String)>>,
+) -> () {
+    rows.push((String::from(title), Vec::new()));
+
+    // union of the keys across every entity, in first-seen order
+    let mut keys: Vec<String> = Vec::new();
+    for entity in per_entity.iter() {
+        for (key, _) in entity.iter() {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+    }
+
+    for key in keys {
+        let values: Vec<String> = per_entity
+            .iter()
+            .map(|entity| match entity.iter().find(|(k, _)| *k == key) {
+                Some((_, v)) => v.clone(),
+                None => String::new(),
+            })
+            .collect();
+        rows.push((key, values));
+    }
+}
+
+fn build_compare_rows(
+    db: &Connection,
+    eids: &[String],
+) -> Result<Vec<(String, Vec<String>)>, RowError> {
+    // each row is (label, one value per entity), an empty value list marks a section title
+    let mut rows: Vec<(String, Vec<String>)> = Vec::new();
+    let repository: Repository = Repository::new(db);
+    let ids: Vec<i64> = eids
+        .iter()
+        .map(parse_entity_id)
+        .collect::<Result<Vec<i64>, RowError>>()?;
+
+    rows.push((String::from("Base"), Vec::new()));
+    let mut infos: Vec<Option<[(&'static str, String); 4]>> = Vec::new();
+    for id in ids.iter() {
+        infos.push(repository.entity(*id)?.as_ref().map(entity_base_columns));
+    }
+    for index in 0..4 {
+        let mut header: &str = "";
+        let values: Vec<String> = infos
+            .iter()
+            .map(|columns| match columns {
+                Some(columns) => {
+                    header = columns[index].0;
+                    columns[index].1.clone()
+                }
+                None => String::new(),
+            })
+            .collect();
+        if !header.is_empty() {
+            rows.push((String::from(header), values));
+        }
+    }
+
+    rows.push((String::from("Composition Layers"), Vec::new()));
+    let mut enabled: Vec<Vec<String>> = Vec::new();
+    for id in ids.iter() {
+        enabled.push(
+            repository
+                .entity_composition_layers(*id)?
+                .iter()
+                .map(|l| l.id.to_string())
+                .collect(),
+        );
+    }
+    for layer in fetch_entity_comp_layers_general(db)? {
+        let comp_id: String = layer.id.to_string();
+        let values: Vec<String> = enabled
+            .iter()
+            .map(|layers| match layers.contains(&comp_id) {
+                true => String::from("yes"),
+                false => String::new(),
+            })
+            .collect();
+        rows.push((layer.name, values));
+    }
+
+    let mut attributes: Vec<Vec<(String, String)>> = Vec::new();
+    for id in ids.iter() {
+        attributes.push(
+            repository
+                .entity_attribute_values(*id)?
+                .into_iter()
+                .map(|a| (a.name, a.value.to_string()))
+                .collect(),
+        );
+    }
+    push_keyed_compare_section(&mut rows, "Attributes", attributes);
+
+    let mut actions: Vec<Vec<(String, String)>> = Vec::new();
+    for id in ids.iter() {
+        actions.push(
+            repository
+                .entity_actions(*id)?
+                .into_iter()
+                .map(|a| (a.name.unwrap_or_default(), String::from("yes")))
+                .collect(),
+        );
+    }
+    push_keyed_compare_section(&mut rows, "Actions", actions);
+
+    Ok(rows)
+}
+
+fn fill_compare_grid(eids: &[String], rows: Vec<(String, Vec<String>)>) -> Option<()> {
+    let mut grid_pack: Pack = widget_from_id::<Pack>("compare_grid_pack")?;
+    grid_pack.clear();
+    grid_pack.begin();
+
+    let mut header_row: Vec<String> = vec![String::from("Field")];
+    header_row.extend(
+        eids.iter()
+            .map(|eid| String::from_iter(["Entity ", &eid[..]])),
+    );
+
+    for (index, (label, values)) in std::iter::once((String::new(), header_row))
+        .chain(rows)
+        .enumerate()
+    {
+        let mut inner_pack: Pack = Pack::default()
+            .with_size(0, 30)
+            .with_type(PackType::Horizontal);
+        inner_pack.set_spacing(2);
+        inner_pack.begin();
+
+        // section titles span the grid, everything else gets a label column plus one per entity
+        if values.is_empty() {
+            let mut f: Frame = Frame::default()
+                .with_size(200 + 152 * eids.len() as i32, 0)
+                .with_label(&label[..]);
+            f.set_frame(FrameType::FlatBox);
+            f.set_color(Color::Dark2);
+            f.set_label_color(Color::White);
+        } else {
+            let differs: bool = index > 0 && values.iter().any(|v| *v != values[0]);
+            let mut cells: Vec<String> = Vec::new();
+            if index > 0 {
+                cells.push(label.clone());
+            }
+            cells.extend(values);
+
+            for (column, value) in cells.iter().enumerate() {
+                let width: i32 = match column {
+                    0 => 200,
+                    _ => 150,
+                };
+                let mut f: Frame = Frame::default().with_size(width, 0).with_label(&value[..]);
+                match differs && column > 0 {
+                    true => {
+                        f.set_frame(FrameType::FlatBox);
+                        f.set_color(Color::from_rgb(255, 214, 153));
+                    }
+                    false => f.set_frame(FrameType::EngravedBox),
+                }
+            }
+        }
+
+        inner_pack.end();
+    }
+
+    grid_pack.end();
+    grid_pack.redraw();
+
+    Some(widget_from_id::<Scroll>("compare_scroll")?.redraw())
+}
+
+fn update_entity_actions(db: &Connection, package: EAPackage) -> Result<(), Error> {
+    // entity_actions_definitions has no unique constraint, so guard the insert by hand
+    let sql: &str = match package.cb_state {
+        true => {
+            "INSERT INTO entity_actions_definitions (
+                entity_base_id, entity_action_id
+            ) SELECT ?2, ?1 WHERE NOT EXISTS (
+                SELECT 1 FROM entity_actions_definitions WHERE entity_base_id = ?2 AND entity_action_id = ?1
+            );"
+        }
+        false => {
+            "DELETE FROM entity_actions_definitions WHERE entity_base_id = ?2 AND entity_action_id = ?1;"
+        }
+    };
+
+    savepoint(db, || {
+        if db.execute(sql, (&package.action_id, &package.entity_id))? == 0 {
+            return Ok(());
+        }
+        let (before, after): (Option<String>, Option<String>) = match package.cb_state {
+            true => (None, Some(package.action_id.clone())),
+            false => (Some(package.action_id.clone()), None),
+        };
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_actions_definitions",
+                row_key: String::from_iter([&package.entity_id[..], ":", &package.action_id[..]]),
+                column_name: Some("entity_action_id"),
+                before,
+                after,
+                entity_id: Some(package.entity_id.clone()),
+            },
+        )
+    })
+}
+
+fn fetch_entity_attribute_value(
+    conn: &Connection,
+    eid: &String,
+    attribute_id: &String,
+) -> Result<Option<String>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": eid, ":aid": attribute_id };
+    let value: Option<TextValue> = query_one_as(
+        conn,
+        "SELECT 'd'.'attribute_value' as 'value' FROM 'entity_attributes_definitions' as 'd' WHERE 'd'.'entity_base_id' = :eid AND 'd'.'entity_attribute_id' = :aid;",
+        &x,
+    )?;
+
+    Ok(value.and_then(|v| v.value))
+}
+
+fn write_entity_attribute(
+    db: &Connection,
+    eid: &String,
+    attribute_id: &String,
+    value: Option<String>,
+) -> Result<(), Error> {
+    // None removes the attribute from the entity
+    let before: Option<String> =
+        fetch_entity_attribute_value(db, eid, attribute_id).map_err(read_failed)?;
+
+    savepoint(db, || {
+        let changed: usize = match (&before, &value) {
+            (Some(_), Some(v)) => db.execute(
+                "UPDATE entity_attributes_definitions SET attribute_value = ?3 WHERE entity_base_id = ?1 AND entity_attribute_id = ?2;",
+                (eid, attribute_id, v),
+            )?,
+            (None, Some(v)) => db.execute(
+                "INSERT INTO entity_attributes_definitions (
+                    entity_base_id, entity_attribute_id, attribute_value
+                ) VALUES (
+                    ?1, ?2, ?3
+                );",
+                (eid, attribute_id, v),
+            )?,
+            (Some(_), None) => db.execute(
+                "DELETE FROM entity_attributes_definitions WHERE entity_base_id = ?1 AND entity_attribute_id = ?2;",
+                (eid, attribute_id),
+            )?,
+            (None, None) => 0,
+        };
+
+        // read the value back so "5" and "5.0" don't count as a change
+        let after: Option<String> =
+            fetch_entity_attribute_value(db, eid, attribute_id).map_err(read_failed)?;
+        if changed == 0 || before == after {
+            return Ok(());
+        }
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_attributes_definitions",
+                row_key: String::from_iter([&eid[..], ":", &attribute_id[..]]),
+                column_name: Some("attribute_value"),
+                before,
+                after,
+                entity_id: Some(eid.clone()),
+            },
+        )
+    })
+}
+
+fn fetch_templates(conn: &Connection) -> Result<Vec<Template>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = &[];
+    query_as(
+        conn,
+        "SELECT 't'.'entity_template_id', 't'.'name', 't'.'entity_core_type_id', 't'.'entity_sub_type_id', 'c'.'name' as 'core_type_name', 's'.'name' as 'sub_type_name' FROM 'entity_templates' as 't' LEFT JOIN 'entity_core_types' as 'c' ON 't'.'entity_core_type_id' = 'c'.'entity_core_type_id' LEFT JOIN 'entity_sub_types' as 's' ON 't'.'entity_sub_type_id' = 's'.'entity_sub_type_id' ORDER BY 't'.'name' ASC;",
+        &x,
+    )
+}
+
+fn fetch_templates_for_core_type(
+    conn: &Connection,
+    ect: String,
+) -> Result<Vec<Template>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":ect": ect };
+    query_as(
+        conn,
+        "SELECT 't'.'entity_template_id', 't'.'name', 't'.'entity_core_type_id', 't'.'entity_sub_type_id', 'c'.'name' as 'core_type_name', 's'.'name' as 'sub_type_name' FROM 'entity_templates' as 't' INNER JOIN 'entity_core_types' as 'c' ON 't'.'entity_core_type_id' = 'c'.'entity_core_type_id' LEFT JOIN 'entity_sub_types' as 's' ON 't'.'entity_sub_type_id' = 's'.'entity_sub_type_id' WHERE 'c'.'name' = :ect ORDER BY 't'.'name' ASC;",
+        &x,
+    )
+}
+
+fn fetch_template_header(conn: &Connection, tid: &String) -> Option<TPackage> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":tid": tid };
+    let template: Template = rows_or_report(query_as::<Template>(
+        conn,
+        "SELECT 't'.'entity_template_id', 't'.'name', 't'.'entity_core_type_id', 't'.'entity_sub_type_id', NULL as 'core_type_name', NULL as 'sub_type_name' FROM 'entity_templates' as 't' WHERE 't'.'entity_template_id' = :tid;",
+        &x,
+    ))
+    .pop()?;
+
+    Some(TPackage {
+        tid: template.id.to_string(),
+        name: template.name,
+        ecid: template.core_type_id.to_string(),
+        esid: template.sub_type_id.to_string(),
+    })
+}
+
+fn fetch_template_comp_layers(
+    conn: &Connection,
+    tid: &String,
+) -> Result<Vec<CompositionLayer>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":tid": tid };
+    query_as(
+        conn,
+        "SELECT 'c'.'entity_composition_layer_id', 'c'.'name', 'c'.'resource' FROM 'entity_templates_composition_layers' as 't' INNER JOIN 'entity_composition_layers' as 'c' ON 't'.'entity_composition_layer_id' = 'c'.'entity_composition_layer_id' WHERE 't'.'entity_template_id' = :tid ORDER BY 'c'.'entity_composition_layer_id' ASC;",
+        &x,
+    )
+}
+
+fn fetch_template_attributes(
+    conn: &Connection,
+    tid: &String,
+) -> Result<Vec<AttributeValue>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":tid": tid };
+    query_as(
+        conn,
+        "SELECT 't'.'entity_attribute_id', 'a'.'name', 't'.'attribute_value' FROM 'entity_templates_attributes' as 't' LEFT JOIN 'entity_attributes' as 'a' ON 't'.'entity_attribute_id' = 'a'.'entity_attribute_id' WHERE 't'.'entity_template_id' = :tid ORDER BY 't'.'entity_attribute_id' ASC;",
+        &x,
+    )
+}
+
+fn fetch_template_actions(conn: &Connection, tid: &String) -> Result<Vec<EntityAction>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":tid": tid };
+    query_as(
+        conn,
+        "SELECT 't'.'entity_action_id', 'a'.'name', 'a'.'action_type_id' FROM 'entity_templates_actions' as 't' LEFT JOIN 'entity_actions' as 'a' ON 't'.'entity_action_id' = 'a'.'entity_action_id' WHERE 't'.'entity_template_id' = :tid ORDER BY 't'.'entity_action_id' ASC;",
+        &x,
+    )
+}
+
+fn fetch_entity_template_link(conn: &Connection, eid: &String) -> Result<Option<String>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": eid };
+    let value: Option<TextValue> = query_one_as(
+        conn,
+        "SELECT 'd'.'entity_template_id' as 'value' FROM 'entity_templates_definitions' as 'd' WHERE 'd'.'entity_base_id' = :eid AND 'd'.'entity_template_id' IS NOT NULL;",
+        &x,
+    )?;
+
+    Ok(value.and_then(|v| v.value))
+}
+
+fn apply_template(db: &Connection, eid: &String, tid: &String) -> Result<(), Error> {
+    savepoint(db, || {
+        for layer in rows_or_report(fetch_template_comp_layers(db, tid)) {
+            let refused: Option<String> = write_comp_layer_change(
+                db,
+                CLPackage {
+                    cb_state: true,
+                    comp_id: layer.id.to_string(),
+                    entity_id: eid.clone(),
+                },
+            )?;
+            if let Some(reason) = refused {
+                return Err(layer_rules_refused(eid, reason));
+            }
+        }
+
+        for attribute in rows_or_report(fetch_template_attributes(db, tid)) {
+            write_entity_attribute(
+                db,
+                eid,
+                &attribute.attribute_id.to_string(),
+                Some(attribute.value.to_string()),
+            )?;
+        }
+
+        for action in rows_or_report(fetch_template_actions(db, tid)) {
+            update_entity_actions(
+                db,
+                EAPackage {
+                    cb_state: true,
+                    action_id: action.id.to_string(),
+                    entity_id: eid.clone(),
+                },
+            )?;
+        }
+
+        // keep the link so the canvas can show inherited versus overridden values
+        link_entity_template(db, eid, tid)
+    })
+}
+
+fn link_entity_template(db: &Connection, eid: &String, tid: &String) -> Result<(), Error> {
+    let before: Option<String> = fetch_entity_template_link(db, eid).map_err(read_failed)?;
+    if before.as_ref() == Some(tid) {
+        return Ok(());
+    }
+
+    savepoint(db, || {
+        db.execute(
+            "INSERT INTO entity_templates_definitions (
+                entity_base_id, entity_template_id
+            ) VALUES (
+                ?1, ?2
+            ) ON CONFLICT(entity_base_id) DO UPDATE SET entity_template_id = excluded.entity_template_id;",
+            (eid, tid),
+        )?;
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_templates_definitions",
+                row_key: eid.clone(),
+                column_name: Some("entity_template_id"),
+                before,
+                after: Some(tid.clone()),
+                entity_id: Some(eid.clone()),
+            },
+        )
+    })
+}
+
+fn create_template_from_entity(db: &Connection, eid: &String, name: &String) -> Result<(), Error> {
+    let header: NPackage = match fetch_entity_header(db, eid).map_err(read_failed)? {
+        Some(header) => header,
+        None => return Err(Error::QueryReturnedNoRows),
+    };
+
+    savepoint(db, || {
+        db.execute(
+            "INSERT INTO entity_templates (
+                name, entity_core_type_id, entity_sub_type_id
+            ) VALUES (
+                ?1, ?2, ?3
+            );",
+            (name, &header.ecid, &header.esid),
+        )?;
+        let tid: i64 = db.last_insert_rowid();
+
+        // snapshot the entity's current layers, attributes and actions into the template
+        for sql in [
+            "INSERT OR IGNORE INTO entity_templates_composition_layers (entity_template_id, entity_composition_layer_id)
+                SELECT ?1, entity_composition_layer_id FROM entity_composition_layers_definitions WHERE entity_base_id = ?2;",
+            "INSERT OR IGNORE INTO entity_templates_attributes (entity_template_id, entity_attribute_id, attribute_value)
+                SELECT ?1, entity_attribute_id, attribute_value FROM entity_attributes_definitions WHERE entity_base_id = ?2;",
+            "INSERT OR IGNORE INTO entity_templates_actions (entity_template_id, entity_action_id)
+                SELECT ?1, entity_action_id FROM entity_actions_definitions WHERE entity_base_id = ?2;",
+        ] {
+            db.execute(sql, (tid, eid))?;
+        }
+
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_templates",
+                row_key: tid.to_string(),
+                column_name: None,
+                before: None,
+                after: Some(name.clone()),
+                entity_id: None,
+            },
+        )
+    })
+}
+
+fn delete_template(db: &Connection, tid: &String) -> Result<(), Error> {
+    // a template that is already gone has nothing left to delete
+    let header: TPackage = match fetch_template_header(db, tid) {
+        Some(header) => header,
+        None => return Ok(()),
+    };
+
+    savepoint(db, || {
+        // entities created from it keep their values, they just stop being linked
+        for sql in [
+            "DELETE FROM entity_templates_definitions WHERE entity_template_id = ?1;",
+            "DELETE FROM entity_templates_composition_layers WHERE entity_template_id = ?1;",
+            "DELETE FROM entity_templates_attributes WHERE entity_template_id = ?1;",
+            "DELETE FROM entity_templates_actions WHERE entity_template_id = ?1;",
+            "DELETE FROM entity_templates WHERE entity_template_id = ?1;",
+        ] {
+            db.execute(sql, (tid,))?;
+        }
+
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_templates",
+                row_key: tid.clone(),
+                column_name: None,
+                before: Some(header.name.clone()),
+                after: None,
+                entity_id: None,
+            },
+        )
+    })
+}
+
+fn escape_menu_label(s: &str) -> String {
+    // menu items treat '/' as a submenu separator and '&' as a shortcut marker
+    s.replace('/', "\\/").replace('&', "&&")
+}
+
+fn fill_template_choice(db: &Connection, choice: &mut Choice, category: String) -> () {
+    choice.clear();
+    choice.add_choice("none");
+
+    for template in rows_or_report(fetch_templates_for_core_type(db, category)) {
+        let label: String =
+            String::from_iter([&template.id.to_string()[..], ":", &template.name[..]]);
+        choice.add_choice(&escape_menu_label(&label)[..]);
+    }
+
+    choice.set_value(0);
+}
+
+fn fill_template_choices(db: &Connection) -> () {
+    for category in fetch_entity_category_names(db) {
+        let choice_id: String = String::from_iter([&category[..], "_template_choice"]);
+        if let Some(mut choice) = widget_from_id::<Choice>(&choice_id[..]) {
+            fill_template_choice(db, &mut choice, category);
+        }
+    }
+}
+
+fn build_template_status(db: &Connection, eid: &String) -> Option<(String, Vec<String>)> {
+    let tid: String = value_or_report(fetch_entity_template_link(db, eid))??;
+    let template: TPackage = fetch_template_header(db, &tid)?;
+    let mut lines: Vec<String> = Vec::new();
+
+    let template_layers: Vec<String> = rows_or_report(fetch_template_comp_layers(db, &tid))
+        .into_iter()
+        .map(|layer| layer.id.to_string())
+        .collect();
+    let entity_layers: Vec<String> = fetch_entity_comp_layer_ids(db, eid);
+    for layer in rows_or_report(fetch_entity_comp_layers_general(db)) {
+        let (comp_id, comp_name): (String, String) = (layer.id.to_string(), layer.name);
+        let status: &str = match (
+            template_layers.contains(&comp_id),
+            entity_layers.contains(&comp_id),
+        ) {
+            (true, true) => "inherited",
+            (true, false) => "overridden (removed)",
+            (false, true) => "overridden (added)",
+            (false, false) => continue,
+        };
+        lines.push(String::from_iter(["layer ", &comp_name[..], ": ", status]));
+    }
+
+    let template_attributes: Vec<(String, String)> =
+        rows_or_report(fetch_template_attributes(db, &tid))
+            .into_iter()
+            .map(|a| (a.name, a.value.to_string()))
+            .collect();
+    let entity_attributes: Vec<(String, String)> = rows_or_report(
+        parse_entity_id(eid).and_then(|id| Repository::new(db).entity_attribute_values(id)),
+    )
+    .into_iter()
+    .map(|a| (a.name, a.value.to_string()))
+    .collect();
+    for (name, value) in entity_attributes.iter() {
+        let line: String = match template_attributes.iter().find(|(n, _)| n == name) {
+            Some((_, v)) if v == value => {
+                String::from_iter(["attribute ", &name[..], " = ", &value[..], ": inherited"])
+            }
+            Some((_, v)) => String::from_iter([
+                "attribute ",
+                &name[..],
+                " = ",
+                &value[..],
+                ": overridden (template ",
+                &v[..],
+                ")",
+            ]),
+            None => String::from_iter([
+                "attribute ",
+                &name[..],
+                " = ",
+                &value[..],
+                ": overridden (added)",
+            ]),
+        };
+        lines.push(line);
+    }
+    for (name, value) in template_attributes.iter() {
+        if !entity_attributes.iter().any(|(n, _)| n == name) {
+            lines.push(String::from_iter([
+                "attribute ",
+                &name[..],
+                ": overridden (removed, template ",
+                &value[..],
+                ")",
+            ]));
+        }
+    }
+
+    let template_actions: Vec<String> = rows_or_report(fetch_template_actions(db, &tid))
+        .into_iter()
+        .map(|a| a.name.unwrap_or_default())
+        .collect();
+    let entity_actions: Vec<String> =
+        rows_or_report(parse_entity_id(eid).and_then(|id| Repository::new(db).entity_actions(id)))
+            .into_iter()
+            .map(|a| a.name.unwrap_or_default())
+            .collect();
+    for name in entity_actions.iter() {
+        let status: &str = match template_actions.contains(name) {
+            true => "inherited",
+            false => "overridden (added)",
+        };
+        lines.push(String::from_iter(["action ", &name[..], ": ", status]));
+    }
+    for name in template_actions.iter() {
+        if !entity_actions.contains(name) {
+            lines.push(String::from_iter([
+                "action ",
+                &name[..],
+                ": overridden (removed)",
+            ]));
+        }
+    }
+
+    Some((template.name, lines))
+}
+
+fn fill_template_status(db: &Connection, eid: &String) -> Option<()> {
+    let mut template_frame: Frame = widget_from_id::<Frame>("template_frame")?;
+    let mut template_browser: HoldBrowser = widget_from_id::<HoldBrowser>("template_browser")?;
+    template_browser.clear();
+
+    match build_template_status(db, eid) {
+        Some((name, lines)) => {
+            template_frame.set_label(&String::from_iter(["Template: ", &name[..]])[..]);
+            for line in lines {
+                template_browser.add(&line[..]);
+            }
+        }
+        None => template_frame.set_label("Template: none"),
+    }
+
+    template_frame.redraw();
+    Some(template_browser.redraw())
+}
+
+fn build_template_section() -> () {
+    let _: Frame = Frame::default()
+        .with_size(0, 35)
+        .with_label("Template: none")
+        .with_id("template_frame");
+    let _: HoldBrowser = HoldBrowser::default()
+        .with_size(0, 120)
+        .with_id("template_browser");
+}
+
+fn build_templates_window(templates: Vec<Template>, app_sender: Sender<Message>) -> () {
+    close_window_by_id("templates_window");
+
+    let mut templates_window: DoubleWindow = Window::default()
+        .with_size(500, 450)
+        .center_screen()
+        .with_label("Entity Templates")
+        .with_id("templates_window");
+
+    let mut browser: HoldBrowser = HoldBrowser::new(5, 5, 490, 350, "");
+    for template in templates {
+        // "<template id>:<name> (<core type> / <sub type>)"
+        browser.add(&String::from_iter([
+            &template.id.to_string()[..],
+            ":",
+            &template.name[..],
+            " (",
+            template.core_type.as_deref().unwrap_or(""),
+            " / ",
+            template.sub_type.as_deref().unwrap_or(""),
+            ")",
+        ]));
+    }
+
+    let mut save_button: Button = Button::new(5, 360, 490, 40, "Save Current Entity as Template");
+    let app_sender_clone: Sender<Message> = app_sender.clone();
+    let mut window_clone: DoubleWindow = templates_window.clone();
+    save_button.set_callback(move |_| {
+        let eid: String = match widget_from_id::<Input>("entity_base_id") {
+            Some(i) => i.value(),
+            None => String::new(),
+        };
+        if eid.is_empty() {
+            dialog::alert_default(
+                "Open an entity first, its layers, attributes and actions become the template",
+            );
+            return;
+        }
+        if let Some(name) = dialog::input_default("Template name", "") {
+            if !name.trim().is_empty() {
+                app_sender_clone.send(Message::CreateTemplate(eid, String::from(name.trim())));
+                window_clone.hide();
+            }
+        }
+    });
+
+    let mut delete_button: Button = Button::new(5, 405, 490, 40, "Delete Selected Template");
+    let mut window_clone: DoubleWindow = templates_window.clone();
+    delete_button.set_callback(move |_| {
+        let line: String = match browser.selected_text() {
+            Some(l) => l,
+            None => return,
+        };
+        let confirmed: Option<i32> = dialog::choice2_default(
+            "Delete this template? Entities created from it keep their values.",
+            "Cancel",
+            "Delete",
+            "",
+        );
+        if confirmed == Some(1) {
+            app_sender.send(Message::DeleteTemplate(slice_beginning_of_string(
+                line, ":",
+            )));
+            window_clone.hide();
+        }
+    });
+
+    templates_window.end();
+    templates_window.show();
+}
+
+fn quick_search_items() -> Vec<TreeItem> {
+    match widget_from_id::<Tree>("main_window_tree") {
+        Some(tree) => tree
+            .get_items()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|item| item.label() == Some(String::from("quick_search")))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+fn listed_entity_items() -> Vec<TreeItem> {
+    // search results are the children of each category's "quick_search" item, in display order
+    let mut items: Vec<TreeItem> = Vec::new();
+
+    if let Some(tree) = widget_from_id::<Tree>("main_window_tree") {
+        for item in tree.get_items().unwrap_or_default() {
+            if let Some(parent) = item.parent() {
+                if parent.label() == Some(String::from("quick_search"))
+                    && item.label() != Some(String::from(LOAD_MORE_LABEL))
+                {
+                    items.push(item);
+                }
+            }
+        }
+    }
+
+    items
+}
+
+fn listed_entity_ids() -> Vec<String> {
+    listed_entity_items()
+        .iter()
+        .map(|item| slice_beginning_of_string(item.label().unwrap_or_default(), ":"))
+        .collect()
+}
+
+fn highlight_selected_entities(selected: &[String]) -> Option<()> {
+    let mut tree: Tree = widget_from_id::<Tree>("main_window_tree")?;
+    let (normal_fg, normal_bg): (Color, Color) =
+        (tree.item_label_fgcolor(), tree.item_label_bgcolor());
+
+    for mut item in listed_entity_items() {
+        let eid: String = slice_beginning_of_string(item.label().unwrap_or_default(), ":");
+        match selected.contains(&eid) {
+            true => {
+                item.set_label_bgcolor(Color::Selection);
+                item.set_label_fgcolor(Color::White);
+            }
+            false => {
+                item.set_label_bgcolor(normal_bg);
+                item.set_label_fgcolor(normal_fg);
+            }
+        }
+    }
+
+    Some(tree.redraw())
+}
+
+fn active_quick_search() -> Option<TreeItem> {
+    let items: Vec<TreeItem> = quick_search_items();
+
+    // the lookup being typed in wins, then the first one under an open category
+    if let Some(focus) = app::focus() {
+        for t in items.iter() {
+            if let Some(pack) = t.try_widget() {
+                if focus.inside(&pack) {
+                    return Some(t.clone());
+                }
+            }
+        }
+    }
+
+    items
+        .iter()
+        .find(|t| t.parent().is_some_and(|p| p.is_open()))
+        .or(items.first())
+        .cloned()
+}
+
+fn focus_quick_search(t: &TreeItem) -> Option<()> {
+    let mut tree: Tree = t.tree()?;
+    // a closed category hides its lookup, so open it first
+    let mut category: TreeItem = t.parent()?;
+    category.open();
+    tree.show_item_top(t);
+
+    let parent_pack: Pack = WidgetBase::from_dyn_widget(&t.try_widget()?)?;
+    let mut input: Input = WidgetBase::from_dyn_widget(&parent_pack.child(1)?)?;
+    let _ = input.take_focus();
+    // select what's there so typing replaces the last lookup
+    let _ = input.set_position(input.value().len() as i32);
+    let _ = input.set_mark(0);
+
+    Some(tree.redraw())
+}
+
+fn show_entity_in_tree(eid: &String) -> Option<()> {
+    let mut tree: Tree = widget_from_id::<Tree>("main_window_tree")?;
+    let item: TreeItem = listed_entity_items()
+        .into_iter()
+        .find(|item| slice_beginning_of_string(item.label().unwrap_or_default(), ":") == *eid)?;
+    tree.show_item_middle(&item);
+
+    Some(tree.redraw())
+}
+
+fn remove_entity_from_tree(eid: &String) -> Option<()> {
+    let mut tree: Tree = widget_from_id::<Tree>("main_window_tree")?;
+
+    for item in listed_entity_items() {
+        if slice_beginning_of_string(item.label().unwrap_or_default(), ":") == *eid {
+            let _ = tree.remove(&item);
+        }
+    }
+
+    Some(tree.redraw())
+}
+
+fn bulk_update_comp_layers(db: &Connection, package: &BCLPackage) -> Result<(), Error> {
+    // every selected entity changes or none of them do
+    savepoint(db, || {
+        for (i, eid) in package.entity_ids.iter().enumerate() {
+            check_entity_version(db, eid, package.row_versions.get(i).copied().flatten())?;
+            let refused: Option<String> = write_comp_layer_change(
+                db,
+                CLPackage {
+                    cb_state: package.cb_state,
+                    comp_id: package.comp_id.clone(),
+                    entity_id: eid.clone(),
+                },
+            )?;
+            if let Some(reason) = refused {
+                return Err(layer_rules_refused(eid, reason));
+            }
+        }
+        Ok(())
+    })
+}
+
+fn bulk_update_attribute(db: &Connection, package: &BAPackage) -> Result<(), Error> {
+    savepoint(db, || {
+        for (i, eid) in package.entity_ids.iter().enumerate() {
+            check_entity_version(db, eid, package.row_versions.get(i).copied().flatten())?;
+            write_entity_attribute(db, eid, &package.attribute_id, package.value.clone())?;
+        }
+        Ok(())
+    })
+}
+
+fn describe_bulk_error(e: Error) -> String {
+    match e {
+        Error::StatementChangedRows(0) => String::from(
+            "one of the entities was changed by someone else, the values below are theirs",
+        ),
+        e => e.to_string(),
+    }
+}
+
+fn summarize_bulk_attribute(db: &Connection, eids: &[String], attribute_id: &String) -> String {
+    let mut values: Vec<String> = Vec::new();
+    for eid in eids.iter() {
+        let value: String = match fetch_entity_attribute_value(db, eid, attribute_id) {
+            Ok(Some(v)) => v,
+            Ok(None) => String::from("not set"),
+            Err(_) => String::from("unreadable"),
+        };
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+
+    match values.len() {
+        1 => String::from_iter(["all ", &values[0][..]]),
+        _ => String::from_iter(["mixed (", &values.join(", ")[..], ")"]),
+    }
+}
+
+fn build_bulk_edit_window(
+    db: &Connection,
+    eids: &[String],
+    problem: Option<String>,
+    app_sender: Sender<Message>,
+) -> () {
+    close_window_by_id("bulk_edit_window");
+
+    // without the versions the bulk writes couldn't tell whether someone else got there first
+    let row_versions: Vec<Option<i64>> = match eids
+        .iter()
+        .map(|eid| fetch_row_version(db, "entity_base_definitions", eid))
+        .collect::<Result<Vec<Option<i64>>, RowError>>()
+    {
+        Ok(v) => v,
+        Err(e) => return dialog::alert_default(&format!("Some data could not be read: {}", e)[..]),
+    };
+
+    let mut bulk_window: DoubleWindow = Window::default()
+        .with_size(420, 640)
+        .center_screen()
+        .with_label("Bulk Edit")
+        .with_id("bulk_edit_window");
+
+    let bulk_scroll: Scroll = Scroll::new(5, 5, 410, 630, "");
+    bulk_scroll.begin();
+    let mut bulk_pack: Pack = Pack::new(5, 5, 390, 0, "").with_type(PackType::Vertical);
+    bulk_pack.set_spacing(5);
+    bulk_pack.begin();
+
+    let _: Frame = Frame::default().with_size(0, 35).with_label(
+        &String::from_iter([
+            &eids.len().to_string()[..],
+            " entities selected: ",
+            &eids.join(", ")[..],
+        ])[..],
+    );
+
+    // the last bulk change was rolled back, the values below are what is still stored
+    if let Some(problem) = problem {
+        let mut problem_frame: Frame = Frame::default()
+            .with_size(0, 35)
+            .with_label(&String::from_iter(["Nothing was changed: ", &problem[..]])[..]);
+        problem_frame.set_label_color(Color::Red);
+    }
+
+    let _: Frame = Frame::default()
+        .with_size(0, 35)
+        .with_label("Composition Layers");
+
+    let enabled: Vec<Vec<String>> = eids
+        .iter()
+        .map(|eid| fetch_entity_comp_layer_ids(db, eid))
+        .collect();
+
+    for layer in rows_or_report(fetch_entity_comp_layers_general(db)) {
+        let (comp_id, comp_name): (String, String) = (layer.id.to_string(), layer.name);
+        let count: usize = enabled.iter().filter(|l| l.contains(&comp_id)).count();
+
+        let mut c: CheckButton = CheckButton::default().with_size(0, 30);
+        c.set_checked(count == eids.len());
+
+        // FLTK has no tri-state checkbox, so a mixed layer shows unchecked and is called out
+        match count > 0 && count < eids.len() {
+            true => {
+                c.set_label(&String::from_iter([&comp_name[..], "  (mixed)"])[..]);
+                c.set_label_color(Color::from_rgb(180, 100, 0));
+            }
+            false => c.set_label(&comp_name[..]),
+        }
+
+        let app_sender_clone: Sender<Message> = app_sender.clone();
+        let entity_ids: Vec<String> = eids.to_vec();
+        let versions: Vec<Option<i64>> = row_versions.clone();
+        c.set_callback(move |b| {
+            app_sender_clone.send(Message::BulkUpdateCompLayers(BCLPackage {
+                cb_state: b.is_checked(),
+                comp_id: comp_id.clone(),
+                entity_ids: entity_ids.clone(),
+                row_versions: versions.clone(),
+            }));
+        });
+    }
+
+    let _: Frame = Frame::default().with_size(0, 35).with_label("Attributes");
+
+    let attributes: Vec<(String, String)> = rows_or_report(Repository::new(db).attributes())
+        .into_iter()
+        .map(|a| (a.id.to_string(), a.name))
+        .collect();
+    let summaries: Vec<String> = attributes
+        .iter()
+        .map(|(aid, _)| summarize_bulk_attribute(db, eids, aid))
+        .collect();
+
+    let mut attribute_choice: Choice = Choice::default().with_size(0, 30);
+    for (aid, name) in attributes.iter() {
+        attribute_choice
+            .add_choice(&escape_menu_label(&String::from_iter([&aid[..], ":", &name[..]]))[..]);
+    }
+
+    let mut summary_frame: Frame = Frame::default().with_size(0, 30);
+    summary_frame.set_frame(FrameType::EngravedBox);
+    if let Some(first) = summaries.first() {
+        attribute_choice.set_value(0);
+        summary_frame.set_label(&first[..]);
+    }
+    attribute_choice.set_callback(move |c| {
+        if let Some(s) = summaries.get(c.value() as usize) {
+            summary_frame.set_label(&s[..]);
+        }
+    });
+
+    let value_input: Input = Input::default().with_size(0, 30);
+
+    let mut set_button: Button = Button::default().with_size(0, 35).with_label("Set on All");
+    let app_sender_clone: Sender<Message> = app_sender.clone();
+    let entity_ids: Vec<String> = eids.to_vec();
+    let versions: Vec<Option<i64>> = row_versions.clone();
+    let choice_clone: Choice = attribute_choice.clone();
+    set_button.set_callback(move |_| {
+        let value: String = String::from(value_input.value().trim());
+        if value.parse::<f64>().is_err() {
+            dialog::alert_default("Attribute values must be numbers");
+            return;
+        }
+        if let Some(text) = choice_clone.choice() {
+            app_sender_clone.send(Message::BulkUpdateAttribute(BAPackage {
+                attribute_id: slice_beginning_of_string(text, ":"),
+                value: Some(value),
+                entity_ids: entity_ids.clone(),
+                row_versions: versions.clone(),
+            }));
+        }
+    });
+
+    let mut remove_button: Button = Button::default()
+        .with_size(0, 35)
+        .with_label("Remove from All");
+    let entity_ids: Vec<String> = eids.to_vec();
+    remove_button.set_callback(move |_| {
+        if let Some(text) = attribute_choice.choice() {
+            app_sender.send(Message::BulkUpdateAttribute(BAPackage {
+                attribute_id: slice_beginning_of_string(text, ":"),
+                value: None,
+                entity_ids: entity_ids.clone(),
+                row_versions: row_versions.clone(),
+            }));
+        }
+    });
+
+    bulk_pack.end();
+    bulk_scroll.end();
+    bulk_window.end();
+    bulk_window.show();
+}
+
+fn fetch_avatars(conn: &Connection) -> Result<Vec<Avatar>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = &[];
+    query_as(
+        conn,
+        "SELECT 'a'.'entity_avatar_id', 'a'.'name' FROM 'entity_avatars' as 'a' ORDER BY 'a'.'entity_avatar_id' ASC;",
+        &x,
+    )
+}
+
+fn fetch_entity_avatar(conn: &Connection, eid: &String) -> Result<Option<String>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": eid };
+    let value: Option<TextValue> = query_one_as(
+        conn,
+        "SELECT 'd'.'entity_avatar_id' as 'value' FROM 'entity_avatars_definitions' as 'd' WHERE 'd'.'entity_base_id' = :eid AND 'd'.'entity_avatar_id' IS NOT NULL;",
+        &x,
+    )?;
+
+    Ok(value.and_then(|v| v.value))
+}
+
+fn fetch_avatar_resource(conn: &Connection, aid: &String) -> Result<Option<String>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":aid": aid };
+    let value: Option<TextValue> = query_one_as(
+        conn,
+        "SELECT 'a'.'resource' as 'value' FROM 'entity_avatars' as 'a' WHERE 'a'.'entity_avatar_id' = :aid;",
+        &x,
+    )?;
+
+    Ok(value.and_then(|v| v.value))
+}
+
+fn update_entity_avatar(
+    db: &Connection,
+    eid: &String,
+    avatar_id: Option<String>,
+) -> Result<(), Error> {
+    // one avatar per entity, so assigning replaces and None unassigns
+    let before: Option<String> = fetch_entity_avatar(db, eid).map_err(read_failed)?;
+    if before == avatar_id {
+        return Ok(());
+    }
+
+    savepoint(db, || {
+        match &avatar_id {
+            Some(aid) => db.execute(
+                "INSERT INTO entity_avatars_definitions (
+                    entity_base_id, entity_avatar_id
+                ) VALUES (
+                    ?1, ?2
+                ) ON CONFLICT(entity_base_id) DO UPDATE SET entity_avatar_id = excluded.entity_avatar_id;",
+                (eid, aid),
+            )?,
+            None => db.execute(
+                "DELETE FROM entity_avatars_definitions WHERE entity_base_id = ?1;",
+                (eid,),
+            )?,
+        };
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_avatars_definitions",
+                row_key: eid.clone(),
+                column_name: Some("entity_avatar_id"),
+                before,
+                after: avatar_id,
+                entity_id: Some(eid.clone()),
+            },
+        )
+    })
+}
+
+fn create_avatar(db: &Connection, path: &String) -> Result<String, String> {
+    // the image is read before anything is written, an avatar never lands without its bytes
+    let name: String = match Path::new(path).file_stem() {
+        Some(stem) => stem.to_string_lossy().to_string(),
+        None => return Err(format!("{} is not an image file", path)),
+    };
+    let bytes: Vec<u8> =
+        fs::read(path).map_err(|e| format!("{} could not be read: {}", path, e))?;
+
+    savepoint(db, || {
+        db.execute(
+            "INSERT INTO entity_avatars (
+                name, resource
+            ) VALUES (
+                ?1, ?2
+            );",
+            (&name, path),
+        )?;
+        let rowid: i64 = db.last_insert_rowid();
+        write_blob(db, "entity_avatars", "image", rowid, &bytes)?;
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_avatars",
+                row_key: rowid.to_string(),
+                column_name: Some("resource"),
+                before: None,
+                after: Some(path.clone()),
+                entity_id: None,
+            },
+        )?;
+        Ok(rowid.to_string())
+    })
+    .map_err(|e| format!("The avatar could not be saved: {}", e))
+}
+
+fn set_frame_image_from_file(frame: &mut Frame, path: &Path) -> bool {
+    let (w, h): (i32, i32) = (frame.w(), frame.h());
+    let extension: String = match path.extension() {
+        Some(e) => e.to_string_lossy().to_lowercase(),
+        None => String::new(),
+    };
+
+    match &extension[..] {
+        "jpg" | "jpeg" => match JpegImage::load(path) {
+            Ok(mut img) => {
+                img.scale(w, h, true, true);
+                frame.set_image(Some(img));
+                true
+            }
+            Err(_) => false,
+        },
+        "png" => match PngImage::load(path) {
+            Ok(mut img) => {
+                img.scale(w, h, true, true);
+                frame.set_image(Some(img));
+                true
+            }
+            Err(_) => false,
+        },
+        _ => false,
+    }
+}
+
+fn fetch_avatar_image(conn: &Connection, aid: &String) -> Option<BlobRef> {
+    // entity_avatar_id is the rowid, which is what blob_open wants
+    let rowid: i64 = aid.parse().ok()?;
+    fetch_blob_ref(conn, "entity_avatars", "image", rowid)
+        .ok()
+        .flatten()
+        .filter(|image| image.len > 0)
+}
+
+fn set_frame_image_from_bytes(frame: &mut Frame, bytes: &[u8]) -> bool {
+    let (w, h): (i32, i32) = (frame.w(), frame.h());
+
+    // sniff the magic numbers rather than trusting a file name we no longer have
+    if bytes.starts_with(&[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]) {
+        if let Ok(mut img) = PngImage::from_data(bytes) {
+            img.scale(w, h, true, true);
+            frame.set_image(Some(img));
+            return true;
+        }
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        if let Ok(mut img) = JpegImage::from_data(bytes) {
+            img.scale(w, h, true, true);
+            frame.set_image(Some(img));
+            return true;
+        }
+    }
+
+    false
+}
+
+fn render_blob_into_frame(frame: &mut Frame, db: &Connection, blob: &BlobRef) -> () {
+    frame.set_image(None::<PngImage>);
+    // anything too big to be a preview image is only summarized, which reads its first bytes
+    if blob.len <= MAX_PREVIEW_BLOB_BYTES {
+        if let Ok(bytes) = blob.read(db) {
+            if set_frame_image_from_bytes(frame, &bytes) {
+                return frame.set_label("");
+            }
+        }
+    }
+
+    match blob.summary(db) {
+        Ok(summary) => frame.set_label(&summary[..]),
+        Err(e) => frame.set_label(&format!("can't read the image: {}", e)[..]),
+    }
+}
+
+fn fill_avatar_section(db: &Connection, eid: &String) -> Option<()> {
+    let mut avatar_choice: Choice = widget_from_id::<Choice>("avatar_choice")?;
+    let mut avatar_preview: Frame = widget_from_id::<Frame>("avatar_preview")?;
+
+    // the avatar list can grow while the app is open, so refill it each time
+    let assigned: Option<String> = value_or_report(fetch_entity_avatar(db, eid))?;
+    avatar_choice.clear();
+    avatar_choice.add_choice("none");
+    avatar_choice.set_value(0);
+    for (index, avatar) in rows_or_report(fetch_avatars(db)).iter().enumerate() {
+        let aid: String = avatar.id.to_string();
+        avatar_choice.add_choice(
+            &escape_menu_label(&String::from_iter([&aid[..], ":", &avatar.name[..]]))[..],
+        );
+        if assigned.as_ref() == Some(&aid) {
+            avatar_choice.set_value(index as i32 + 1);
+        }
+    }
+
+    avatar_preview.set_image(None::<PngImage>);
+    avatar_preview.set_label("");
+    if let Some(aid) = assigned {
+        // the stored copy wins, older avatars only have the path they were created from
+        match (
+            fetch_avatar_image(db, &aid),
+            fetch_avatar_resource(db, &aid),
+        ) {
+            (Some(image), _) => render_blob_into_frame(&mut avatar_preview, db, &image),
+            (None, Ok(Some(resource))) => {
+                if !set_frame_image_from_file(&mut avatar_preview, Path::new(&resource)) {
+                    avatar_preview
+                        .set_label(&String::from_iter(["can't load ", &resource[..]])[..]);
+                }
+            }
+            (None, Ok(None)) => avatar_preview.set_label("no image for this avatar"),
+            (None, Err(e)) => {
+                avatar_preview.set_label(&format!("can't read the avatar: {}", e)[..])
+            }
+        }
+    }
+
+    avatar_choice.redraw();
+    Some(avatar_preview.redraw())
+}
+
+fn build_avatar_section(app_sender: Sender<Message>) -> () {
+    let _: Frame = Frame::default().with_size(0, 35).with_label("Avatar");
+
+    let mut avatar_choice: Choice = Choice::default().with_size(0, 30).with_id("avatar_choice");
+    avatar_choice.add_choice("none");
+    avatar_choice.set_value(0);
+
+    let app_sender_clone: Sender<Message> = app_sender.clone();
+    avatar_choice.set_callback(move |c| {
+        let eid: String = match widget_from_id::<Input>("entity_base_id") {
+            Some(i) => i.value(),
+            None => String::new(),
+        };
+        if eid.is_empty() {
+            c.set_value(0);
+            return;
+        }
+        let aid: Option<String> = match c.value() {
+            i if i > 0 => c.choice().map(|text| slice_beginning_of_string(text, ":")),
+            _ => None,
+        };
+        app_sender_clone.send(Message::AssignAvatar(eid, aid));
+    });
+
+    let mut avatar_preview: Frame = Frame::default().with_size(0, 160).with_id("avatar_preview");
+    avatar_preview.set_frame(FrameType::DownBox);
+
+    let mut button: Button = Button::default()
+        .with_size(0, 35)
+        .with_label("New Avatar from Image...");
+    button.set_callback(move |_| {
+        let current_directory: PathBuf = match current_dir() {
+            Ok(p) => p,
+            Err(_) => PathBuf::new(),
+        };
+        if let Ok(Some(path)) = FileDialog::new()
+            .set_location(&current_directory)
+            .add_filter("Images", &["png", "jpg", "jpeg"])
+            .show_open_single_file()
+        {
+            app_sender.send(Message::NewAvatar(path.to_string_lossy().to_string()));
+        }
+    });
+}
+
+fn fetch_states(conn: &Connection) -> Result<Vec<EntityState>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = &[];
+    query_as(
+        conn,
+        "SELECT 's'.'entity_state_id', 's'.'name' FROM 'entity_states' as 's' ORDER BY 's'.'entity_state_id' ASC;",
+        &x,
+    )
+}
+
+fn fetch_state_modifiers(conn: &Connection, sid: &String) -> Result<Vec<StateModifier>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":sid": sid };
+    query_as(
+        conn,
+        "SELECT 'd'.'index', 'd'.'entity_state_id', 'd'.'entity_attribute_id', 'a'.'name' as 'attribute_name', 'd'.'magnitude', 'd'.'duration', 'd'.'row_version' FROM 'entity_states_definitions' as 'd' LEFT JOIN 'entity_attributes' as 'a' ON 'd'.'entity_attribute_id' = 'a'.'entity_attribute_id' WHERE 'd'.'entity_state_id' = :sid ORDER BY 'd'.'index' ASC;",
+        &x,
+    )
+}
+
+fn fetch_state_modifier(conn: &Connection, index: &String) -> Option<SMPackage> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":idx": index };
+    let modifier: StateModifier = rows_or_report(query_as::<StateModifier>(
+        conn,
+        "SELECT 'd'.'index', 'd'.'entity_state_id', 'd'.'entity_attribute_id', NULL as 'attribute_name', 'd'.'magnitude', 'd'.'duration', 'd'.'row_version' FROM 'entity_states_definitions' as 'd' WHERE 'd'.'index' = :idx;",
+        &x,
+    ))
+    .pop()?;
+
+    Some(SMPackage {
+        index: Some(modifier.index.to_string()),
+        state_id: modifier.state_id.to_string(),
+        attribute_id: modifier.attribute_id.to_string(),
+        magnitude: modifier.magnitude.to_string(),
+        duration: modifier.duration.to_string(),
+        row_version: Some(modifier.row_version),
+    })
+}
+
+fn describe_state_modifier(package: &SMPackage) -> String {
+    String::from_iter([
+        "attribute ",
+        &package.attribute_id[..],
+        ", magnitude ",
+        &package.magnitude[..],
+        ", duration ",
+        &package.duration[..],
+    ])
+}
+
+fn build_state_modifier_merge_window(
+    db: &Connection,
+    mine: SMPackage,
+    version: i64,
+    app_sender: Sender<Message>,
+) -> () {
+    let index: String = mine.index.clone().unwrap_or_default();
+    let theirs: SMPackage = match fetch_state_modifier(db, &index) {
+        Some(t) => t,
+        None => return alert_stale_delete(&format!("State modifier {}", index)),
+    };
+
+    build_merge_window(
+        &format!("State modifier {}", index),
+        vec![
+            (
+                "entity_attribute_id",
+                mine.attribute_id.clone(),
+                theirs.attribute_id,
+            ),
+            ("magnitude", mine.magnitude.clone(), theirs.magnitude),
+            ("duration", mine.duration.clone(), theirs.duration),
+        ],
+        move |values| {
+            Message::SaveStateModifier(SMPackage {
+                index: mine.index.clone(),
+                state_id: mine.state_id.clone(),
+                attribute_id: values[0].clone(),
+                magnitude: values[1].clone(),
+                duration: values[2].clone(),
+                row_version: Some(version),
+            })
+        },
+        app_sender,
+    );
+}
+
+fn create_state(db: &Connection, name: &String) -> Result<(), Error> {
+    savepoint(db, || {
+        db.execute(
+            "INSERT INTO entity_states (
+                name
+            ) VALUES (
+                ?1
+            );",
+            (name,),
+        )?;
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_states",
+                row_key: db.last_insert_rowid().to_string(),
+                column_name: Some("name"),
+                before: None,
+                after: Some(name.clone()),
+                entity_id: None,
+            },
+        )
+    })
+}
+
+fn save_state_modifier(db: &Connection, package: &SMPackage) -> Result<Option<i64>, Error> {
+    // returns the modifier's current version when it moved on from the one it was read at
+    let before: Option<SMPackage> = match &package.index {
+        Some(index) => fetch_state_modifier(db, index),
+        None => None,
+    };
+
+    let written: usize = savepoint(db, || {
+        let written: usize = match &package.index {
+            Some(index) => db.execute(
+                "UPDATE entity_states_definitions SET
+                    entity_attribute_id = ?2,
+                    magnitude = ?3,
+                    duration = ?4
+                WHERE \"index\" = ?1 AND (?5 IS NULL OR row_version = ?5);",
+                (
+                    index,
+                    &package.attribute_id,
+                    &package.magnitude,
+                    &package.duration,
+                    package.row_version,
+                ),
+            )?,
+            None => db.execute(
+                "INSERT INTO entity_states_definitions (
+                    entity_state_id, entity_attribute_id, magnitude, duration
+                ) VALUES (
+                    ?1, ?2, ?3, ?4
+                );",
+                (
+                    &package.state_id,
+                    &package.attribute_id,
+                    &package.magnitude,
+                    &package.duration,
+                ),
+            )?,
+        };
+        if written == 0 {
+            return Ok(0);
+        }
+
+        let index: String = match &package.index {
+            Some(index) => index.clone(),
+            None => db.last_insert_rowid().to_string(),
+        };
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_states_definitions",
+                row_key: index,
+                column_name: None,
+                before: before.as_ref().map(describe_state_modifier),
+                after: Some(describe_state_modifier(package)),
+                entity_id: None,
+            },
+        )?;
+        Ok(written)
+    })?;
+
+    match (written, &package.index) {
+        (0, Some(index)) => {
+            stale_row_version(db, "entity_states_definitions", index, package.row_version)
+        }
+        _ => Ok(None),
+    }
+}
+
+fn delete_state_modifier(
+    db: &Connection,
+    index: &String,
+    seen: Option<i64>,
+) -> Result<Option<i64>, Error> {
+    // returns the modifier's current version when it moved on from `seen`, it is kept then
+    let before: Option<SMPackage> = fetch_state_modifier(db, index);
+
+    let written: usize = savepoint(db, || {
+        let deleted: usize = db.execute(
+            "DELETE FROM entity_states_definitions WHERE \"index\" = ?1 AND (?2 IS NULL OR row_version = ?2);",
+            (index, seen),
+        )?;
+        if deleted > 0 {
+            record_audit(
+                db,
+                AuditPackage {
+                    table_name: "entity_states_definitions",
+                    row_key: index.clone(),
+                    column_name: None,
+                    before: before.as_ref().map(describe_state_modifier),
+                    after: None,
+                    entity_id: None,
+                },
+            )?;
+        }
+        Ok(deleted)
+    })?;
+
+    match written {
+        0 => stale_row_version(db, "entity_states_definitions", index, seen),
+        _ => Ok(None),
+    }
+}
+
+fn title_case(s: &str) -> String {
+    // "POISONED" -> "Poisoned", "FIRE_BOLT" -> "Fire Bolt"
+    s.split('_')
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) => first
+                    .to_uppercase()
+                    .chain(chars.flat_map(|c| c.to_lowercase()))
+                    .collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn summarize_state(state_name: &str, modifiers: &[StateModifier]) -> String {
+    // "Poisoned: HEALTH -5 for 10 ticks, MOVE_SPEED -2 for 10 ticks"
+    let parts: Vec<String> = modifiers
+        .iter()
+        .map(|m| {
+            String::from_iter([
+                m.attribute_name.as_deref().unwrap_or(""),
+                " ",
+                &format!("{:+}", m.magnitude)[..],
+                " for ",
+                &m.duration.to_string()[..],
+                " ticks",
+            ])
+        })
+        .collect();
+
+    match parts.len() {
+        0 => String::from_iter([&title_case(state_name)[..], ": no modifiers"]),
+        _ => String::from_iter([&title_case(state_name)[..], ": ", &parts.join(", ")[..]]),
+    }
+}
+
+fn fill_states_browser(db: &Connection) -> Option<()> {
+    let mut browser: HoldBrowser = widget_from_id::<HoldBrowser>("states_browser")?;
+    browser.clear();
+
+    for state in rows_or_report(fetch_states(db)) {
+        browser.add(&String::from_iter([&state.id.to_string()[..], ":", &state.name[..]])[..]);
+    }
+
+    Some(browser.redraw())
+}
+
+fn fill_state_modifiers(db: &Connection, sid: &String, app_sender: Sender<Message>) -> Option<()> {
+    let mut modifiers_pack: Pack = widget_from_id::<Pack>("state_modifiers_pack")?;
+    let mut summary: Frame = widget_from_id::<Frame>("state_summary")?;
+
+    let state_name: String = rows_or_report(fetch_states(db))
+        .into_iter()
+        .find(|state| state.id.to_string() == *sid)
+        .map(|state| state.name)
+        .unwrap_or_default();
+    let modifiers: Vec<StateModifier> = rows_or_report(fetch_state_modifiers(db, sid));
+    summary.set_label(&summarize_state(&state_name, &modifiers)[..]);
+
+    let attributes: Vec<(String, String)> = rows_or_report(Repository::new(db).attributes())
+        .into_iter()
+        .map(|a| (a.id.to_string(), a.name))
+        .collect();
+
+    modifiers_pack.clear();
+    modifiers_pack.begin();
+
+    {
+        // grid headers
+        let inner_pack: Pack = Pack::default()
+            .with_size(0, 30)
+            .with_type(PackType::Horizontal);
+        inner_pack.begin();
+        for (label, width) in [
+            ("Attribute", 200),
+            ("Magnitude", 100),
+            ("Duration", 100),
+            ("", 150),
+        ] {
+            let mut f: Frame = Frame::default().with_size(width, 0).with_label(label);
+            f.set_frame(FrameType::EngravedBox);
+        }
+        inner_pack.end();
+    }
+
+    for modifier in modifiers {
+        let index: String = modifier.index.to_string();
+        let attribute_id: String = modifier.attribute_id.to_string();
+        let row_version: Option<i64> = Some(modifier.row_version);
+
+        let mut inner_pack: Pack = Pack::default()
+            .with_size(0, 35)
+            .with_type(PackType::Horizontal);
+        inner_pack.set_spacing(5);
+        inner_pack.begin();
+
+        let mut attribute_choice: Choice = Choice::default().with_size(200, 0);
+        for (position, (aid, name)) in attributes.iter().enumerate() {
+            attribute_choice
+                .add_choice(&escape_menu_label(&String::from_iter([&aid[..], ":", &name[..]]))[..]);
+            if *aid == attribute_id {
+                attribute_choice.set_value(position as i32);
+            }
+        }
+
+        let mut magnitude_input: IntInput = IntInput::default().with_size(100, 0);
+        magnitude_input.set_value(&modifier.magnitude.to_string()[..]);
+        let mut duration_input: IntInput = IntInput::default().with_size(100, 0);
+        duration_input.set_value(&modifier.duration.to_string()[..]);
+
+        let mut save_button: Button = Button::default().with_size(70, 0).with_label("Save");
+        let app_sender_clone: Sender<Message> = app_sender.clone();
+        let state_id: String = sid.clone();
+        let index_clone: String = index.clone();
+        save_button.set_callback(move |_| {
+            let magnitude: String = String::from(magnitude_input.value().trim());
+            let duration: String = String::from(duration_input.value().trim());
+            if magnitude.parse::<i64>().is_err() || duration.parse::<i64>().is_err() {
+                dialog::alert_default("Magnitude and duration must be whole numbers");
+                return;
+            }
+            if let Some(text) = attribute_choice.choice() {
+                app_sender_clone.send(Message::SaveStateModifier(SMPackage {
+                    index: Some(index_clone.clone()),
+                    state_id: state_id.clone(),
+                    attribute_id: slice_beginning_of_string(text, ":"),
+                    magnitude,
+                    duration,
+                    row_version,
+                }));
+            }
+        });
+
+        let mut delete_button: Button = Button::default().with_size(70, 0).with_label("Delete");
+        let app_sender_clone: Sender<Message> = app_sender.clone();
+        let state_id: String = sid.clone();
+        delete_button.set_callback(move |_| {
+            app_sender_clone.send(Message::DeleteStateModifier(
+                index.clone(),
+                state_id.clone(),
+                row_version,
+            ));
+        });
+
+        inner_pack.end();
+    }
+
+    modifiers_pack.end();
+    modifiers_pack.redraw();
+    summary.redraw();
+
+    Some(widget_from_id::<Scroll>("state_modifiers_scroll")?.redraw())
+}
+
+fn build_states_window(states: Vec<EntityState>, app_sender: Sender<Message>) -> () {
+    close_window_by_id("states_window");
+
+    let mut states_window: DoubleWindow = Window::default()
+        .with_size(900, 500)
+        .center_screen()
+        .with_label("States")
+        .with_id("states_window");
+
+    let mut browser: HoldBrowser = HoldBrowser::new(5, 5, 250, 410, "").with_id("states_browser");
+    for state in states {
+        browser.add(&String::from_iter([&state.id.to_string()[..], ":", &state.name[..]])[..]);
+    }
+    let app_sender_clone: Sender<Message> = app_sender.clone();
+    browser.set_callback(move |b| {
+        if let Some(line) = b.selected_text() {
+            app_sender_clone.send(Message::SelectState(slice_beginning_of_string(line, ":")));
+        }
+    });
+
+    let name_input: Input = Input::new(5, 420, 250, 35, "");
+    let mut new_button: Button = Button::new(5, 460, 250, 35, "New State");
+    let app_sender_clone: Sender<Message> = app_sender.clone();
+    new_button.set_callback(move |_| {
+        let name: String = name_input.value().trim().to_uppercase();
+        if !name.is_empty() {
+            app_sender_clone.send(Message::CreateState(name));
+        }
+    });
+
+    let mut summary: Frame = Frame::new(260, 5, 635, 60, "Select a state").with_id("state_summary");
+    summary.set_frame(FrameType::EngravedBox);
+    summary.set_align(Align::Inside | Align::Wrap);
+
+    let modifiers_scroll: Scroll =
+        Scroll::new(260, 70, 635, 380, "").with_id("state_modifiers_scroll");
+    modifiers_scroll.begin();
+    let mut modifiers_pack: Pack = Pack::new(260, 70, 615, 0, "")
+        .with_type(PackType::Vertical)
+        .with_id("state_modifiers_pack");
+    modifiers_pack.set_spacing(5);
+    modifiers_pack.end();
+    modifiers_scroll.end();
+
+    let mut add_button: Button = Button::new(260, 455, 635, 40, "Add Modifier");
+    add_button.set_callback(move |_| {
+        let line: String =
+            match widget_from_id::<HoldBrowser>("states_browser").and_then(|b| b.selected_text()) {
+                Some(l) => l,
+                None => {
+                    dialog::alert_default("Select a state first");
+                    return;
+                }
+            };
+        // a new modifier starts on the first attribute with zeroed numbers, edited in the grid
+        // the attribute is filled in from the db when the message is handled
+        app_sender.send(Message::SaveStateModifier(SMPackage {
+            index: None,
+            state_id: slice_beginning_of_string(line, ":"),
+            attribute_id: String::new(),
+            magnitude: String::from("0"),
+            duration: String::from("0"),
+            row_version: None,
+        }));
+    });
+
+    states_window.end();
+    states_window.show();
+}
+
+fn fetch_identity_types(conn: &Connection) -> Result<Vec<IdentityType>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = &[];
+    query_as(
+        conn,
+        "SELECT 'i'.'entity_identity_type_id', 'i'.'name', (SELECT COUNT(*) FROM 'entity_identities_definitions' as 'd' WHERE 'd'.'entity_identity_type_id' = 'i'.'entity_identity_type_id') as 'name_count' FROM 'entity_identities' as 'i' ORDER BY 'i'.'entity_identity_type_id' ASC;",
+        &x,
+    )
+}
+
+fn fetch_identity_names(conn: &Connection, tid: &String) -> Result<Vec<IdentityName>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":tid": tid };
+    query_as(
+        conn,
+        "SELECT 'd'.'index', 'd'.'name' FROM 'entity_identities_definitions' as 'd' WHERE 'd'.'entity_identity_type_id' = :tid ORDER BY 'd'.'name' ASC;",
+        &x,
+    )
+}
+
+fn fetch_entity_identity_type(conn: &Connection, eid: &String) -> Result<Option<String>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": eid };
+    let value: Option<TextValue> = query_one_as(
+        conn,
+        "SELECT 'd'.'entity_identity_type_id' as 'value' FROM 'entity_identity_types_definitions' as 'd' WHERE 'd'.'entity_base_id' = :eid AND 'd'.'entity_identity_type_id' IS NOT NULL;",
+        &x,
+    )?;
+
+    Ok(value.and_then(|v| v.value))
+}
+
+fn entity_has_identity_layer(conn: &Connection, eid: &String) -> Result<bool, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": eid };
+    let layer: Option<IntValue> = query_one_as(
+        conn,
+        "SELECT 'd'.'entity_composition_layer_id' as 'value' FROM 'entity_composition_layers_definitions' as 'd' INNER JOIN 'entity_composition_layers' as 'c' ON 'd'.'entity_composition_layer_id' = 'c'.'entity_composition_layer_id' WHERE 'd'.'entity_base_id' = :eid AND 'c'.'name' = 'EntityIdentity';",
+        &x,
+    )?;
+
+    Ok(layer.is_some())
+}
+
+fn create_identity_type(db: &Connection, name: &String) -> Result<(), Error> {
+    savepoint(db, || {
+        db.execute(
+            "INSERT INTO entity_identities (
+                name
+            ) VALUES (
+                ?1
+            );",
+            (name,),
+        )?;
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_identities",
+                row_key: db.last_insert_rowid().to_string(),
+                column_name: Some("name"),
+                before: None,
+                after: Some(name.clone()),
+                entity_id: None,
+            },
+        )
+    })
+}
+
+fn split_pasted_names(text: &str) -> Vec<String> {
+    // one name per line or comma separated, blanks and repeats dropped
+    let mut names: Vec<String> = Vec::new();
+    for name in text.split(['\n', '\r', ',']) {
+        let name: String = name.trim().to_uppercase();
+        if !name.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    names
+}
+
+fn add_identity_names(db: &Connection, tid: &String, names: &[String]) -> Result<(), Error> {
+    // a pasted list goes in as one write, names already in the pool are skipped
+    savepoint(db, || {
+        for name in names {
+            let added: usize = db.execute(
+                "INSERT INTO entity_identities_definitions (
+                    entity_identity_type_id, name
+                ) SELECT ?1, ?2
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM entity_identities_definitions WHERE entity_identity_type_id = ?1 AND name = ?2
+                );",
+                (tid, name),
+            )?;
+            if added > 0 {
+                record_audit(
+                    db,
+                    AuditPackage {
+                        table_name: "entity_identities_definitions",
+                        row_key: db.last_insert_rowid().to_string(),
+                        column_name: Some("name"),
+                        before: None,
+                        after: Some(name.clone()),
+                        entity_id: None,
+                    },
+                )?;
+            }
+        }
+        Ok(())
+    })
+}
+
+fn delete_identity_names(db: &Connection, indexes: &[String]) -> Result<(), Error> {
+    savepoint(db, || {
+        for index in indexes {
+            let x: &[(&str, &dyn ToSql)] = named_params! { ":idx": index };
+            let before: Option<TextValue> = query_one_as(
+                db,
+                "SELECT 'd'.'name' as 'value' FROM 'entity_identities_definitions' as 'd' WHERE 'd'.'index' = :idx;",
+                &x,
+            )
+            .map_err(read_failed)?;
+
+            let deleted: usize = db.execute(
+                "DELETE FROM entity_identities_definitions WHERE \"index\" = ?1;",
+                (index,),
+            )?;
+            // a name someone else already removed leaves nothing to audit
+            if deleted > 0 {
+                record_audit(
+                    db,
+                    AuditPackage {
+                        table_name: "entity_identities_definitions",
+                        row_key: index.clone(),
+                        column_name: Some("name"),
+                        before: before.and_then(|b| b.value),
+                        after: None,
+                        entity_id: None,
+                    },
+                )?;
+            }
+        }
+        Ok(())
+    })
+}
+
+fn update_entity_identity_type(
+    db: &Connection,
+    eid: &String,
+    identity_type_id: Option<String>,
+) -> Result<(), Error> {
+    // same shape as avatars, one identity type per entity and None unassigns
+    let before: Option<String> = fetch_entity_identity_type(db, eid).map_err(read_failed)?;
+    if before == identity_type_id {
+        return Ok(());
+    }
+
+    savepoint(db, || {
+        match &identity_type_id {
+            Some(tid) => db.execute(
+                "INSERT INTO entity_identity_types_definitions (
+                    entity_base_id, entity_identity_type_id
+                ) VALUES (
+                    ?1, ?2
+                ) ON CONFLICT(entity_base_id) DO UPDATE SET entity_identity_type_id = excluded.entity_identity_type_id;",
+                (eid, tid),
+            )?,
+            None => db.execute(
+                "DELETE FROM entity_identity_types_definitions WHERE entity_base_id = ?1;",
+                (eid,),
+            )?,
+        };
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_identity_types_definitions",
+                row_key: eid.clone(),
+                column_name: Some("entity_identity_type_id"),
+                before,
+                after: identity_type_id,
+                entity_id: Some(eid.clone()),
+            },
+        )
+    })
+}
+
+fn fill_identity_section(db: &Connection, eid: &String) -> Option<()> {
+    let mut identity_choice: Choice = widget_from_id::<Choice>("identity_choice")?;
+    let mut identity_names: Frame = widget_from_id::<Frame>("identity_names")?;
+
+    let assigned: Option<String> = value_or_report(fetch_entity_identity_type(db, eid))?;
+    identity_choice.clear();
+    identity_choice.add_choice("none");
+    identity_choice.set_value(0);
+    for (index, identity_type) in rows_or_report(fetch_identity_types(db)).iter().enumerate() {
+        let tid: String = identity_type.id.to_string();
+        identity_choice.add_choice(
+            &escape_menu_label(&String::from_iter([&tid[..], ":", &identity_type.name[..]]))[..],
+        );
+        if assigned.as_ref() == Some(&tid) {
+            identity_choice.set_value(index as i32 + 1);
+        }
+    }
+
+    // the identity type only means something once the EntityIdentity layer is on
+    match (
+        value_or_report(entity_has_identity_layer(db, eid))?,
+        &assigned,
+    ) {
+        (false, _) => {
+            identity_choice.deactivate();
+            identity_names.set_label("EntityIdentity layer not enabled");
+        }
+        (true, None) => {
+            identity_choice.activate();
+            identity_names.set_label("no identity type, pick one to draw names from");
+        }
+        (true, Some(tid)) => {
+            identity_choice.activate();
+            let names: Vec<String> = rows_or_report(fetch_identity_names(db, tid))
+                .into_iter()
+                .map(|n| n.name)
+                .collect();
+            identity_names
+                .set_label(&String::from_iter(["draws from: ", &names.join(", ")[..]])[..]);
+        }
+    }
+
+    identity_choice.redraw();
+    Some(identity_names.redraw())
+}
+
+fn build_identity_section(app_sender: Sender<Message>) -> () {
+    let _: Frame = Frame::default().with_size(0, 35).with_label("Identity");
+
+    let mut identity_choice: Choice = Choice::default()
+        .with_size(0, 30)
+        .with_id("identity_choice");
+    identity_choice.add_choice("none");
+    identity_choice.set_value(0);
+    identity_choice.deactivate();
+    identity_choice.set_callback(move |c| {
+        let eid: String = match widget_from_id::<Input>("entity_base_id") {
+            Some(i) => i.value(),
+            None => String::new(),
+        };
+        if eid.is_empty() {
+            c.set_value(0);
+            return;
+        }
+        let tid: Option<String> = match c.value() {
+            i if i > 0 => c.choice().map(|text| slice_beginning_of_string(text, ":")),
+            _ => None,
+        };
+        app_sender.send(Message::AssignIdentityType(eid, tid));
+    });
+
+    let mut identity_names: Frame = Frame::default().with_size(0, 50).with_id("identity_names");
+    identity_names.set_frame(FrameType::EngravedBox);
+    identity_names.set_align(Align::Inside | Align::Wrap);
+}
+
+fn fill_identity_types_browser(db: &Connection) -> Option<()> {
+    let mut browser: HoldBrowser = widget_from_id::<HoldBrowser>("identity_types_browser")?;
+    let selected: i32 = browser.value();
+    browser.clear();
+
+    for identity_type in rows_or_report(fetch_identity_types(db)) {
+        // "<type id>:<name> (<n> names)"
+        browser.add(
+            &String::from_iter([
+                &identity_type.id.to_string()[..],
+                ":",
+                &identity_type.name[..],
+                " (",
+                &identity_type.name_count.to_string()[..],
+                " names)",
+            ])[..],
+        );
+    }
+    browser.select(selected);
+
+    Some(browser.redraw())
+}
+
+fn fill_identity_names_browser(db: &Connection, tid: &String) -> Option<()> {
+    let mut browser: MultiBrowser = widget_from_id::<MultiBrowser>("identity_names_browser")?;
+    browser.clear();
+
+    for name in rows_or_report(fetch_identity_names(db, tid)) {
+        browser.add(&String::from_iter([&name.index.to_string()[..], ":", &name.name[..]])[..]);
+    }
+
+    Some(browser.redraw())
+}
+
+fn selected_identity_type() -> Option<String> {
+    let line: String = widget_from_id::<HoldBrowser>("identity_types_browser")?.selected_text()?;
+    Some(slice_beginning_of_string(line, ":"))
+}
+
+fn build_identities_window(identity_types: Vec<IdentityType>, app_sender: Sender<Message>) -> () {
+    close_window_by_id("identities_window");
+
+    let mut identities_window: DoubleWindow = Window::default()
+        .with_size(800, 500)
+        .center_screen()
+        .with_label("Identities")
+        .with_id("identities_window");
+
+    let mut types_browser: HoldBrowser =
+        HoldBrowser::new(5, 5, 250, 410, "").with_id("identity_types_browser");
+    for identity_type in identity_types {
+        types_browser.add(
+            &String::from_iter([
+                &identity_type.id.to_string()[..],
+                ":",
+                &identity_type.name[..],
+                " (",
+                &identity_type.name_count.to_string()[..],
+                " names)",
+            ])[..],
+        );
+    }
+    let app_sender_clone: Sender<Message> = app_sender.clone();
+    types_browser.set_callback(move |b| {
+        if let Some(line) = b.selected_text() {
+            app_sender_clone.send(Message::SelectIdentityType(slice_beginning_of_string(
+                line, ":",
+            )));
+        }
+    });
+
+    let type_input: Input = Input::new(5, 420, 250, 35, "");
+    let mut new_type_button: Button = Button::new(5, 460, 250, 35, "New Identity Type");
+    let app_sender_clone: Sender<Message> = app_sender.clone();
+    new_type_button.set_callback(move |_| {
+        let name: String = type_input.value().trim().to_uppercase();
+        if !name.is_empty() {
+            app_sender_clone.send(Message::CreateIdentityType(name));
+        }
+    });
+
+    let _: MultiBrowser = MultiBrowser::new(260, 5, 260, 450, "").with_id("identity_names_browser");
+    let mut delete_button: Button = Button::new(260, 460, 260, 35, "Delete Selected Names");
+    let app_sender_clone: Sender<Message> = app_sender.clone();
+    delete_button.set_callback(move |_| {
+        let (tid, browser) = match (
+            selected_identity_type(),
+            widget_from_id::<MultiBrowser>("identity_names_browser"),
+        ) {
+            (Some(t), Some(b)) => (t, b),
+            _ => return,
+        };
+        let indexes: Vec<String> = (1..=browser.size())
+            .filter(|line| browser.selected(*line))
+            .filter_map(|line| browser.text(line))
+            .map(|text| slice_beginning_of_string(text, ":"))
+            .collect();
+        if !indexes.is_empty() {
+            app_sender_clone.send(Message::DeleteIdentityNames(tid, indexes));
+        }
+    });
+
+    let mut paste_input: MultilineInput =
+        MultilineInput::new(525, 30, 270, 425, "Paste names, one per line");
+    paste_input.set_align(Align::TopLeft);
+    let mut add_button: Button = Button::new(525, 460, 270, 35, "Add Names");
+    add_button.set_callback(move |_| {
+        let tid: String = match selected_identity_type() {
+            Some(t) => t,
+            None => {
+                dialog::alert_default("Select an identity type first");
+                return;
+            }
+        };
+        let names: Vec<String> = split_pasted_names(&paste_input.value());
+        if !names.is_empty() {
+            app_sender.send(Message::AddIdentityNames(tid, names));
+            paste_input.set_value("");
+        }
+    });
+
+    identities_window.end();
+    identities_window.show();
+}
+
+fn first_attribute_id(db: &Connection) -> String {
+    // new grid rows start on the first attribute and are changed from there
+    rows_or_report(Repository::new(db).attributes())
+        .first()
+        .map(|a| a.id.to_string())
+        .unwrap_or_default()
+}
+
+fn fetch_actions(conn: &Connection) -> Result<Vec<ActionSummary>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = &[];
+    query_as(
+        conn,
+        "SELECT 'a'.'entity_action_id', 'a'.'name', 't'.'name' as 'action_type_name' FROM 'entity_actions' as 'a' LEFT JOIN 'entity_actions_types' as 't' ON 'a'.'action_type_id' = 't'.'action_type_id' ORDER BY 'a'.'entity_action_id' ASC;",
+        &x,
+    )
+}
+
+fn fetch_action_requirements(
+    conn: &Connection,
+    aid: &String,
+) -> Result<Vec<RequirementDefinition>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":aid": aid };
+    query_as(
+        conn,
+        "SELECT 'r'.'entity_action_requirement_id', 'r'.'entity_action_id', 'r'.'entity_attributes', 'a'.'name' as 'attribute_name', 'r'.'entity_action_cost', 'r'.'row_version' FROM 'entity_actions_requirements_definitions' as 'r' LEFT JOIN 'entity_attributes' as 'a' ON 'r'.'entity_attributes' = 'a'.'entity_attribute_id' WHERE 'r'.'entity_action_id' = :aid ORDER BY 'r'.'entity_action_requirement_id' ASC;",
+        &x,
+    )
+}
+
+fn fetch_action_requirement(conn: &Connection, rid: &String) -> Option<RQPackage> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":rid": rid };
+    let requirement: RequirementDefinition = rows_or_report(query_as::<RequirementDefinition>(
+        conn,
+        "SELECT 'r'.'entity_action_requirement_id', 'r'.'entity_action_id', 'r'.'entity_attributes', NULL as 'attribute_name', 'r'.'entity_action_cost', 'r'.'row_version' FROM 'entity_actions_requirements_definitions' as 'r' WHERE 'r'.'entity_action_requirement_id' = :rid;",
+        &x,
+    ))
+    .pop()?;
+    let shown = |v: Option<i64>| v.map(|v| v.to_string()).unwrap_or_default();
+
+    Some(RQPackage {
+        requirement_id: Some(requirement.requirement_id.to_string()),
+        action_id: shown(requirement.action_id),
+        attribute_id: shown(requirement.attribute_id),
+        cost: shown(requirement.cost),
+        row_version: Some(requirement.row_version),
+    })
+}
+
+fn describe_action_requirement(package: &RQPackage) -> String {
+    String::from_iter([
+        "action ",
+        &package.action_id[..],
+        ", attribute ",
+        &package.attribute_id[..],
+        ", cost ",
+        &package.cost[..],
+    ])
+}
+
+fn build_action_requirement_merge_window(
+    db: &Connection,
+    mine: RQPackage,
+    version: i64,
+    app_sender: Sender<Message>,
+) -> () {
+    let rid: String = mine.requirement_id.clone().unwrap_or_default();
+    let theirs: RQPackage = match fetch_action_requirement(db, &rid) {
+        Some(t) => t,
+        None => return alert_stale_delete(&format!("Requirement {}", rid)),
+    };
+
+    build_merge_window(
+        &format!("Requirement {}", rid),
+        vec![
+            (
+                "entity_attributes",
+                mine.attribute_id.clone(),
+                theirs.attribute_id,
+            ),
+            ("entity_action_cost", mine.cost.clone(), theirs.cost),
+        ],
+        move |values| {
+            Message::SaveActionRequirement(RQPackage {
+                requirement_id: mine.requirement_id.clone(),
+                action_id: mine.action_id.clone(),
+                attribute_id: values[0].clone(),
+                cost: values[1].clone(),
+                row_version: Some(version),
+            })
+        },
+        app_sender,
+    );
+}
+
+fn save_action_requirement(db: &Connection, package: &RQPackage) -> Result<Option<i64>, Error> {
+    // returns the requirement's current version when it moved on from the one it was read at
+    let before: Option<RQPackage> = match &package.requirement_id {
+        Some(rid) => fetch_action_requirement(db, rid),
+        None => None,
+    };
+
+    let written: usize = savepoint(db, || {
+        let written: usize = match &package.requirement_id {
+            Some(rid) => db.execute(
+                "UPDATE entity_actions_requirements_definitions SET
+                    entity_attributes = ?2,
+                    entity_action_cost = ?3
+                WHERE entity_action_requirement_id = ?1 AND (?4 IS NULL OR row_version = ?4);",
+                (
+                    rid,
+                    &package.attribute_id,
+                    &package.cost,
+                    package.row_version,
+                ),
+            )?,
+            None => db.execute(
+                "INSERT INTO entity_actions_requirements_definitions (
+                    entity_action_id, entity_attributes, entity_action_cost
+                ) VALUES (
+                    ?1, ?2, ?3
+                );",
+                (&package.action_id, &package.attribute_id, &package.cost),
+            )?,
+        };
+        if written == 0 {
+            return Ok(0);
+        }
+
+        // a new row's key is given out by the insert trigger
+        let rid: String = match &package.requirement_id {
+            Some(rid) => rid.clone(),
+            None => {
+                let x: &[(&str, &dyn ToSql)] = named_params! { ":rowid": db.last_insert_rowid() };
+                query_one_as::<IntValue>(
+                    db,
+                    "SELECT 'r'.'entity_action_requirement_id' as 'value' FROM 'entity_actions_requirements_definitions' as 'r' WHERE 'r'.'rowid' = :rowid;",
+                    &x,
+                )
+                .map_err(read_failed)?
+                .map(|k| k.value.to_string())
+                .unwrap_or_default()
+            }
+        };
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_actions_requirements_definitions",
+                row_key: rid,
+                column_name: None,
+                before: before.as_ref().map(describe_action_requirement),
+                after: Some(describe_action_requirement(package)),
+                entity_id: None,
+            },
+        )?;
+        Ok(written)
+    })?;
+
+    match (written, &package.requirement_id) {
+        (0, Some(rid)) => stale_row_version(
+            db,
+            "entity_actions_requirements_definitions",
+            rid,
+            package.row_version,
+        ),
+        _ => Ok(None),
+    }
+}
+
+fn delete_action_requirement(
+    db: &Connection,
+    rid: &String,
+    seen: Option<i64>,
+) -> Result<Option<i64>, Error> {
+    // returns the requirement's current version when it moved on from `seen`, it is kept then
+    let before: Option<RQPackage> = fetch_action_requirement(db, rid);
+
+    let written: usize = savepoint(db, || {
+        let deleted: usize = db.execute(
+            "DELETE FROM entity_actions_requirements_definitions WHERE entity_action_requirement_id = ?1 AND (?2 IS NULL OR row_version = ?2);",
+            (rid, seen),
+        )?;
+        if deleted > 0 {
+            record_audit(
+                db,
+                AuditPackage {
+                    table_name: "entity_actions_requirements_definitions",
+                    row_key: rid.clone(),
+                    column_name: None,
+                    before: before.as_ref().map(describe_action_requirement),
+                    after: None,
+                    entity_id: None,
+                },
+            )?;
+        }
+        Ok(deleted)
+    })?;
+
+    match written {
+        0 => stale_row_version(db, "entity_actions_requirements_definitions", rid, seen),
+        _ => Ok(None),
+    }
+}
+
+fn fetch_entity_action_costs(conn: &Connection, eid: &String) -> Result<Vec<ActionCost>, RowError> {
+    // one row per (action, requirement), actions without requirements come back with nulls
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": eid };
+    query_as(
+        conn,
+        "SELECT 'a'.'entity_action_id', 'a'.'name' as 'action_name', 'ea'.'name' as 'attribute_name', 'r'.'entity_action_cost', 'v'.'attribute_value' FROM 'entity_actions_definitions' as 'd' INNER JOIN 'entity_actions' as 'a' ON 'd'.'entity_action_id' = 'a'.'entity_action_id' LEFT JOIN 'entity_actions_requirements_definitions' as 'r' ON 'r'.'entity_action_id' = 'a'.'entity_action_id' LEFT JOIN 'entity_attributes' as 'ea' ON 'r'.'entity_attributes' = 'ea'.'entity_attribute_id' LEFT JOIN 'entity_attributes_definitions' as 'v' ON 'v'.'entity_base_id' = 'd'.'entity_base_id' AND 'v'.'entity_attribute_id' = 'r'.'entity_attributes' WHERE 'd'.'entity_base_id' = :eid ORDER BY 'a'.'entity_action_id' ASC, 'r'.'entity_action_requirement_id' ASC;",
+        &x,
+    )
+}
+
+fn summarize_action_costs(costs: &[ActionCost]) -> Vec<(Option<bool>, String)> {
+    // (affordable, line) per action. None means a required attribute has no value on the entity
+    let mut summary: Vec<(i64, Option<bool>, Vec<String>)> = Vec::new();
+
+    for row in costs {
+        if summary.last().map(|s| s.0) != Some(row.action_id) {
+            summary.push((
+                row.action_id,
+                Some(true),
+                vec![row.action_name.clone().unwrap_or_default()],
+            ));
+        }
+        let entry = summary.last_mut().unwrap();
+        let cost: i64 = match row.cost {
+            Some(c) => c,
+            None => continue,
+        };
+
+        let attribute: &str = row.attribute_name.as_deref().unwrap_or("");
+        match row.attribute_value {
+            None => {
+                entry.1 = None;
+                entry.2.push(String::from_iter([
+                    attribute,
+                    " ",
+                    &cost.to_string()[..],
+                    " (no value)",
+                ]));
+            }
+            Some(value) => {
+                if value < cost as f64 && entry.1.is_some() {
+                    entry.1 = Some(false);
+                }
+                entry.2.push(String::from_iter([
+                    attribute,
+                    " ",
+                    &cost.to_string()[..],
+                    " / ",
+                    &value.to_string()[..],
+                ]));
+            }
+        }
+    }
+
+    summary
+        .into_iter()
+        .map(|(_, affordable, parts)| {
+            let line: String = match parts.len() {
+                1 => String::from_iter([&parts[0][..], ": no requirements"]),
+                _ => String::from_iter([&parts[0][..], ": ", &parts[1..].join(", ")[..]]),
+            };
+            (affordable, line)
+        })
+        .collect()
+}
+
+fn fill_action_costs_section(db: &Connection, eid: &String) -> Option<()> {
+    let mut browser: HoldBrowser = widget_from_id::<HoldBrowser>("action_costs_browser")?;
+    browser.clear();
+
+    for (affordable, line) in
+        summarize_action_costs(&rows_or_report(fetch_entity_action_costs(db, eid)))
+    {
+        // @C sets the line colour, @. stops format parsing so names are shown as is
+        let prefix: &str = match affordable {
+            Some(true) => "@C60@.can afford  ",
+            Some(false) => "@C1@.can't afford  ",
+            None => "@C94@.unknown  ",
+        };
+        browser.add(&String::from_iter([prefix, &line[..]])[..]);
+    }
+
+    Some(browser.redraw())
+}
+
+fn build_action_costs_section(app_sender: Sender<Message>) -> () {
+    let _: Frame = Frame::default().with_size(0, 35).with_label("Actions");
+    let _: HoldBrowser = HoldBrowser::default()
+        .with_size(0, 100)
+        .with_id("action_costs_browser");
+
+    let mut button: Button = Button::default()
+        .with_size(0, 35)
+        .with_label("Enable Layers Required by Actions");
+    button.set_callback(move |_| {
+        if let Some(i) = widget_from_id::<Input>("entity_base_id") {
+            if !i.value().is_empty() {
+                app_sender.send(Message::SyncActionLayers(i.value()));
+            }
+        }
+    });
+}
+
+fn build_action_requirements_section(
+    db: &Connection,
+    aid: &String,
+    app_sender: Sender<Message>,
+) -> () {
+    let _: Frame = Frame::default().with_size(0, 35).with_label("Requirements");
+
+    let attributes: Vec<(String, String)> = rows_or_report(Repository::new(db).attributes())
+        .into_iter()
+        .map(|a| (a.id.to_string(), a.name))
+        .collect();
+
+    {
+        // grid headers
+        let inner_pack: Pack = Pack::default()
+            .with_size(0, 30)
+            .with_type(PackType::Horizontal);
+        inner_pack.begin();
+        for (label, width) in [("Attribute", 250), ("Cost", 100), ("", 150)] {
+            let mut f: Frame = Frame::default().with_size(width, 0).with_label(label);
+            f.set_frame(FrameType::EngravedBox);
+        }
+        inner_pack.end();
+    }
+
+    for requirement in rows_or_report(fetch_action_requirements(db, aid)) {
+        let rid: String = requirement.requirement_id.to_string();
+        let required_id: Option<String> = requirement.attribute_id.map(|a| a.to_string());
+        let row_version: Option<i64> = Some(requirement.row_version);
+
+        let mut inner_pack: Pack = Pack::default()
+            .with_size(0, 35)
+            .with_type(PackType::Horizontal);
+        inner_pack.set_spacing(5);
+        inner_pack.begin();
+
+        let mut attribute_choice: Choice = Choice::default().with_size(250, 0);
+        for (position, (attribute_id, name)) in attributes.iter().enumerate() {
+            attribute_choice.add_choice(
+                &escape_menu_label(&String::from_iter([&attribute_id[..], ":", &name[..]]))[..],
+            );
+            if Some(attribute_id) == required_id.as_ref() {
+                attribute_choice.set_value(position as i32);
+            }
+        }
+
+        let mut cost_input: IntInput = IntInput::default().with_size(100, 0);
+        if let Some(cost) = requirement.cost {
+            cost_input.set_value(&cost.to_string()[..]);
+        }
+
+        let mut save_button: Button = Button::default().with_size(70, 0).with_label("Save");
+        let app_sender_clone: Sender<Message> = app_sender.clone();
+        let action_id: String = aid.clone();
+        let rid_clone: String = rid.clone();
+        save_button.set_callback(move |_| {
+            let cost: String = String::from(cost_input.value().trim());
+            if cost.parse::<i64>().is_err() {
+                dialog::alert_default("Cost must be a whole number");
+                return;
+            }
+            if let Some(text) = attribute_choice.choice() {
+                app_sender_clone.send(Message::SaveActionRequirement(RQPackage {
+                    requirement_id: Some(rid_clone.clone()),
+                    action_id: action_id.clone(),
+                    attribute_id: slice_beginning_of_string(text, ":"),
+                    cost,
+                    row_version,
+                }));
+            }
+        });
+
+        let mut delete_button: Button = Button::default().with_size(70, 0).with_label("Delete");
+        let app_sender_clone: Sender<Message> = app_sender.clone();
+        let action_id: String = aid.clone();
+        delete_button.set_callback(move |_| {
+            app_sender_clone.send(Message::DeleteActionRequirement(
+                rid.clone(),
+                action_id.clone(),
+                row_version,
+            ));
+        });
+
+        inner_pack.end();
+    }
+
+    let mut add_button: Button = Button::default()
+        .with_size(0, 35)
+        .with_label("Add Requirement");
+    let action_id: String = aid.clone();
+    add_button.set_callback(move |_| {
+        // the attribute is filled in from the db when the message is handled
+        app_sender.send(Message::SaveActionRequirement(RQPackage {
+            requirement_id: None,
+            action_id: action_id.clone(),
+            attribute_id: String::new(),
+            cost: String::from("0"),
+            row_version: None,
+        }));
+    });
+}
+
+fn fill_action_workspace(db: &Connection, aid: &String, app_sender: Sender<Message>) -> Option<()> {
+    let mut workspace_pack: Pack = widget_from_id::<Pack>("action_workspace_pack")?;
+
+    workspace_pack.clear();
+    workspace_pack.begin();
+
+    build_action_requirements_section(db, aid, app_sender.clone());
+    build_action_link_section(db, ACTION_TARGETS, aid, app_sender.clone());
+    build_action_link_section(db, ACTION_MODALS, aid, app_sender);
+
+    workspace_pack.end();
+    workspace_pack.redraw();
+
+    Some(widget_from_id::<Scroll>("action_workspace_scroll")?.redraw())
 }
 
-fn main() -> Result<(), ()> {
-    unsafe {
-        env::set_var("RUST_BACKTRACE", "1");
+fn build_actions_window(actions: Vec<ActionSummary>, app_sender: Sender<Message>) -> () {
+    close_window_by_id("actions_window");
+
+    let mut actions_window: DoubleWindow = Window::default()
+        .with_size(900, 600)
+        .center_screen()
+        .with_label("Actions")
+        .with_id("actions_window");
+
+    let mut browser: HoldBrowser = HoldBrowser::new(5, 5, 250, 590, "").with_id("actions_browser");
+    for action in actions {
+        // "<action id>:<name> (<action type>)"
+        browser.add(
+            &String::from_iter([
+                &action.id.to_string()[..],
+                ":",
+                action.name.as_deref().unwrap_or(""),
+                " (",
+                action.action_type.as_deref().unwrap_or(""),
+                ")",
+            ])[..],
+        );
+    }
+    browser.set_callback(move |b| {
+        if let Some(line) = b.selected_text() {
+            app_sender.send(Message::SelectAction(slice_beginning_of_string(line, ":")));
+        }
+    });
+
+    let workspace_scroll: Scroll =
+        Scroll::new(260, 5, 635, 590, "").with_id("action_workspace_scroll");
+    workspace_scroll.begin();
+    let mut workspace_pack: Pack = Pack::new(260, 5, 615, 0, "")
+        .with_type(PackType::Vertical)
+        .with_id("action_workspace_pack");
+    workspace_pack.set_spacing(5);
+    workspace_pack.end();
+    workspace_scroll.end();
+
+    actions_window.end();
+    actions_window.show();
+}
+
+fn fetch_action_links(
+    conn: &Connection,
+    link: ActionLink,
+    aid: &String,
+) -> Result<Vec<LinkOption>, RowError> {
+    // every type with how many times the action is linked to it, more than one is a duplicate
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":aid": aid };
+    query_as(
+        conn,
+        &format!(
+            "SELECT 't'.'{column}' as 'type_id', 't'.'name', (SELECT COUNT(*) FROM '{table}' as 'd' WHERE 'd'.'entity_action_id' = :aid AND 'd'.'{column}' = 't'.'{column}') as 'link_count' FROM '{types_table}' as 't' ORDER BY 't'.'{column}' ASC;",
+            column = link.column,
+            table = link.table,
+            types_table = link.types_table,
+        )[..],
+        &x,
+    )
+}
+
+fn update_action_link(db: &Connection, package: &ALPackage) -> Result<(), Error> {
+    // neither link table has a unique constraint, so the insert is guarded and the
+    // delete clears every copy, which also cleans up duplicates that are already there
+    let sql: String = match package.cb_state {
+        true => format!(
+            "INSERT INTO {table} (
+                entity_action_id, {column}
+            ) SELECT ?1, ?2 WHERE NOT EXISTS (
+                SELECT 1 FROM {table} WHERE entity_action_id = ?1 AND {column} = ?2
+            );",
+            table = package.link.table,
+            column = package.link.column,
+        ),
+        false => format!(
+            "DELETE FROM {table} WHERE entity_action_id = ?1 AND {column} = ?2;",
+            table = package.link.table,
+            column = package.link.column,
+        ),
     };
-    entry_point()?;
-    Ok(())
+
+    savepoint(db, || {
+        if db.execute(&sql[..], (&package.action_id, &package.type_id))? == 0 {
+            return Ok(());
+        }
+        let (before, after): (Option<String>, Option<String>) = match package.cb_state {
+            true => (None, Some(package.type_id.clone())),
+            false => (Some(package.type_id.clone()), None),
+        };
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: package.link.table,
+                row_key: String::from_iter([&package.action_id[..], ":", &package.type_id[..]]),
+                column_name: Some(package.link.column),
+                before,
+                after,
+                entity_id: None,
+            },
+        )
+    })
 }
 
-/*
-fn load_db_into_tree(
-    tree: &mut Tree,
-) -> Result<()> {
-    let rs = query(
-        &Connection::open(DB_PATH).unwrap(),
-        "SELECT name FROM sqlite_schema WHERE type ='table' AND name NOT LIKE 'sqlite_%';",
-        &[]);
-    for x in rs.records {
-        for y in x.fields {
-            tree.add(&y.to_string()[..]);
+fn build_action_link_section(
+    db: &Connection,
+    link: ActionLink,
+    aid: &String,
+    app_sender: Sender<Message>,
+) -> () {
+    let _: Frame = Frame::default().with_size(0, 35).with_label(link.label);
+
+    for option in rows_or_report(fetch_action_links(db, link, aid)) {
+        let type_id: String = option.type_id.to_string();
+        let copies: i64 = option.link_count;
+
+        // same layout as the comp layer rows on the entity canvas
+        let mut inner_pack: Pack = Pack::default()
+            .with_size(0, 35)
+            .with_type(PackType::Horizontal);
+        inner_pack.begin();
+        let mut id_frame: Frame = Frame::default().with_size(50, 0).with_label(&type_id[..]);
+        id_frame.set_frame(FrameType::EngravedBox);
+
+        let mut name_frame: Frame = Frame::default()
+            .with_size(200, 0)
+            .with_label(&option.name[..]);
+        name_frame.set_frame(FrameType::EngravedBox);
+
+        let mut c: CheckButton = CheckButton::default().with_size(50, 0);
+        c.set_checked(copies > 0);
+
+        if copies > 1 {
+            // unchecking removes every copy
+            let mut duplicate_frame: Frame = Frame::default()
+                .with_size(150, 0)
+                .with_label(&format!("{} duplicates", copies - 1)[..]);
+            duplicate_frame.set_label_color(Color::from_rgb(200, 100, 0));
         }
+        inner_pack.end();
+
+        let app_sender_clone: Sender<Message> = app_sender.clone();
+        let action_id: String = aid.clone();
+        c.set_callback(move |b| {
+            app_sender_clone.send(Message::UpdateActionLink(ALPackage {
+                link,
+                cb_state: b.is_checked(),
+                action_id: action_id.clone(),
+                type_id: type_id.clone(),
+            }));
+        });
+    }
+}
+
+fn absolute_to_res_path(project_dir: &str, path: &Path) -> Option<String> {
+    // "/home/me/game/scripts/walk.gd" under "/home/me/game" -> "res://scripts/walk.gd"
+    let relative: &Path = path.strip_prefix(project_dir).ok()?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+
+    Some(String::from_iter(["res://", &parts.join("/")[..]]))
+}
+
+fn res_path_to_absolute(project_dir: &str, res_path: &str) -> Option<PathBuf> {
+    let relative: &str = res_path.strip_prefix("res://")?;
+    let mut path: PathBuf = PathBuf::from(project_dir);
+    for part in relative.split('/').filter(|p| !p.is_empty()) {
+        path.push(part);
+    }
+
+    Some(path)
+}
+
+fn check_res_path(project_dir: &str, res_path: &str) -> Option<&'static str> {
+    // None when the path resolves to a file, otherwise why it was flagged
+    if project_dir.is_empty() {
+        return Some("no project dir set");
+    }
+    match res_path_to_absolute(project_dir, res_path) {
+        None => Some("not a res:// path"),
+        Some(path) if !path.is_file() => Some("missing"),
+        Some(_) => None,
+    }
+}
+
+fn fetch_callable_locations(conn: &Connection) -> Result<Vec<CallableLocation>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = &[];
+    query_as(
+        conn,
+        "SELECT 'a'.'entity_action_id', 'a'.'name', 'c'.'callable_location', 'c'.'row_version' FROM 'entity_actions' as 'a' LEFT JOIN 'entity_actions_callables' as 'c' ON 'a'.'entity_action_id' = 'c'.'entity_action_id' ORDER BY 'a'.'entity_action_id' ASC;",
+        &x,
+    )
+}
+
+fn fetch_comp_layer_resources(conn: &Connection) -> Result<Vec<LayerResource>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = &[];
+    query_as(
+        conn,
+        "SELECT 'c'.'entity_composition_layer_id', 'c'.'name', 'c'.'resource', 'c'.'row_version' FROM 'entity_composition_layers' as 'c' ORDER BY COALESCE('c'.'sort_order', 'c'.'entity_composition_layer_id') ASC, 'c'.'entity_composition_layer_id' ASC;",
+        &x,
+    )
+}
+
+fn fetch_callable_location(conn: &Connection, aid: &String) -> Result<Option<String>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":aid": aid };
+    let value: Option<TextValue> = query_one_as(
+        conn,
+        "SELECT 'c'.'callable_location' as 'value' FROM 'entity_actions_callables' as 'c' WHERE 'c'.'entity_action_id' = :aid;",
+        &x,
+    )?;
+
+    Ok(value.and_then(|v| v.value))
+}
+
+fn fetch_comp_layer_resource(conn: &Connection, cid: &String) -> Result<Option<String>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":cid": cid };
+    let value: Option<TextValue> = query_one_as(
+        conn,
+        "SELECT 'c'.'resource' as 'value' FROM 'entity_composition_layers' as 'c' WHERE 'c'.'entity_composition_layer_id' = :cid;",
+        &x,
+    )?;
+
+    Ok(value.and_then(|v| v.value))
+}
+
+fn update_callable_location(
+    db: &Connection,
+    aid: &String,
+    path: &String,
+    seen: Option<i64>,
+) -> Result<Option<i64>, Error> {
+    // returns the callable's current version when it moved on from `seen`, nothing is written then
+    let before: Option<String> = fetch_callable_location(db, aid).map_err(read_failed)?;
+    if before.as_ref() == Some(path) {
+        return Ok(None);
+    }
+
+    // one callable per action, an empty path removes it
+    let written: usize = savepoint(db, || {
+        let written: usize = match path.is_empty() {
+            false => db.execute(
+                "INSERT INTO entity_actions_callables (
+                    entity_action_id, callable_location
+                ) VALUES (
+                    ?1, ?2
+                ) ON CONFLICT(entity_action_id) DO UPDATE SET callable_location = excluded.callable_location
+                WHERE ?3 IS NULL OR row_version = ?3;",
+                (aid, path, seen),
+            )?,
+            true => db.execute(
+                "DELETE FROM entity_actions_callables WHERE entity_action_id = ?1 AND (?2 IS NULL OR row_version = ?2);",
+                (aid, seen),
+            )?,
+        };
+        if written > 0 {
+            record_audit(
+                db,
+                AuditPackage {
+                    table_name: "entity_actions_callables",
+                    row_key: aid.clone(),
+                    column_name: Some("callable_location"),
+                    before,
+                    after: match path.is_empty() {
+                        true => None,
+                        false => Some(path.clone()),
+                    },
+                    entity_id: None,
+                },
+            )?;
+        }
+        Ok(written)
+    })?;
+
+    match written {
+        0 => stale_row_version(db, "entity_actions_callables", aid, seen),
+        _ => Ok(None),
     }
-    Ok(())
 }
- */
 
-fn query(
-    sqlite_connection: &Connection,
-    query_str: &str,
-    params: &[(&str, &dyn ToSql)],
-) -> RecordSet {
-    let mut rs: RecordSet = RecordSet::default();
+fn update_comp_layer_resource(
+    db: &Connection,
+    cid: &String,
+    path: &String,
+    seen: Option<i64>,
+) -> Result<Option<i64>, Error> {
+    // returns the layer's current version when it moved on from `seen`, nothing is written then
+    let before: Option<String> = fetch_comp_layer_resource(db, cid).map_err(read_failed)?;
+    let after: Option<String> = match path.is_empty() {
+        true => None,
+        false => Some(path.clone()),
+    };
+    if before == after {
+        return Ok(None);
+    }
 
-    let stmt: Result<CachedStatement, Error> = sqlite_connection.prepare_cached(query_str);
+    let written: usize = savepoint(db, || {
+        let written: usize = db.execute(
+            "UPDATE entity_composition_layers SET resource = ?2 WHERE entity_composition_layer_id = ?1 AND (?3 IS NULL OR row_version = ?3);",
+            (cid, &after, seen),
+        )?;
+        if written > 0 {
+            record_audit(
+                db,
+                AuditPackage {
+                    table_name: "entity_composition_layers",
+                    row_key: cid.clone(),
+                    column_name: Some("resource"),
+                    before,
+                    after: after.clone(),
+                    entity_id: None,
+                },
+            )?;
+        }
+        Ok(written)
+    })?;
 
-    // execute the query if the statement successfully prepared
-    match stmt {
-        Ok(mut r) => {
-            let col_count: usize = r.column_count();
-            let col_names: Vec<&str> = r.column_names();
+    match written {
+        0 => stale_row_version(db, "entity_composition_layers", cid, seen),
+        _ => Ok(None),
+    }
+}
 
-            rs.headers.column_count = col_count;
-            for x in col_names {
-                rs.headers.column_names.push(String::from(x));
+fn build_resource_path_row(
+    key: String,
+    name: String,
+    res_path: String,
+    row_version: Option<i64>,
+    project_dir: String,
+    on_save: impl Fn(String, String, Option<i64>) -> Message + 'static,
+    app_sender: Sender<Message>,
+) -> bool {
+    // returns whether the path was flagged by the checker
+    let mut inner_pack: Pack = Pack::default()
+        .with_size(0, 35)
+        .with_type(PackType::Horizontal);
+    inner_pack.set_spacing(5);
+    inner_pack.begin();
+
+    let mut id_frame: Frame = Frame::default().with_size(50, 0).with_label(&key[..]);
+    id_frame.set_frame(FrameType::EngravedBox);
+    let mut name_frame: Frame = Frame::default().with_size(180, 0).with_label(&name[..]);
+    name_frame.set_frame(FrameType::EngravedBox);
+
+    let mut path_input: Input = Input::default().with_size(420, 0);
+    path_input.set_value(&res_path[..]);
+
+    let mut browse_button: Button = Button::default().with_size(35, 0).with_label("...");
+    let mut path_input_clone: Input = path_input.clone();
+    let project_dir_clone: String = project_dir.clone();
+    browse_button.set_callback(move |_| {
+        if project_dir_clone.is_empty() {
+            dialog::alert_default("Set the Godot project directory in Settings first");
+            return;
+        }
+        if let Ok(Some(path)) = FileDialog::new()
+            .set_location(&project_dir_clone)
+            .show_open_single_file()
+        {
+            match absolute_to_res_path(&project_dir_clone, &path) {
+                Some(res_path) => path_input_clone.set_value(&res_path[..]),
+                None => dialog::alert_default("That file is outside the Godot project directory"),
             }
+        }
+    });
+
+    let mut save_button: Button = Button::default().with_size(70, 0).with_label("Save");
+    save_button.set_callback(move |_| {
+        let path: String = String::from(path_input.value().trim());
+        if !path.is_empty() && !path.starts_with("res://") {
+            dialog::alert_default("Paths must start with res://");
+            return;
+        }
+        app_sender.send(on_save(key.clone(), path, row_version));
+    });
 
-            // when iterating through the rows later, this is the function that each row will be passed into
-            let mut rows = r
-                .query_map(params, |row| {
-                    let mut v: Vec<rusqlite::types::Value> =
-                        Vec::with_capacity(rs.headers.column_count);
+    let flagged: Option<&str> = match res_path.is_empty() {
+        true => None,
+        false => check_res_path(&project_dir, &res_path),
+    };
+    let mut status_frame: Frame = Frame::default()
+        .with_size(150, 0)
+        .with_label(flagged.unwrap_or("ok"));
+    if flagged.is_some() {
+        status_frame.set_label_color(Color::Red);
+    }
 
-                    for ind in 0..col_count {
-                        v.push(row.get(ind).unwrap());
-                    }
-                    Ok(v)
-                })
-                .unwrap();
+    inner_pack.end();
 
-            while let Some(r) = rows.next() {
-                match r {
-                    Ok(e) => {
-                        let mut new_row: Record = Record::new();
-                        for ind in 0..e.len() {
-                            // get each field in this row and put it in the gd_recordset
-                            // converting each SQLite value to a Godot equivalent
-                            match &e[ind] {
-                                types::Value::Null => new_row.fields.push(SqlData::Null),
-                                types::Value::Integer(v_i64) => {
-                                    new_row.fields.push(SqlData::Integer(v_i64.clone()))
-                                }
-                                types::Value::Real(v_f64) => {
-                                    new_row.fields.push(SqlData::Real(v_f64.clone()))
-                                }
-                                types::Value::Text(v_string) => {
-                                    new_row.fields.push(SqlData::Text(v_string.clone()))
-                                }
-                                types::Value::Blob(_v_vec_u8) => {
-                                    new_row.fields.push(SqlData::Blob(()))
-                                }
-                            }
-                        }
-                        rs.records.push(new_row);
-                    }
-                    Err(_) => {}
+    flagged.is_some()
+}
+
+fn fill_resource_paths(
+    db: &Connection,
+    settings: &Settings,
+    app_sender: Sender<Message>,
+) -> Option<()> {
+    let mut paths_pack: Pack = widget_from_id::<Pack>("resource_paths_pack")?;
+    let mut summary: Frame = widget_from_id::<Frame>("resource_paths_summary")?;
+    let project_dir: &String = &settings.godot_project_dir;
+    let mut flagged: usize = 0;
+
+    paths_pack.clear();
+    paths_pack.begin();
+
+    let _: Frame = Frame::default()
+        .with_size(0, 35)
+        .with_label("Action Callables");
+    for callable in rows_or_report(fetch_callable_locations(db)) {
+        if build_resource_path_row(
+            callable.action_id.to_string(),
+            callable.action_name.unwrap_or_default(),
+            callable.location.unwrap_or_default(),
+            callable.row_version,
+            project_dir.clone(),
+            Message::SaveCallablePath,
+            app_sender.clone(),
+        ) {
+            flagged += 1;
+        }
+    }
+
+    let _: Frame = Frame::default()
+        .with_size(0, 35)
+        .with_label("Composition Layer Resources");
+    for layer in rows_or_report(fetch_comp_layer_resources(db)) {
+        if build_resource_path_row(
+            layer.id.to_string(),
+            layer.name,
+            layer.resource.unwrap_or_default(),
+            Some(layer.row_version),
+            project_dir.clone(),
+            Message::SaveCompLayerResource,
+            app_sender.clone(),
+        ) {
+            flagged += 1;
+        }
+    }
+
+    paths_pack.end();
+    paths_pack.redraw();
+
+    let text: String = match (project_dir.is_empty(), flagged) {
+        (true, _) => String::from("No Godot project directory set, paths can't be checked"),
+        (false, 0) => String::from_iter(["All paths found under ", &project_dir[..]]),
+        (false, n) => format!("{} paths flagged under {}", n, project_dir),
+    };
+    summary.set_label(&text[..]);
+    summary.redraw();
+
+    Some(widget_from_id::<Scroll>("resource_paths_scroll")?.redraw())
+}
+
+fn build_resource_paths_window(app_sender: Sender<Message>) -> () {
+    close_window_by_id("resource_paths_window");
+
+    let mut resource_paths_window: DoubleWindow = Window::default()
+        .with_size(960, 600)
+        .center_screen()
+        .with_label("Resource Paths")
+        .with_id("resource_paths_window");
+
+    let mut summary: Frame = Frame::new(5, 5, 750, 35, "").with_id("resource_paths_summary");
+    summary.set_frame(FrameType::EngravedBox);
+
+    // the checker runs whenever the list is filled, this just refills it
+    let mut check_button: Button = Button::new(760, 5, 195, 35, "Check Paths");
+    check_button.set_callback(move |_| {
+        app_sender.send(Message::CheckResourcePaths);
+    });
+
+    let paths_scroll: Scroll = Scroll::new(5, 45, 950, 550, "").with_id("resource_paths_scroll");
+    paths_scroll.begin();
+    let mut paths_pack: Pack = Pack::new(5, 45, 930, 0, "")
+        .with_type(PackType::Vertical)
+        .with_id("resource_paths_pack");
+    paths_pack.set_spacing(5);
+    paths_pack.end();
+    paths_scroll.end();
+
+    resource_paths_window.end();
+    resource_paths_window.show();
+}
+
+fn fill_comp_pack(db: &Connection, app_sender: Sender<Message>) -> Option<()> {
+    // rebuilt whenever the layer list changes so the canvas never needs a restart
+    let mut comp_pack: Pack = widget_from_id::<Pack>("comp_pack")?;
+    let comps: Vec<CompositionLayer> = rows_or_report(fetch_entity_comp_layers_general(db));
+
+    comp_pack.clear();
+    comp_pack.begin();
+
+    // getting all composition layer names for layout
+    for layer in comps {
+        // composition layer info
+        let comp_name: String = layer.name;
+        let comp_id: String = layer.id.to_string();
+        // and entity_base_id to be passed into checkbuttons
+
+        let mut inner_pack: Pack = Pack::default().with_size(0, 35).with_id(&comp_id[..]);
+        inner_pack.set_type(PackType::Horizontal);
+
+        inner_pack.begin();
+        let mut f: Frame = Frame::default().with_size(50, 0).with_label(&comp_id[..]);
+        f.set_frame(FrameType::EngravedBox);
+
+        let mut f: Frame = Frame::default()
+            .with_size(200, 0)
+            .with_label(&comp_name[..]);
+        f.set_frame(FrameType::EngravedBox);
+
+        let sender_clone: Sender<Message> = app_sender.clone();
+        let mut c: CheckButton = CheckButton::default().with_size(50, 0);
+        c.handle(move |b, event| {
+            match event {
+                Event::Released => {
+                    let eid: String = widget_from_id::<Input>("entity_base_id").unwrap().value();
+                    let c: CLPackage = CLPackage {
+                        cb_state: b.is_checked(),
+                        comp_id: comp_id.clone(),
+                        entity_id: eid,
+                    };
+                    // checkbox pushed, update the entity comp layer listings in coldstorage
+                    sender_clone.send(Message::UpdateCompLayers(c));
+                    // inform FLTK that we handled the event as 'TRUE'
+                    true
                 }
+                _ => false,
+            }
+        });
+
+        // filled per entity with the actions that need this layer
+        let mut f: Frame = Frame::default().with_size(200, 0);
+        f.set_frame(FrameType::EngravedBox);
+        inner_pack.end();
+    }
+    comp_pack.end();
+
+    Some(comp_pack.redraw())
+}
+
+fn fetch_comp_layer_usage(conn: &Connection, cid: &String) -> LayerUsage {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":cid": cid };
+    rows_or_report(query_as::<LayerUsage>(
+        conn,
+        "SELECT (SELECT COUNT(*) FROM 'entity_composition_layers_definitions' WHERE entity_composition_layer_id = :cid) as 'entities', (SELECT COUNT(*) FROM 'entity_actions_composition_definitions' WHERE entity_composition_layer_id = :cid) as 'actions', (SELECT COUNT(*) FROM 'entity_templates_composition_layers' WHERE entity_composition_layer_id = :cid) as 'templates';",
+        &x,
+    ))
+    .pop()
+    .unwrap_or_default()
+}
+
+fn create_comp_layer(db: &Connection, name: &String, resource: &String) -> Result<(), Error> {
+    let resource: Option<&String> = match resource.is_empty() {
+        true => None,
+        false => Some(resource),
+    };
+
+    savepoint(db, || {
+        db.execute(
+            "INSERT INTO entity_composition_layers (
+                name, resource, sort_order
+            ) VALUES (
+                ?1, ?2, (SELECT COALESCE(MAX(COALESCE(sort_order, entity_composition_layer_id)), 0) + 1 FROM entity_composition_layers)
+            );",
+            (name, resource),
+        )?;
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_composition_layers",
+                row_key: db.last_insert_rowid().to_string(),
+                column_name: Some("name"),
+                before: None,
+                after: Some(name.clone()),
+                entity_id: None,
+            },
+        )
+    })
+}
+
+fn fetch_comp_layer_name(conn: &Connection, cid: &String) -> Result<Option<String>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":cid": cid };
+    let value: Option<TextValue> = query_one_as(
+        conn,
+        "SELECT 'c'.'name' as 'value' FROM 'entity_composition_layers' as 'c' WHERE 'c'.'entity_composition_layer_id' = :cid;",
+        &x,
+    )?;
+
+    Ok(value.and_then(|v| v.value))
+}
+
+fn save_comp_layer(
+    db: &Connection,
+    cid: &String,
+    name: &String,
+    resource: &String,
+    seen: Option<i64>,
+) -> Result<Option<i64>, Error> {
+    // returns the layer's current version when it moved on from `seen`, nothing is written then
+    let before_name: Option<String> = fetch_comp_layer_name(db, cid).map_err(read_failed)?;
+    let before_resource: Option<String> =
+        fetch_comp_layer_resource(db, cid).map_err(read_failed)?;
+    let after_resource: Option<String> = match resource.is_empty() {
+        true => None,
+        false => Some(resource.clone()),
+    };
+    if name.is_empty() || (before_name.as_ref() == Some(name) && before_resource == after_resource)
+    {
+        return Ok(None);
+    }
+
+    // name and resource go in one update so the version is only checked once
+    let written: usize = savepoint(db, || {
+        let written: usize = db.execute(
+            "UPDATE entity_composition_layers SET name = ?2, resource = ?3 WHERE entity_composition_layer_id = ?1 AND (?4 IS NULL OR row_version = ?4);",
+            (cid, name, &after_resource, seen),
+        )?;
+        if written == 0 {
+            return Ok(0);
+        }
+
+        let columns: [(&'static str, Option<String>, Option<String>); 2] = [
+            ("name", before_name, Some(name.clone())),
+            ("resource", before_resource, after_resource.clone()),
+        ];
+        for (column, before, after) in columns {
+            if before != after {
+                record_audit(
+                    db,
+                    AuditPackage {
+                        table_name: "entity_composition_layers",
+                        row_key: cid.clone(),
+                        column_name: Some(column),
+                        before,
+                        after,
+                        entity_id: None,
+                    },
+                )?;
+            }
+        }
+        Ok(written)
+    })?;
+
+    match written {
+        0 => stale_row_version(db, "entity_composition_layers", cid, seen),
+        _ => Ok(None),
+    }
+}
+
+fn build_comp_layer_merge_window(
+    db: &Connection,
+    cid: String,
+    name: String,
+    resource: String,
+    version: i64,
+    app_sender: Sender<Message>,
+) -> () {
+    let theirs_name: String = match value_or_report(fetch_comp_layer_name(db, &cid)) {
+        Some(Some(n)) => n,
+        Some(None) => return alert_stale_delete(&format!("Composition layer {}", cid)),
+        None => return,
+    };
+    let theirs_resource: String = match value_or_report(fetch_comp_layer_resource(db, &cid)) {
+        Some(r) => r.unwrap_or_default(),
+        None => return,
+    };
+
+    build_merge_window(
+        &format!("Composition layer {}", cid),
+        vec![
+            ("name", name, theirs_name),
+            ("resource", resource, theirs_resource),
+        ],
+        move |values| {
+            Message::SaveCompLayer(
+                cid.clone(),
+                values[0].clone(),
+                values[1].clone(),
+                Some(version),
+            )
+        },
+        app_sender,
+    );
+}
+
+fn move_comp_layer(
+    db: &Connection,
+    cid: &String,
+    offset: i32,
+    seen: Option<i64>,
+) -> Option<String> {
+    // returns why the layer stayed put.
+    // renumber every layer in its current order with the moved one swapped into place
+    let mut order: Vec<String> = rows_or_report(fetch_entity_comp_layers_general(db))
+        .iter()
+        .map(|l| l.id.to_string())
+        .collect();
+    let from: usize = match order.iter().position(|id| id == cid) {
+        Some(i) => i,
+        None => return Some(format!("Layer {} no longer exists", cid)),
+    };
+    let to: usize = (from as i32 + offset).clamp(0, order.len() as i32 - 1) as usize;
+    if from == to {
+        return None;
+    }
+    order.swap(from, to);
+
+    let result: Result<(), Error> = savepoint(db, || {
+        for (position, id) in order.iter().enumerate() {
+            let position: i64 = position as i64 + 1;
+            // only the moved layer is checked, its neighbours just follow the new order
+            let checked: Option<i64> = match id == cid {
+                true => seen,
+                false => None,
+            };
+            if db.execute(
+                "UPDATE entity_composition_layers SET sort_order = ?2 WHERE entity_composition_layer_id = ?1 AND (?3 IS NULL OR row_version = ?3);",
+                (id, position, checked),
+            )? == 0
+            {
+                return Err(Error::StatementChangedRows(0));
+            }
+        }
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_composition_layers",
+                row_key: cid.clone(),
+                column_name: Some("sort_order"),
+                before: Some((from + 1).to_string()),
+                after: Some((to + 1).to_string()),
+                entity_id: None,
+            },
+        )
+    });
+
+    match result {
+        Ok(_) => None,
+        Err(Error::StatementChangedRows(0)) => Some(format!(
+            "Layer {} was changed by someone else since it was loaded, check it and move it again",
+            cid
+        )),
+        Err(e) => Some(format!("error with code {:?}", e)),
+    }
+}
+
+fn delete_comp_layer(db: &Connection, cid: &String, seen: Option<i64>) -> Option<String> {
+    // returns why the layer was kept, layers still in use are never deleted
+    let usage: LayerUsage = fetch_comp_layer_usage(db, cid);
+    if usage.entities + usage.actions + usage.templates > 0 {
+        return Some(format!(
+            "Layer {} is still used by {} entities, {} actions and {} templates, remove it from those first",
+            cid, usage.entities, usage.actions, usage.templates
+        ));
+    }
+    let stale: String = format!(
+        "Layer {} was changed by someone else since it was loaded, check it and delete again",
+        cid
+    );
+    let unreadable = |e: RowError| format!("Layer {} could not be read: {}", cid, e);
+    match fetch_row_version(db, "entity_composition_layers", cid) {
+        Ok(current) if seen.is_some() && current != seen => return Some(stale),
+        Ok(_) => {}
+        Err(e) => return Some(unreadable(e)),
+    }
+
+    let before: Option<String> = match fetch_comp_layer_name(db, cid) {
+        Ok(name) => name,
+        Err(e) => return Some(unreadable(e)),
+    };
+
+    // rules mentioning the layer go with it, and stay when the layer does
+    let result: Result<(), Error> = savepoint(db, || {
+        db.execute(
+            "DELETE FROM entity_composition_layers_rules WHERE entity_composition_layer_id = ?1 OR related_layer_id = ?1;",
+            (cid,),
+        )?;
+
+        match db.execute(
+            "DELETE FROM entity_composition_layers WHERE entity_composition_layer_id = ?1 AND (?2 IS NULL OR row_version = ?2);",
+            (cid, seen),
+        )? {
+            0 => Err(Error::StatementChangedRows(0)),
+            _ => record_audit(
+                db,
+                AuditPackage {
+                    table_name: "entity_composition_layers",
+                    row_key: cid.clone(),
+                    column_name: Some("name"),
+                    before,
+                    after: None,
+                    entity_id: None,
+                },
+            ),
+        }
+    });
+
+    match result {
+        Ok(_) => None,
+        Err(Error::StatementChangedRows(0)) => Some(stale),
+        Err(e) => Some(format!("error with code {:?}", e)),
+    }
+}
+
+fn fill_comp_layer_manager(db: &Connection, app_sender: Sender<Message>) -> Option<()> {
+    let mut layers_pack: Pack = widget_from_id::<Pack>("comp_layers_manager_pack")?;
+
+    layers_pack.clear();
+    layers_pack.begin();
+
+    for layer in rows_or_report(fetch_comp_layer_resources(db)) {
+        let cid: String = layer.id.to_string();
+        let row_version: Option<i64> = Some(layer.row_version);
+
+        let mut inner_pack: Pack = Pack::default()
+            .with_size(0, 35)
+            .with_type(PackType::Horizontal);
+        inner_pack.set_spacing(5);
+        inner_pack.begin();
+
+        let mut id_frame: Frame = Frame::default().with_size(50, 0).with_label(&cid[..]);
+        id_frame.set_frame(FrameType::EngravedBox);
+
+        let mut name_input: Input = Input::default().with_size(200, 0);
+        name_input.set_value(&layer.name[..]);
+        let mut resource_input: Input = Input::default().with_size(330, 0);
+        resource_input.set_value(layer.resource.as_deref().unwrap_or(""));
+
+        for (label, offset) in [("@8->", -1), ("@2->", 1)] {
+            let mut move_button: Button = Button::default().with_size(35, 0).with_label(label);
+            let app_sender_clone: Sender<Message> = app_sender.clone();
+            let cid_clone: String = cid.clone();
+            move_button.set_callback(move |_| {
+                app_sender_clone.send(Message::MoveCompLayer(
+                    cid_clone.clone(),
+                    offset,
+                    row_version,
+                ));
+            });
+        }
+
+        let mut save_button: Button = Button::default().with_size(70, 0).with_label("Save");
+        let app_sender_clone: Sender<Message> = app_sender.clone();
+        let cid_clone: String = cid.clone();
+        save_button.set_callback(move |_| {
+            let name: String = String::from(name_input.value().trim());
+            let resource: String = String::from(resource_input.value().trim());
+            if name.is_empty() {
+                dialog::alert_default("Layer names can't be empty");
+                return;
+            }
+            if !resource.is_empty() && !resource.starts_with("res://") {
+                dialog::alert_default("Paths must start with res://");
+                return;
+            }
+            app_sender_clone.send(Message::SaveCompLayer(
+                cid_clone.clone(),
+                name,
+                resource,
+                row_version,
+            ));
+        });
+
+        let mut delete_button: Button = Button::default().with_size(70, 0).with_label("Delete");
+        let app_sender_clone: Sender<Message> = app_sender.clone();
+        delete_button.set_callback(move |_| {
+            if let Some(0) = dialog::choice2_default(
+                &String::from_iter(["Delete composition layer ", &cid[..], "?"])[..],
+                "Delete",
+                "Cancel",
+                "",
+            ) {
+                app_sender_clone.send(Message::DeleteCompLayer(cid.clone(), row_version));
+            }
+        });
+
+        inner_pack.end();
+    }
+
+    layers_pack.end();
+    layers_pack.redraw();
+
+    Some(widget_from_id::<Scroll>("comp_layers_manager_scroll")?.redraw())
+}
+
+fn build_comp_layers_window(app_sender: Sender<Message>) -> () {
+    close_window_by_id("comp_layers_window");
+
+    let mut comp_layers_window: DoubleWindow = Window::default()
+        .with_size(850, 800)
+        .center_screen()
+        .with_label("Composition Layers")
+        .with_id("comp_layers_window");
+
+    let layers_scroll: Scroll =
+        Scroll::new(5, 5, 840, 495, "").with_id("comp_layers_manager_scroll");
+    layers_scroll.begin();
+    let mut layers_pack: Pack = Pack::new(5, 5, 820, 0, "")
+        .with_type(PackType::Vertical)
+        .with_id("comp_layers_manager_pack");
+    layers_pack.set_spacing(5);
+    layers_pack.end();
+    layers_scroll.end();
+
+    let name_input: Input = Input::new(5, 510, 250, 35, "");
+    name_input.set_tooltip("layer name, e.g. EntityInventory");
+    let resource_input: Input = Input::new(260, 510, 430, 35, "");
+    resource_input.set_tooltip("res:// path of the layer's script");
+    let mut create_button: Button = Button::new(695, 510, 150, 35, "New Layer");
+    let app_sender_clone: Sender<Message> = app_sender.clone();
+    create_button.set_callback(move |_| {
+        let name: String = String::from(name_input.value().trim());
+        let resource: String = String::from(resource_input.value().trim());
+        if name.is_empty() {
+            return;
+        }
+        if !resource.is_empty() && !resource.starts_with("res://") {
+            dialog::alert_default("Paths must start with res://");
+            return;
+        }
+        app_sender_clone.send(Message::CreateCompLayer(name, resource));
+    });
+
+    build_comp_layer_rules_section(app_sender);
+
+    comp_layers_window.end();
+    comp_layers_window.show();
+}
+
+fn fetch_comp_layer_rules(conn: &Connection) -> Result<Vec<CompositionLayerRule>, RowError> {
+    let x: &[(&str, &dyn ToSql)] = &[];
+    query_as(
+        conn,
+        "SELECT 'r'.'entity_composition_layers_rule_id', 'r'.'entity_composition_layer_id', 'a'.'name' as 'layer_name', 'r'.'relation', 'r'.'related_layer_id', 'b'.'name' as 'related_layer_name' FROM 'entity_composition_layers_rules' as 'r' LEFT JOIN 'entity_composition_layers' as 'a' ON 'r'.'entity_composition_layer_id' = 'a'.'entity_composition_layer_id' LEFT JOIN 'entity_composition_layers' as 'b' ON 'r'.'related_layer_id' = 'b'.'entity_composition_layer_id' ORDER BY 'r'.'entity_composition_layer_id' ASC, 'r'.'relation' ASC;",
+        &x,
+    )
+}
+
+fn check_comp_layer_change(
+    rules: &[(String, String, String)],
+    names: &[(String, String)],
+    enabled: &[String],
+    comp_id: &String,
+    enable: bool,
+) -> CompLayerCheck {
+    // rules are (layer, relation, related layer) and requires is followed transitively
+    let name_of = |id: &String| -> String {
+        match names.iter().find(|(i, _)| i == id) {
+            Some((_, name)) => name.clone(),
+            None => id.clone(),
+        }
+    };
+
+    if !enable {
+        let dependents: Vec<String> = rules
+            .iter()
+            .filter(|(layer, relation, related)| {
+                relation == "requires" && related == comp_id && enabled.contains(layer)
+            })
+            .map(|(layer, _, _)| name_of(layer))
+            .collect();
+        return match dependents.is_empty() {
+            true => CompLayerCheck::Allowed,
+            false => CompLayerCheck::Refused(format!(
+                "{} is required by {}, disable those first",
+                name_of(comp_id),
+                dependents.join(", ")
+            )),
+        };
+    }
+
+    let mut needed: Vec<String> = Vec::new();
+    let mut pending: Vec<String> = vec![comp_id.clone()];
+    while let Some(layer) = pending.pop() {
+        for (_, _, related) in rules
+            .iter()
+            .filter(|(l, relation, _)| *l == layer && relation == "requires")
+        {
+            if related != comp_id && !enabled.contains(related) && !needed.contains(related) {
+                needed.push(related.clone());
+                pending.push(related.clone());
+            }
+        }
+    }
+
+    let mut adding: Vec<String> = needed.clone();
+    adding.push(comp_id.clone());
+    for (layer, relation, related) in rules.iter().filter(|(_, r, _)| r == "conflicts") {
+        let clash: bool = (adding.contains(layer)
+            && (enabled.contains(related) || adding.contains(related)))
+            || (adding.contains(related) && enabled.contains(layer));
+        if clash {
+            return CompLayerCheck::Refused(format!(
+                "{} conflicts with {}",
+                name_of(layer),
+                name_of(related)
+            ));
+        }
+    }
+
+    match needed.is_empty() {
+        true => CompLayerCheck::Allowed,
+        false => {
+            let needed_names: Vec<String> = needed.iter().map(|id| name_of(id)).collect();
+            let question: String = format!(
+                "{} requires {}. Enable them too?",
+                name_of(comp_id),
+                needed_names.join(", ")
+            );
+            CompLayerCheck::NeedsPrerequisites(needed, question)
+        }
+    }
+}
+
+fn evaluate_comp_layer_change(
+    db: &Connection,
+    package: &CLPackage,
+) -> Result<CompLayerCheck, RowError> {
+    let rules: Vec<(String, String, String)> = fetch_comp_layer_rules(db)?
+        .into_iter()
+        .map(|r| {
+            (
+                r.layer_id.to_string(),
+                r.relation,
+                r.related_layer_id.to_string(),
+            )
+        })
+        .collect();
+    let names: Vec<(String, String)> = fetch_entity_comp_layers_general(db)?
+        .into_iter()
+        .map(|l| (l.id.to_string(), l.name))
+        .collect();
+    let enabled: Vec<String> = Repository::new(db)
+        .entity_composition_layers(parse_entity_id(&package.entity_id)?)?
+        .into_iter()
+        .map(|layer| layer.id.to_string())
+        .collect();
+
+    Ok(check_comp_layer_change(
+        &rules,
+        &names,
+        &enabled,
+        &package.comp_id,
+        package.cb_state,
+    ))
+}
+
+fn write_comp_layer_change(db: &Connection, package: CLPackage) -> Result<Option<String>, Error> {
+    // every layer switch goes through here so the rules hold whoever asks. prerequisites are
+    // enabled along with the layer, returns why the rules turned it down, nothing is written then
+    match evaluate_comp_layer_change(db, &package).map_err(read_failed)? {
+        CompLayerCheck::Allowed => update_entity_comp_layers(db, package)?,
+        CompLayerCheck::NeedsPrerequisites(ids, _) => savepoint(db, || {
+            for comp_id in ids {
+                update_entity_comp_layers(
+                    db,
+                    CLPackage {
+                        cb_state: true,
+                        comp_id,
+                        entity_id: package.entity_id.clone(),
+                    },
+                )?;
             }
-        }
-        Err(_) => {}
-    };
+            update_entity_comp_layers(db, package)
+        })?,
+        CompLayerCheck::Refused(reason) => return Ok(Some(reason)),
+    }
 
-    rs
+    Ok(None)
 }
 
-fn entry_point() -> Result<(), ()> {
-    let mut f: AppContext = AppContext::new();
-    f.construct();
+fn layer_rules_refused(eid: &String, reason: String) -> Error {
+    // a refusal inside a bigger write fails all of it, shown like a read error is
+    Error::ToSqlConversionFailure(format!("entity {}: {}", eid, reason).into())
+}
 
-    match widget_from_id::<DoubleWindow>("main_window") {
-        Some(mut mainwindow) => {
-            mainwindow.show();
-            f.event_loop()
+fn fill_comp_checks(db: &Connection, eid: &String) -> () {
+    let enabled: Vec<String> = fetch_entity_comp_layer_ids(db, eid);
+
+    for layer in rows_or_report(fetch_entity_comp_layers_general(db)) {
+        let comp_id: String = layer.id.to_string();
+        if let Some(p) = widget_from_id::<Pack>(&comp_id[..]) {
+            if let Some(cb) = p.child(2) {
+                let wi: Option<CheckButton> = fltk::prelude::WidgetBase::from_dyn_widget(&cb);
+                if let Some(c) = wi {
+                    c.set_checked(enabled.contains(&comp_id));
+                }
+            }
         }
-        None => Ok(()),
     }
 }
 
-fn clear_scroll_gui() -> () {
-    let header_info_pack = widget_from_id::<Pack>("header_info_pack").unwrap();
-    let comp_pack = widget_from_id::<Pack>("comp_pack").unwrap();
-    println!("clearing scroll GUI!");
-    for _ in 0..header_info_pack.children() {
-        let sub_pack_widget = header_info_pack.child(0).unwrap();
-        let wi: Option<Pack> = fltk::prelude::WidgetBase::from_dyn_widget(&sub_pack_widget);
-        let input_widget = wi.unwrap().child(1).unwrap();
-        let wi: Option<Input> = fltk::prelude::WidgetBase::from_dyn_widget(&input_widget);
-        wi.unwrap().set_value("");
+fn add_comp_layer_rule(
+    db: &Connection,
+    cid: &String,
+    relation: &String,
+    related_id: &String,
+) -> Result<(), Error> {
+    if cid == related_id {
+        dialog::alert_default("A layer can't have a rule against itself");
+        return Ok(());
     }
 
-    for i in 0..comp_pack.children() {
-        let sub_pack: Option<Pack> =
-            fltk::prelude::WidgetBase::from_dyn_widget(&comp_pack.child(i).unwrap());
-        let checkbutton = sub_pack.unwrap().child(2).unwrap();
-        let wi: Option<CheckButton> = fltk::prelude::WidgetBase::from_dyn_widget(&checkbutton);
-        wi.unwrap().set_checked(false);
-    }
+    savepoint(db, || {
+        let added: usize = db.execute(
+            "INSERT OR IGNORE INTO entity_composition_layers_rules (
+                entity_composition_layer_id, relation, related_layer_id
+            ) VALUES (
+                ?1, ?2, ?3
+            );",
+            (cid, relation, related_id),
+        )?;
+        if added == 0 {
+            return Ok(());
+        }
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_composition_layers_rules",
+                row_key: db.last_insert_rowid().to_string(),
+                column_name: None,
+                before: None,
+                after: Some(String::from_iter([
+                    &cid[..],
+                    " ",
+                    &relation[..],
+                    " ",
+                    &related_id[..],
+                ])),
+                entity_id: None,
+            },
+        )
+    })
 }
 
-fn fill_scroll_gui(eid: &String, db: &Connection) -> () {
-    clear_scroll_gui();
-    let mut hdrs: RecordSet = fetch_entity_information(db, &eid);
-    let comps: RecordSet = fetch_entity_comp_layers_defs(db, &eid);
+fn delete_comp_layer_rule(db: &Connection, rule_id: &String) -> Result<(), Error> {
+    let before: Option<String> = rows_or_report(fetch_comp_layer_rules(db))
+        .into_iter()
+        .find(|r| r.id.to_string() == *rule_id)
+        .map(|r| {
+            String::from_iter([
+                &r.layer_id.to_string()[..],
+                " ",
+                &r.relation[..],
+                " ",
+                &r.related_layer_id.to_string()[..],
+            ])
+        });
 
-    let mut fields: Vec<String> = Vec::new();
+    savepoint(db, || {
+        db.execute(
+            "DELETE FROM entity_composition_layers_rules WHERE entity_composition_layers_rule_id = ?1;",
+            (rule_id,),
+        )?;
+        record_audit(
+            db,
+            AuditPackage {
+                table_name: "entity_composition_layers_rules",
+                row_key: rule_id.clone(),
+                column_name: None,
+                before,
+                after: None,
+                entity_id: None,
+            },
+        )
+    })
+}
 
-    if let Some(r) = hdrs.records.pop() {
-        r.fields.iter().for_each(|x| fields.push(x.to_string()));
+fn fill_comp_layer_rules(db: &Connection) -> Option<()> {
+    let mut browser: HoldBrowser = widget_from_id::<HoldBrowser>("comp_layer_rules_browser")?;
+    let mut layer_choice: Choice = widget_from_id::<Choice>("comp_layer_rule_layer")?;
+    let mut related_choice: Choice = widget_from_id::<Choice>("comp_layer_rule_related")?;
+
+    browser.clear();
+    for rule in rows_or_report(fetch_comp_layer_rules(db)) {
+        // "<rule id>:<layer> requires <layer>"
+        browser.add(
+            &String::from_iter([
+                &rule.id.to_string()[..],
+                ":",
+                rule.layer_name.as_deref().unwrap_or(""),
+                " ",
+                &rule.relation[..],
+                " ",
+                rule.related_layer_name.as_deref().unwrap_or(""),
+            ])[..],
+        );
     }
 
-    for index in 0..hdrs.headers.column_count {
-        if let Some(header) = hdrs.headers.column_names.get(index) {
-            if let Some(mut input_box) = widget_from_id::<Input>(&header[..]) {
-                input_box.set_value(&fields.get(index).unwrap());
-            }
-        }
+    layer_choice.clear();
+    related_choice.clear();
+    for layer in rows_or_report(fetch_entity_comp_layers_general(db)) {
+        let label: String = escape_menu_label(&String::from_iter([
+            &layer.id.to_string()[..],
+            ":",
+            &layer.name[..],
+        ]));
+        layer_choice.add_choice(&label[..]);
+        related_choice.add_choice(&label[..]);
     }
+    layer_choice.set_value(0);
+    related_choice.set_value(0);
 
-    for record in comps.records {
-        let comp_id: String = match record.fields.get(0) {
-            Some(s) => s.to_string(),
-            None => String::new(),
-        };
+    Some(browser.redraw())
+}
 
-        let p: Pack = widget_from_id::<Pack>(&comp_id[..]).unwrap();
-        let cb = p.child(2).unwrap();
-        let wi: Option<CheckButton> = fltk::prelude::WidgetBase::from_dyn_widget(&cb);
-        wi.unwrap().set_checked(true);
-    }
+fn build_comp_layer_rules_section(app_sender: Sender<Message>) -> () {
+    let _: Frame = Frame::new(5, 550, 840, 30, "Rules");
+    let _: HoldBrowser = HoldBrowser::new(5, 580, 840, 170, "").with_id("comp_layer_rules_browser");
+
+    let layer_choice: Choice = Choice::new(5, 760, 250, 35, "").with_id("comp_layer_rule_layer");
+    let mut relation_choice: Choice = Choice::new(260, 760, 110, 35, "");
+    relation_choice.add_choice("requires|conflicts");
+    relation_choice.set_value(0);
+    let related_choice: Choice =
+        Choice::new(375, 760, 250, 35, "").with_id("comp_layer_rule_related");
+
+    let mut add_button: Button = Button::new(630, 760, 100, 35, "Add Rule");
+    let app_sender_clone: Sender<Message> = app_sender.clone();
+    add_button.set_callback(move |_| {
+        if let (Some(layer), Some(relation), Some(related)) = (
+            layer_choice.choice(),
+            relation_choice.choice(),
+            related_choice.choice(),
+        ) {
+            app_sender_clone.send(Message::AddCompLayerRule(
+                slice_beginning_of_string(layer, ":"),
+                relation,
+                slice_beginning_of_string(related, ":"),
+            ));
+        }
+    });
+
+    let mut delete_button: Button = Button::new(735, 760, 110, 35, "Delete Rule");
+    delete_button.set_callback(move |_| {
+        if let Some(browser) = widget_from_id::<HoldBrowser>("comp_layer_rules_browser") {
+            if let Some(line) = browser.selected_text() {
+                app_sender.send(Message::DeleteCompLayerRule(slice_beginning_of_string(
+                    line, ":",
+                )));
+            }
+        }
+    });
+}
 
-    widget_from_id::<Scroll>("sub_pack_scroll")
-        .unwrap()
-        .redraw();
+fn fetch_comp_layer_rule_violations(conn: &Connection) -> Result<Vec<RuleViolation>, RowError> {
+    // entities missing a required layer, or carrying both sides of a conflict
+    let x: &[(&str, &dyn ToSql)] = &[];
+    query_as(
+        conn,
+        "SELECT 'b'.'entity_base_id', 'b'.'name', 'a'.'name' as 'layer_name', 'r'.'relation', 'c'.'name' as 'related_layer_name' FROM 'entity_composition_layers_rules' as 'r' INNER JOIN 'entity_composition_layers_definitions' as 'd' ON 'd'.'entity_composition_layer_id' = 'r'.'entity_composition_layer_id' INNER JOIN 'entity_base_definitions' as 'b' ON 'b'.'entity_base_id' = 'd'.'entity_base_id' LEFT JOIN 'entity_composition_layers' as 'a' ON 'a'.'entity_composition_layer_id' = 'r'.'entity_composition_layer_id' LEFT JOIN 'entity_composition_layers' as 'c' ON 'c'.'entity_composition_layer_id' = 'r'.'related_layer_id' WHERE ('r'.'relation' = 'requires') != EXISTS (SELECT 1 FROM 'entity_composition_layers_definitions' as 'e' WHERE 'e'.'entity_base_id' = 'd'.'entity_base_id' AND 'e'.'entity_composition_layer_id' = 'r'.'related_layer_id') ORDER BY 'b'.'entity_base_id' ASC;",
+        &x,
+    )
 }
 
-#[allow(dead_code)]
-fn slice_end_of_string(s: String, delim: &str) -> String {
-    let mut x: Vec<&str> = s.split(delim).collect();
+fn run_validation(db: &Connection) -> Result<Vec<String>, RowError> {
+    let mut problems: Vec<String> = Vec::new();
 
-    match x.pop() {
-        Some(y) => y.to_string(),
-        None => String::new(),
+    for violation in fetch_comp_layer_rule_violations(db)? {
+        let layer: &str = violation.layer_name.as_deref().unwrap_or("");
+        let related: &str = violation.related_layer_name.as_deref().unwrap_or("");
+        let detail: String = match &violation.relation[..] {
+            "requires" => format!("{} requires {} but it isn't enabled", layer, related),
+            _ => format!("{} conflicts with {} but both are enabled", layer, related),
+        };
+        problems.push(String::from_iter([
+            &violation.entity_id.to_string()[..],
+            ":",
+            &violation.entity_name[..],
+            " - ",
+            &detail[..],
+        ]));
     }
-}
 
-fn slice_beginning_of_string(s: String, delim: &str) -> String {
-    let mut x: Vec<&str> = s.split(delim).collect();
-    x.reverse();
-    match x.pop() {
-        Some(y) => y.to_string(),
-        None => String::new(),
-    }
+    Ok(problems)
 }
 
-fn locate_cold_storage() -> Option<PathBuf> {
-    let current_directory: PathBuf = match current_dir() {
-        Ok(p) => p,
-        Err(_) => PathBuf::new(),
+fn build_validation_window(problems: Vec<String>) -> () {
+    close_window_by_id("validation_window");
+
+    let mut validation_window: DoubleWindow = Window::default()
+        .with_size(700, 450)
+        .center_screen()
+        .with_label("Validation")
+        .with_id("validation_window");
+
+    let summary: String = match problems.len() {
+        0 => String::from("No problems found"),
+        n => format!("{} problems found", n),
     };
+    let _: Frame = Frame::new(5, 5, 690, 35, "").with_label(&summary[..]);
 
-    match FileDialog::new()
-        .set_location(&current_directory)
-        .add_filter("DB", &["db"])
-        .show_open_single_file()
-    {
-        Ok(p) => p,
-        Err(_) => None,
+    let mut browser: HoldBrowser = HoldBrowser::new(5, 45, 690, 400, "");
+    for line in problems {
+        browser.add(&line[..]);
     }
+
+    validation_window.end();
+    validation_window.show();
 }
 
-#[allow(dead_code)]
-fn print_tree_items(tree: &mut Tree) -> () {
-    match tree.get_items() {
-        Some(v) => {
-            for i in v {
-                match tree.item_pathname(&i) {
-                    Ok(s) => println!("item pathname: {}", s),
-                    Err(_) => println!("Can't find item pathname"),
-                }
-            }
-        }
-        None => {
-            println!("No items in tree");
+fn fetch_entity_action_layers(
+    conn: &Connection,
+    eid: &String,
+) -> Result<Vec<ActionLayer>, RowError> {
+    // every layer the entity's actions pull in, once per action
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": eid };
+    query_as(
+        conn,
+        "SELECT 'v'.'entity_composition_layer_id', 'a'.'name' FROM 'entity_actions_definitions' as 'd' INNER JOIN 'v_entity_action_composition_layers' as 'v' ON 'v'.'entity_action_id' = 'd'.'entity_action_id' LEFT JOIN 'entity_actions' as 'a' ON 'a'.'entity_action_id' = 'd'.'entity_action_id' WHERE 'd'.'entity_base_id' = :eid GROUP BY 'v'.'entity_composition_layer_id', 'd'.'entity_action_id' ORDER BY 'v'.'entity_composition_layer_id' ASC, 'd'.'entity_action_id' ASC;",
+        &x,
+    )
+}
+
+fn action_driven_layers(conn: &Connection, eid: &String) -> Vec<(String, Vec<String>)> {
+    let mut layers: Vec<(String, Vec<String>)> = Vec::new();
+    for row in rows_or_report(fetch_entity_action_layers(conn, eid)) {
+        let layer_id: String = row.layer_id.to_string();
+        let action: String = row.action_name.unwrap_or_default();
+        match layers.last_mut() {
+            Some((cid, actions)) if *cid == layer_id => actions.push(action),
+            _ => layers.push((layer_id, vec![action])),
         }
-    };
+    }
+
+    layers
 }
 
-#[allow(dead_code)]
-fn print_all_tree_items() -> () {
-    let tree_object: Tree = widget_from_id::<Tree>("main_window_tree").unwrap();
-    match tree_object.find_item("Mob") {
-        Some(ti_mob) => {
-            for i in 0..=ti_mob.children() {
-                match ti_mob.child(i) {
-                    Some(ti) => {
-                        println!("tree_item: x: {}, y: {}", ti.x(), ti.y());
-                        match ti.try_widget() {
-                            Some(b) => println!(
-                                "input pos: x:{}, y:{} -- input size: w: {}, h: {}",
-                                b.x(),
-                                b.y(),
-                                b.w(),
-                                b.h()
-                            ),
-                            None => {
-                                println!("no button inner widget found")
-                            }
-                        }
+fn fill_action_driven_layers(db: &Connection, eid: &String) -> () {
+    let required: Vec<(String, Vec<String>)> = action_driven_layers(db, eid);
+    let enabled: Vec<String> = fetch_entity_comp_layer_ids(db, eid);
+
+    for layer in rows_or_report(fetch_entity_comp_layers_general(db)) {
+        let comp_id: String = layer.id.to_string();
+        let p: Pack = match widget_from_id::<Pack>(&comp_id[..]) {
+            Some(p) => p,
+            None => continue,
+        };
+        let c: Option<CheckButton> = p
+            .child(2)
+            .and_then(|w| fltk::prelude::WidgetBase::from_dyn_widget(&w));
+        let f: Option<Frame> = p
+            .child(3)
+            .and_then(|w| fltk::prelude::WidgetBase::from_dyn_widget(&w));
+        let (mut c, mut f) = match (c, f) {
+            (Some(c), Some(f)) => (c, f),
+            _ => continue,
+        };
+
+        match required.iter().find(|(cid, _)| *cid == comp_id) {
+            Some((_, actions)) => {
+                f.set_label(
+                    &String::from_iter(["required by action ", &actions.join(", ")[..]])[..],
+                );
+                f.set_frame(FrameType::FlatBox);
+                // locked while enabled, highlighted as missing until synced
+                match enabled.contains(&comp_id) {
+                    true => {
+                        f.set_color(Color::from_rgb(204, 229, 255));
+                        c.deactivate();
+                    }
+                    false => {
+                        f.set_color(Color::from_rgb(255, 214, 153));
+                        c.activate();
                     }
-                    None => {}
                 }
             }
+            None => {
+                f.set_label("");
+                f.set_frame(FrameType::EngravedBox);
+                c.activate();
+            }
         }
-        None => {}
+        f.redraw();
+        c.redraw();
     }
 }
 
-fn _on_search_fill_category_with_items() -> () {
-    let mut tree_object: Tree = widget_from_id::<Tree>("main_window_tree").unwrap();
-    for x in 0..=20 {
-        let mut s: String = String::from("Mob");
-        s.push_str("/Mob");
-        s.push_str(&x.to_string()[..]);
-
-        let tree_item = TreeItem::new(&tree_object, &x.to_string()[..]);
-
-        tree_object.add_item(&s[..], &tree_item);
-        /*
-                let mut b: Input = Input::default()
-                    .with_size(120, tree_item.label_h())
-                    .with_label("test")
-                    .with_id(&s[..]);
+fn sync_action_layers(db: &Connection, eid: &String) -> Vec<String> {
+    // enables every layer the entity's actions need, prerequisites included, in one go.
+    // returns why any layer was left off
+    let mut problems: Vec<String> = Vec::new();
+
+    let result: Result<(), Error> = savepoint(db, || {
+        for (comp_id, _) in action_driven_layers(db, eid) {
+            let package: CLPackage = CLPackage {
+                cb_state: true,
+                comp_id,
+                entity_id: eid.clone(),
+            };
+            if let Some(reason) = write_comp_layer_change(db, package)? {
+                problems.push(reason);
+            }
+        }
+        Ok(())
+    });
 
-                b.set_value("WHERE AM I");
-        */
+    if let Err(e) = result {
+        problems.push(format!("No layers were enabled: {}", e));
     }
-}
 
-fn build_out_creation_categories(app_sender: Sender<Message>) -> () {
-    match widget_from_id::<Tree>("main_window_tree") {
-        Some(mut tree) => {
-            tree.begin();
-            let tree_root: TreeItem = tree.root().unwrap();
-            let count_of_children: i32 = tree_root.children();
-            for x in 0..count_of_children {
-                let child: TreeItem = tree_root.child(x).unwrap();
-                let child_pathname: String = tree.item_pathname(&child).unwrap();
-                let mut new_tree_item: TreeItem = TreeItem::new(&tree, "quick_search");
+    problems
+}
 
-                new_tree_item.draw_item_content(|ti, render| {
-                    let dims: (i32, i32, i32, i32) =
-                        (ti.label_x(), ti.label_y(), ti.label_w(), ti.label_h());
-                    // If the widget is visible 'render'
-                    if render {
-                        if let Some(pack) = ti.try_widget() {
-                            // fetch the nested widget out of TreeItem and cast it to a Pack
-                            let mut pack: Pack = unsafe { pack.into_widget::<Pack>() };
-                            pack.set_pos(dims.0, dims.1);
-                            pack.set_size(dims.2, dims.3);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entity_creator::db::query;
+    use entity_creator::fixtures::fixture_db;
+    use entity_creator::schema::SCHEMA_STEPS;
+
+    // the fixture world as the editor sees it after opening it once
+    fn app_db() -> Connection {
+        let db: Connection = fixture_db().unwrap();
+        ensure_app_schema(&db).unwrap();
+
+        db.execute_batch(
+            "INSERT INTO entity_templates (entity_template_id, name, entity_core_type_id, entity_sub_type_id) VALUES (1, 'orc', 2, 3);
+            INSERT INTO entity_templates_composition_layers (entity_template_id, entity_composition_layer_id) VALUES (1, 1), (1, 2);
+            INSERT INTO entity_templates_attributes (entity_template_id, entity_attribute_id, attribute_value) VALUES (1, 2, 7);
+            INSERT INTO entity_templates_actions (entity_template_id, entity_action_id) VALUES (1, 1);",
+        )
+        .unwrap();
 
-                            let mut dims: (i32, i32, i32, i32) =
-                                (pack.x(), pack.y(), pack.w(), pack.h());
-                            pack.set_color(Color::Gray0);
+        db
+    }
 
-                            for i in 0..pack.children() {
-                                if let Some(mut child) = pack.child(i) {
-                                    child.set_pos(dims.0, dims.1);
-                                    child.set_size(dims.2 - 100, dims.3 / pack.children());
-                                    dims.1 += dims.3;
-                                }
-                            }
-                        }
-                    }
-                    let (label_w, _): (i32, i32) = draw::measure(&ti.label().unwrap()[..], true);
-                    return dims.0 + label_w;
-                });
+    fn column(db: &Connection, sql: &str) -> Vec<String> {
+        query(db, sql, &[])
+            .records
+            .iter()
+            .map(|r| {
+                r.fields
+                    .iter()
+                    .map(|f| f.to_string())
+                    .collect::<Vec<String>>()
+                    .join("|")
+            })
+            .collect()
+    }
 
-                let mut new_path: String = child.label().unwrap();
-                new_path.push_str("/");
-                new_path.push_str("quick_search");
+    fn audit_log(db: &Connection) -> Vec<String> {
+        column(
+            db,
+            "SELECT table_name, row_key, column_name, before_value, after_value FROM entity_audit_log ORDER BY entity_audit_log_id ASC;",
+        )
+    }
 
-                match tree.add_item(&new_path, &new_tree_item) {
-                    Some(mut ti) => {
-                        ti.set_label_size(ti.label_size() * 5);
+    fn enabled_layers(db: &Connection, eid: &str) -> Vec<String> {
+        fetch_entity_comp_layer_ids(db, &String::from(eid))
+    }
 
-                        let mut hg: Pack = Pack::new(
-                            ti.label_x(),
-                            ti.label_h(),
-                            ti.label_w(),
-                            ti.label_h() * 2,
-                            "",
-                        );
+    #[test]
+    fn new_entity_copies_its_template() {
+        let db: Connection = app_db();
 
-                        hg.set_frame(FrameType::ThinUpFrame);
+        new_entity(
+            &db,
+            &NEPackage {
+                eid: String::from("10"),
+                template_id: Some(String::from("1")),
+            },
+        )
+        .unwrap();
+
+        let header: NPackage = fetch_entity_header(&db, &String::from("10"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(header.name, "orc 10");
+        assert_eq!(
+            (header.ecid, header.esid),
+            (String::from("2"), String::from("3"))
+        );
 
-                        hg.add(&Frame::new(
-                            hg.x(),
-                            hg.y(),
-                            hg.width(),
-                            hg.height(),
-                            "Quick Lookup",
-                        ));
+        let mut layers: Vec<String> = enabled_layers(&db, "10");
+        layers.sort();
+        assert_eq!(layers, vec!["1", "2"]);
+        assert_eq!(
+            column(
+                &db,
+                "SELECT entity_attribute_id, attribute_value FROM entity_attributes_definitions WHERE entity_base_id = 10;",
+            ),
+            vec!["2|7"]
+        );
+        assert_eq!(
+            column(
+                &db,
+                "SELECT entity_action_id FROM entity_actions_definitions WHERE entity_base_id = 10;",
+            ),
+            vec!["1"]
+        );
+        assert_eq!(
+            fetch_entity_template_link(&db, &String::from("10")).unwrap(),
+            Some(String::from("1"))
+        );
+        assert_eq!(
+            audit_log(&db).first().map(|s| &s[..]),
+            Some("entity_base_definitions|10|||created")
+        );
+    }
 
-                        hg.add(&Input::new(hg.x(), hg.y(), hg.width(), hg.height(), ""));
-                        let mut button_id: String = String::from(child_pathname.clone());
-                        button_id.push_str("_search_button");
+    #[test]
+    fn new_entity_leaves_an_existing_id_alone() {
+        let db: Connection = app_db();
 
-                        let mut button: Button =
-                            Button::new(hg.x(), hg.y(), hg.width(), hg.height(), "Search Entity")
-                                .with_id(&button_id[..]);
+        new_entity(
+            &db,
+            &NEPackage {
+                eid: String::from("3"),
+                template_id: Some(String::from("1")),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            fetch_entity_header(&db, &String::from("3"))
+                .unwrap()
+                .unwrap()
+                .name,
+            "human"
+        );
+        assert_eq!(enabled_layers(&db, "3"), vec!["1", "2", "4"]);
+        assert!(audit_log(&db).is_empty());
+    }
 
-                        let tree_item: TreeItem = ti.clone();
-                        let app_sender_clone: Sender<Message> = app_sender.clone();
+    #[test]
+    fn update_entity_writes_and_audits_changed_columns() {
+        let db: Connection = app_db();
+
+        // update_entity reads this package from the canvas inputs
+        write_entity_header(
+            &db,
+            NPackage {
+                eid: String::from("4"),
+                name: String::from("hobgoblin"),
+                ecid: String::from("2"),
+                esid: String::from("3"),
+            },
+            Some(0),
+        )
+        .unwrap();
+
+        assert_eq!(
+            fetch_entity_header(&db, &String::from("4"))
+                .unwrap()
+                .unwrap()
+                .name,
+            "hobgoblin"
+        );
+        assert_eq!(
+            audit_log(&db),
+            vec!["entity_base_definitions|4|name|goblin|hobgoblin"]
+        );
+    }
 
-                        button.set_callback(move |_| {
-                            let tree_item: TreeItem = tree_item.clone();
-                            app_sender_clone.send(Message::SearchEntities(tree_item));
-                        });
+    #[test]
+    fn stale_writes_report_the_current_version() {
+        let db: Connection = app_db();
+        let eid: String = String::from("4");
+        let mine = |name: &str| NPackage {
+            eid: eid.clone(),
+            name: String::from(name),
+            ecid: String::from("2"),
+            esid: String::from("3"),
+        };
 
-                        hg.add(&button);
+        // someone else saves the goblin first, the trigger moves it to version 1
+        db.execute(
+            "UPDATE entity_base_definitions SET name = 'theirs' WHERE entity_base_id = 4;",
+            (),
+        )
+        .unwrap();
+        assert_eq!(
+            fetch_row_version(&db, "entity_base_definitions", &eid).unwrap(),
+            Some(1)
+        );
 
-                        button_id = String::from(child_pathname.clone());
-                        button_id.push_str("_clear_button");
+        assert_eq!(
+            write_entity_header(&db, mine("mine"), Some(0)).unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            fetch_entity_header(&db, &eid).unwrap().unwrap().name,
+            "theirs"
+        );
+        assert!(audit_log(&db).is_empty());
 
-                        button = Button::new(hg.x(), hg.y(), hg.width(), hg.height(), "Clear List");
+        // retried against the version the merge dialog was shown
+        assert_eq!(
+            write_entity_header(&db, mine("mine"), Some(1)).unwrap(),
+            None
+        );
+        assert_eq!(
+            fetch_entity_header(&db, &eid).unwrap().unwrap().name,
+            "mine"
+        );
+        assert_eq!(
+            fetch_row_version(&db, "entity_base_definitions", &eid).unwrap(),
+            Some(2)
+        );
 
-                        let tree_item: TreeItem = ti.clone();
-                        let app_sender_clone: Sender<Message> = app_sender.clone();
+        let requirement: String = String::from("1");
+        let seen: Option<i64> =
+            fetch_row_version(&db, "entity_actions_requirements_definitions", &requirement)
+                .unwrap();
+        let mut package: RQPackage = fetch_action_requirement(&db, &requirement).unwrap();
+        package.cost = String::from("3");
+        assert_eq!(save_action_requirement(&db, &package).unwrap(), None);
+        assert_eq!(
+            delete_action_requirement(&db, &requirement, seen).unwrap(),
+            Some(seen.unwrap() + 1)
+        );
+        assert!(fetch_action_requirement(&db, &requirement).is_some());
+
+        assert_eq!(
+            save_comp_layer(
+                &db,
+                &String::from("3"),
+                &String::from("EntityPersona"),
+                &String::new(),
+                Some(5),
+            )
+            .unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            fetch_comp_layer_name(&db, &String::from("3"))
+                .unwrap()
+                .as_deref(),
+            Some("EntityIdentity")
+        );
+    }
 
-                        button.set_callback(move |_| {
-                            let tree_item: TreeItem = tree_item.clone();
-                            app_sender_clone.send(Message::ClearEntities(tree_item));
-                        });
+    #[test]
+    fn delete_entity_takes_its_rows_with_it() {
+        let db: Connection = app_db();
+        let eid: String = String::from("3");
+        let rows_for = |table: &str| {
+            column(
+                &db,
+                &format!(
+                    "SELECT entity_base_id FROM {} WHERE entity_base_id = 3;",
+                    table
+                )[..],
+            )
+            .len()
+        };
 
-                        hg.add(&button);
+        // a version from before someone else's save keeps everything in place
+        assert!(delete_entity(&db, &eid, Some(7)).is_some());
+        assert_eq!(rows_for("entity_composition_layers_definitions"), 3);
+
+        assert_eq!(delete_entity(&db, &eid, Some(0)), None);
+        for table in [
+            "entity_base_definitions",
+            "entity_composition_layers_definitions",
+            "entity_attributes_definitions",
+            "entity_actions_definitions",
+        ] {
+            assert_eq!(rows_for(table), 0, "{}", table);
+        }
+        assert_eq!(
+            audit_log(&db),
+            vec!["entity_base_definitions|3|name|human|"]
+        );
 
-                        button_id = String::from(child_pathname.clone());
-                        button_id.push_str("_add_button");
+        // the other entities are untouched
+        assert_eq!(
+            fetch_entity_header(&db, &String::from("4"))
+                .unwrap()
+                .unwrap()
+                .name,
+            "goblin"
+        );
+    }
 
-                        button = Button::new(hg.x(), hg.y(), hg.width(), hg.height(), "New Entity");
+    #[test]
+    fn deleting_a_layer_takes_its_rules_with_it() {
+        let db: Connection = app_db();
+        let cid: String = String::from("3");
+        let rules = |db: &Connection| {
+            column(
+                db,
+                "SELECT entity_composition_layer_id FROM entity_composition_layers_rules WHERE entity_composition_layer_id = 3 OR related_layer_id = 3;",
+            )
+            .len()
+        };
+        add_comp_layer_rule(&db, &cid, &String::from("requires"), &String::from("1")).unwrap();
+        assert_eq!(rules(&db), 1);
+
+        // someone else renamed it, so the layer and its rule both stay
+        let seen: Option<i64> = fetch_row_version(&db, "entity_composition_layers", &cid).unwrap();
+        db.execute(
+            "UPDATE entity_composition_layers SET name = 'theirs' WHERE entity_composition_layer_id = 3;",
+            (),
+        )
+        .unwrap();
+        assert!(delete_comp_layer(&db, &cid, seen).is_some());
+        assert_eq!(rules(&db), 1);
+
+        let seen: Option<i64> = fetch_row_version(&db, "entity_composition_layers", &cid).unwrap();
+        assert_eq!(delete_comp_layer(&db, &cid, seen), None);
+        assert_eq!(rules(&db), 0);
+        assert_eq!(fetch_comp_layer_name(&db, &cid).unwrap(), None);
+    }
 
-                        let tree_item: TreeItem = ti.clone();
-                        let app_sender_clone: Sender<Message> = app_sender.clone();
+    #[test]
+    fn exported_entities_import_into_another_database() {
+        let db: Connection = app_db();
+        let eids: Vec<String> = vec![String::from("3"), String::from("4")];
+        let exported: String = format_entity_export(
+            &eids
+                .iter()
+                .filter_map(|eid| fetch_entity_export(&db, eid).unwrap())
+                .collect::<Vec<EXPackage>>(),
+        );
 
-                        button.set_callback(move |_| {
-                            let tree_item: TreeItem = tree_item.clone();
-                            let eid: String = get_entity_id_from_tree(&tree_item);
-                            println!("entity id is: {} with length {}", eid, eid.len());
-                            match eid.len() {
-                                0 => return,
-                                _ => app_sender_clone.send(Message::NewEntity(eid)),
-                            }
-                        });
+        let other: Connection = app_db();
+        other
+            .execute("DELETE FROM entity_attributes_definitions;", ())
+            .unwrap();
+        assert_eq!(
+            import_entity_exports(&other, &parse_entity_export(&exported)),
+            None
+        );
+        assert_eq!(
+            build_compare_rows(&other, &eids).unwrap(),
+            build_compare_rows(&db, &eids).unwrap()
+        );
+        // only the attributes that were missing had to be written
+        assert_eq!(audit_log(&other).len(), 3);
 
-                        hg.add(&button);
+        // importing the same file again changes nothing
+        import_entity_exports(&other, &parse_entity_export(&exported));
+        assert_eq!(audit_log(&other).len(), 3);
+    }
 
-                        hg.end();
-                        ti.set_widget(&hg);
-                    }
-                    None => {
-                        println!("Failed to add tree item");
-                    }
-                }
+    #[test]
+    fn duplicate_entity_copies_everything_but_the_id() {
+        let db: Connection = app_db();
+        let (eid, copy): (String, String) = (String::from("3"), fetch_next_entity_id(&db).unwrap());
+        assert_eq!(copy, "5");
+
+        assert_eq!(duplicate_entity(&db, &eid, &copy), None);
+        assert_eq!(
+            fetch_entity_header(&db, &copy).unwrap().unwrap().name,
+            "human copy"
+        );
+        let rows: Vec<(String, Vec<String>)> =
+            build_compare_rows(&db, &[eid.clone(), copy.clone()]).unwrap();
+        for (label, values) in rows.iter().skip(1) {
+            if values.len() == 2 && label != "entity_base_id" && label != "name" {
+                assert_eq!(values[0], values[1], "{}", label);
             }
-            tree.end();
         }
-        None => {}
-    }
-}
 
-fn get_entity_id_from_tree(tree_item: &TreeItem) -> Option<String> {
-    let parent_pack: Pack = WidgetBase::from_dyn_widget(&tree_item.try_widget()?)?;
-    let input: Input = WidgetBase::from_dyn_widget(&parent_pack.child(1)?)?;
+        assert!(duplicate_entity(&db, &eid, &copy).is_some());
 
-    Some(input.value())
-}
+        // a second copy can't take the first copy's name
+        assert_eq!(duplicate_entity(&db, &eid, &String::from("6")), None);
+        assert_eq!(
+            fetch_entity_header(&db, &String::from("6"))
+                .unwrap()
+                .unwrap()
+                .name,
+            "human copy 2"
+        );
+    }
 
-fn fetch_and_fill_entity_search(c: &AppContext, t: TreeItem) -> Option<()> {
-    let input_value: String = get_entity_id_from_tree(&t)?;
-    let parent: TreeItem = t.parent()?;
-    let selected_string: String = parent.label().unwrap_or(String::new());
+    #[test]
+    fn exported_names_keep_their_line_breaks_and_equals_signs() {
+        let db: Connection = app_db();
+        let mut package: EXPackage = fetch_entity_export(&db, &String::from("4"))
+            .unwrap()
+            .unwrap();
+        package.header.name = String::from("gob=lin\nchief \\ of\\n");
+
+        let exported: String = format_entity_export(&[package.clone()]);
+        assert_eq!(
+            exported.lines().filter(|l| l.starts_with("name=")).count(),
+            1
+        );
 
-    let x: String = slice_beginning_of_string(selected_string, "/");
+        let parsed: Vec<EXPackage> = parse_entity_export(&exported);
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].header.name, package.header.name);
+        // nothing assigned stays nothing rather than an empty id
+        assert_eq!(parsed[0].identity_type, None);
+        assert_eq!(parsed[0].template, None);
+    }
 
-    let r: RecordSet = match input_value.len() {
-        0 => fetch_all_entity_base_data(&c.db, x),
-        _ => fetch_specific_entity_base_data(input_value, &c.db, x),
-    };
+    #[test]
+    fn writes_without_their_audit_entry_are_rolled_back() {
+        let db: Connection = app_db();
+        let states: usize = column(&db, "SELECT name FROM entity_states;").len();
+        db.execute_batch("DROP TABLE entity_audit_log;").unwrap();
+
+        assert!(create_state(&db, &String::from("stunned")).is_err());
+        assert!(write_entity_attribute(
+            &db,
+            &String::from("4"),
+            &String::from("3"),
+            Some(String::from("5")),
+        )
+        .is_err());
 
-    fill_tree_with_entity_data(r, t, c);
+        assert_eq!(column(&db, "SELECT name FROM entity_states;").len(), states);
+        assert_eq!(
+            fetch_entity_attribute_value(&db, &String::from("4"), &String::from("3")).unwrap(),
+            None
+        );
+        assert!(db.is_autocommit());
+    }
 
-    Some(())
-}
+    #[test]
+    fn reverting_a_new_attribute_removes_it() {
+        let db: Connection = app_db();
+        let (eid, attribute_id): (String, String) = (String::from("4"), String::from("3"));
+        assert_eq!(
+            fetch_entity_attribute_value(&db, &eid, &attribute_id).unwrap(),
+            None
+        );
 
-fn fetch_entity_categories(conn: &Connection) -> Vec<String> {
-    let rs = query(conn, "SELECT name FROM entity_core_types;", &[]);
+        write_entity_attribute(&db, &eid, &attribute_id, Some(String::from("5"))).unwrap();
+        let entry: AuditEntry = rows_or_report(fetch_entity_history(&db, &eid)).remove(0);
+        assert_eq!(entry.before, None);
+        assert_eq!(describe_audit_entry(&entry), "attribute 3: nothing -> '5'");
 
-    let v: Vec<String> = rs
-        .records
-        .iter()
-        .map(|x| match x.fields.len() {
-            0 => String::new(),
-            _ => x.fields[0].to_string(),
-        })
-        .collect();
-    v
-}
+        assert_eq!(
+            revert_audit_entry(&db, &entry.id.to_string(), &[]),
+            Some(eid.clone())
+        );
+        assert_eq!(
+            fetch_entity_attribute_value(&db, &eid, &attribute_id).unwrap(),
+            None
+        );
+    }
 
-fn fetch_pragma_entity_base_defs(conn: &Connection) -> RecordSet {
-    let x: &[(&str, &dyn ToSql)] = &[];
-    let rs: RecordSet = query(
-        conn,
-        r#"PRAGMA "main".TABLE_INFO("entity_base_definitions");"#,
-        &x,
-    );
+    #[test]
+    fn reverting_a_value_that_moved_on_needs_a_pick() {
+        let db: Connection = app_db();
+        let (eid, attribute_id): (String, String) = (String::from("4"), String::from("3"));
+        write_entity_attribute(&db, &eid, &attribute_id, Some(String::from("5"))).unwrap();
+        let audit_id: String = rows_or_report(fetch_entity_history(&db, &eid))[0]
+            .id
+            .to_string();
+        let layer_id: String = {
+            update_entity_comp_layers(
+                &db,
+                CLPackage {
+                    cb_state: true,
+                    comp_id: String::from("3"),
+                    entity_id: eid.clone(),
+                },
+            )
+            .unwrap();
+            rows_or_report(fetch_entity_history(&db, &eid))[0]
+                .id
+                .to_string()
+        };
+        assert!(find_revert_conflict(&db, &layer_id, &[]).unwrap().is_none());
 
-    rs
-}
+        // someone else changes the value after the entry was written
+        db.execute(
+            "UPDATE entity_attributes_definitions SET attribute_value = 9 WHERE entity_base_id = 4 AND entity_attribute_id = 3;",
+            (),
+        )
+        .unwrap();
+        let conflict: RevertConflict = find_revert_conflict(&db, &audit_id, &[])
+            .unwrap()
+            .expect("a conflict");
+        assert_eq!(conflict.field, "attribute");
+        assert_eq!(conflict.mine, (String::from("attribute 3: nothing"), None));
+        assert_eq!(conflict.theirs.0, "attribute 3: '9'");
+
+        // keeping theirs settles it without writing anything
+        let resolved: Vec<(String, Option<String>)> =
+            vec![(audit_id.clone(), conflict.theirs.1.clone())];
+        assert!(find_revert_conflict(&db, &audit_id, &resolved)
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            revert_audit_entry(&db, &audit_id, &resolved),
+            Some(eid.clone())
+        );
+        assert_eq!(
+            fetch_entity_attribute_value(&db, &eid, &attribute_id).unwrap(),
+            conflict.theirs.1
+        );
+    }
 
-fn fetch_entity_information(conn: &Connection, eid: &String) -> RecordSet {
-    let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": eid };
-    let rs: RecordSet = query(conn, "SELECT 'e'.'entity_base_id', 'e'.'name', 'e'.'entity_core_type_id', 'e'.'entity_sub_type_id' FROM 'entity_base_definitions' as 'e' WHERE 'e'.'entity_base_id' = :eid;", &x);
+    #[test]
+    fn bulk_attribute_changes_land_on_all_entities_or_none() {
+        let db: Connection = app_db();
+        let attribute_id: String = String::from("3");
+        let package = |value: &str| BAPackage {
+            attribute_id: attribute_id.clone(),
+            value: Some(String::from(value)),
+            entity_ids: vec![String::from("3"), String::from("4")],
+            row_versions: Vec::new(),
+        };
 
-    rs
-}
+        bulk_update_attribute(&db, &package("2")).unwrap();
+        assert_eq!(
+            summarize_bulk_attribute(&db, &package("2").entity_ids, &attribute_id),
+            "all 2"
+        );
 
-fn fetch_all_entity_base_data(conn: &Connection, ect: String) -> RecordSet {
-    let x: &[(&str, &dyn ToSql)] = named_params! { ":ect": ect };
-    let rs = query(
-        conn,
-        "SELECT 'e'.'entity_base_id', 'e'.'name' FROM 'entity_base_definitions' as 'e' WHERE 'e'.'entity_core_type_id' IN (SELECT 'e'.'entity_core_type_id' FROM 'entity_core_types' as 'e' WHERE 'e'.'name' = :ect);",
-        &x,
-    );
+        // the goblin refuses the write, so the human keeps its value too
+        db.execute_batch(
+            "CREATE TRIGGER refuse_goblin BEFORE UPDATE ON entity_attributes_definitions
+             WHEN NEW.entity_base_id = 4 BEGIN SELECT RAISE(ABORT, 'refused'); END;",
+        )
+        .unwrap();
+        assert!(bulk_update_attribute(&db, &package("9")).is_err());
+        assert_eq!(
+            summarize_bulk_attribute(&db, &package("9").entity_ids, &attribute_id),
+            "all 2"
+        );
+        assert!(db.is_autocommit());
+    }
 
-    rs
-}
+    #[test]
+    fn rows_of_an_entity_move_its_version() {
+        let db: Connection = app_db();
+        let eid: String = String::from("4");
+        let seen: Option<i64> = fetch_row_version(&db, "entity_base_definitions", &eid).unwrap();
 
-fn fetch_specific_entity_base_data(v: String, conn: &Connection, ect: String) -> RecordSet {
-    let x: &[(&str, &dyn ToSql)] = named_params! { ":ead": v, ":ect": ect };
-    let rs = query(
-        conn,
-        "SELECT 'e'.'entity_base_id', 'e'.'name' FROM 'entity_base_definitions' as 'e' WHERE 'e'.'entity_base_id' = :ead AND 'e'.'entity_core_type_id' IN (SELECT 'e'.'entity_core_type_id' FROM 'entity_core_types' as 'e' WHERE 'e'.'name' = :ect)",
-        &x,
-    );
+        // someone else enables a layer on the goblin
+        db.execute(
+            "INSERT INTO entity_composition_layers_definitions (entity_composition_layer_id, entity_base_id) VALUES (3, 4);",
+            (),
+        )
+        .unwrap();
+        assert!(check_entity_version(&db, &eid, seen).is_err());
+
+        let package: BAPackage = BAPackage {
+            attribute_id: String::from("3"),
+            value: Some(String::from("5")),
+            entity_ids: vec![eid.clone()],
+            row_versions: vec![seen],
+        };
+        assert!(matches!(
+            bulk_update_attribute(&db, &package),
+            Err(Error::StatementChangedRows(0))
+        ));
+        assert_eq!(
+            fetch_entity_attribute_value(&db, &eid, &String::from("3")).unwrap(),
+            None
+        );
 
-    rs
-}
+        let seen: Option<i64> = fetch_row_version(&db, "entity_base_definitions", &eid).unwrap();
+        assert!(check_entity_version(&db, &eid, seen).is_ok());
+        // deleting checks the version before its own child deletes move it on
+        assert_eq!(delete_entity(&db, &eid, seen), None);
+    }
 
-fn fetch_entity_comp_layers_general(conn: &Connection) -> RecordSet {
-    let x: &[(&str, &dyn ToSql)] = &[];
-    let rs = query(
-        conn,
-        "SELECT entity_composition_layer_id, name FROM entity_composition_layers;",
-        &x,
-    );
+    #[test]
+    fn callables_and_layer_order_check_their_version() {
+        let db: Connection = app_db();
+        let (aid, cid): (String, String) = (String::from("1"), String::from("3"));
 
-    rs
-}
+        let path: String = String::from("res://actions/attack.gd");
+        assert_eq!(
+            update_callable_location(&db, &aid, &path, None).unwrap(),
+            None
+        );
+        let seen: Option<i64> = fetch_row_version(&db, "entity_actions_callables", &aid).unwrap();
+        db.execute(
+            "UPDATE entity_actions_callables SET callable_location = 'res://theirs.gd' WHERE entity_action_id = 1;",
+            (),
+        )
+        .unwrap();
+        assert_eq!(
+            update_callable_location(&db, &aid, &String::new(), seen).unwrap(),
+            Some(seen.unwrap() + 1)
+        );
+        assert_eq!(
+            fetch_callable_location(&db, &aid).unwrap().as_deref(),
+            Some("res://theirs.gd")
+        );
 
-fn fetch_entity_comp_layers_defs(conn: &Connection, eid: &String) -> RecordSet {
-    let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": eid };
-    let rs = query(
-        conn,
-        "SELECT 'e'.'entity_composition_layer_id' FROM 'entity_composition_layers_definitions' as 'e' WHERE 'e'.'entity_base_id' = :eid;",
-        &x,
-    );
+        let seen: Option<i64> = fetch_row_version(&db, "entity_composition_layers", &cid).unwrap();
+        assert!(move_comp_layer(&db, &cid, -1, seen.map(|v| v + 1)).is_some());
+        assert!(move_comp_layer(&db, &cid, -1, seen).is_none());
+    }
 
-    rs
-}
+    #[test]
+    fn comp_layers_toggle_once() {
+        let db: Connection = app_db();
+        let package = |cb_state: bool| CLPackage {
+            cb_state,
+            comp_id: String::from("3"),
+            entity_id: String::from("4"),
+        };
 
-fn fill_tree_with_entity_data(rs: RecordSet, mut ti: TreeItem, c: &AppContext) -> Option<()> {
-    let mut t: Tree = ti.tree()?;
-    clear_entities_from_tree(&mut ti)?;
-    t.begin();
-
-    // for each matching entity found by the search
-    for row in rs.records {
-        // ( entity_id, entity_name )
-        let fields: (String, String) = (row.fields[0].to_string(), row.fields[1].to_string());
-
-        let mut new_ti_path: String = String::new();
-        let ti_path: String = t.item_pathname(&ti).unwrap();
-        new_ti_path.push_str(&ti_path[..]);
-        new_ti_path.push('/');
-
-        let new_ti_label: String = String::from_iter([&fields.0[..], ":", &fields.1[..]]);
-        let mut new_tree_item: TreeItem = TreeItem::new(&t, &new_ti_label[..]);
-
-        new_tree_item.draw_item_content(|tree_item, render| {
-            let dims: (i32, i32, i32, i32) = (
-                tree_item.label_x(),
-                tree_item.label_y(),
-                tree_item.label_w(),
-                tree_item.label_h(),
-            );
+        update_entity_comp_layers(&db, package(true)).unwrap();
+        update_entity_comp_layers(&db, package(true)).unwrap();
+        assert_eq!(enabled_layers(&db, "4"), vec!["3"]);
+
+        update_entity_comp_layers(&db, package(false)).unwrap();
+        update_entity_comp_layers(&db, package(false)).unwrap();
+        assert!(enabled_layers(&db, "4").is_empty());
+
+        // repeated clicks that change nothing leave no trace
+        assert_eq!(
+            audit_log(&db),
+            vec![
+                "entity_composition_layers_definitions|4:3|entity_composition_layer_id||3",
+                "entity_composition_layers_definitions|4:3|entity_composition_layer_id|3|",
+            ]
+        );
+    }
 
-            if render {
-                if let Some(frame) = tree_item.try_widget() {
-                    let mut frame: Frame = unsafe { frame.into_widget::<Frame>() };
-                    frame.set_pos(dims.0, dims.1);
-                    frame.set_size(dims.2, dims.3);
-                }
-            }
+    #[test]
+    fn every_layer_write_keeps_the_layer_rules() {
+        let db: Connection = app_db();
+        let eid: String = String::from("3");
+        let bulk = |cb_state: bool, comp_id: &str, eid: &str| BCLPackage {
+            cb_state,
+            comp_id: String::from(comp_id),
+            entity_ids: vec![String::from(eid)],
+            row_versions: vec![None],
+        };
 
-            let (label_w, _): (i32, i32) = draw::measure(&tree_item.label().unwrap()[..], render);
-            return dims.0 + label_w;
-        });
+        // EntityActionRequirement still needs EntityActions
+        let refused: Error = bulk_update_comp_layers(&db, &bulk(false, "1", "3")).unwrap_err();
+        assert!(refused.to_string().contains("is required by"));
+        assert_eq!(enabled_layers(&db, "3"), vec!["1", "2", "4"]);
+        bulk_update_comp_layers(&db, &bulk(true, "4", "4")).unwrap();
+        assert_eq!(enabled_layers(&db, "4"), vec!["1", "4"]);
+
+        // an import dropping both takes the dependent one off first
+        let mut export: EXPackage = fetch_entity_export(&db, &eid).unwrap().unwrap();
+        export.comp_layers = vec![String::from("2")];
+        write_entity_export(&db, &export).unwrap();
+        assert_eq!(enabled_layers(&db, "3"), vec!["2"]);
+
+        // the template's EntityAttributes now clashes with the entity's EntityIdentity
+        db.execute(
+            "INSERT INTO entity_composition_layers_rules (entity_composition_layer_id, relation, related_layer_id) VALUES (3, 'conflicts', 2);",
+            (),
+        )
+        .unwrap();
+        update_entity_comp_layers(
+            &db,
+            CLPackage {
+                cb_state: true,
+                comp_id: String::from("3"),
+                entity_id: String::from("4"),
+            },
+        )
+        .unwrap();
+        assert!(apply_template(&db, &String::from("4"), &String::from("1")).is_err());
+        assert_eq!(enabled_layers(&db, "4"), vec!["1", "3", "4"]);
+    }
 
-        if let Some(mut ti) = t.add_item(&new_ti_path[..], &new_tree_item) {
-            let mut f: Frame = Frame::new(
-                ti.label_x(),
-                ti.label_y(),
-                ti.label_w(),
-                ti.label_h(),
-                &ti.label().unwrap()[..],
-            );
+    #[test]
+    fn db_jobs_answer_with_their_message() {
+        let db: Connection = app_db();
+
+        let eids: Vec<String> = vec![String::from("3"), String::from("4")];
+        match run_db_job(&db, DbJob::CompareEntities(eids.clone())) {
+            Message::CompareRowsReady(compared, rows) => {
+                assert_eq!(compared, eids);
+                assert!(rows
+                    .iter()
+                    .any(|(field, values)| field == "name" && values == &vec!["human", "goblin"]));
+            }
+            _ => panic!("compare job answered with the wrong message"),
+        }
 
-            ti.set_widget(&f);
+        match run_db_job(&db, DbJob::Validate) {
+            Message::ValidationDone(problems) => assert!(problems.is_empty()),
+            _ => panic!("validate job answered with the wrong message"),
+        }
 
-            let sender: Sender<_> = c.sender.clone();
+        let mut path: PathBuf = env::temp_dir();
+        path.push(format!(
+            "entity_creator_export_{}.entities",
+            std::process::id()
+        ));
+        match run_db_job(&db, DbJob::Export(Vec::new(), path.clone())) {
+            Message::ExportDone(written, count) => {
+                assert_eq!(written, path);
+                assert_eq!(count, Repository::new(&db).entities().unwrap().len());
+            }
+            _ => panic!("export job answered with the wrong message"),
+        }
 
-            f.handle(move |f_self, event| match event {
-                Event::Released => {
-                    sender.send(Message::EntityFrameClicked(slice_beginning_of_string(
-                        f_self.label(),
-                        ":",
-                    )));
-                    true
-                }
-                _ => false,
-            });
-        };
+        // a failed job still answers, so the busy indicator can count it as done
+        match run_db_job(
+            &db,
+            DbJob::Export(eids, path.join("inside_a_file.entities")),
+        ) {
+            Message::DbJobFailed(reason) => {
+                assert!(reason.starts_with("The export could not be written"))
+            }
+            _ => panic!("failed export answered with the wrong message"),
+        }
+        let _ = fs::remove_file(&path);
     }
 
-    Some(t.end())
-}
+    #[test]
+    fn only_other_connections_move_data_version() {
+        let mut path: PathBuf = env::temp_dir();
+        path.push(format!("entity_creator_watch_{}.db", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let editor: Connection = Connection::open(&path).unwrap();
+        editor
+            .execute_batch(entity_creator::fixtures::SCHEMA)
+            .unwrap();
+        editor
+            .execute_batch(entity_creator::fixtures::FIXTURES)
+            .unwrap();
+        ensure_app_schema(&editor).unwrap();
+        let seen: i64 = fetch_data_version(&editor).unwrap();
+
+        editor
+            .execute(
+                "UPDATE entity_base_definitions SET name = 'mine' WHERE entity_base_id = 3;",
+                (),
+            )
+            .unwrap();
+        assert_eq!(fetch_data_version(&editor).unwrap(), seen);
+
+        let before: Vec<(String, Vec<String>)> =
+            build_compare_rows(&editor, &[String::from("3")]).unwrap();
+        let other: Connection = Connection::open(&path).unwrap();
+        other
+            .execute(
+                "UPDATE entity_base_definitions SET name = 'theirs' WHERE entity_base_id = 3;",
+                (),
+            )
+            .unwrap();
+        other
+            .execute("DELETE FROM entity_composition_layers_definitions WHERE entity_base_id = 3 AND entity_composition_layer_id = 1;", ())
+            .unwrap();
+        assert_ne!(fetch_data_version(&editor).unwrap(), seen);
+
+        let after: Vec<(String, Vec<String>)> =
+            build_compare_rows(&editor, &[String::from("3")]).unwrap();
+        assert_eq!(
+            changed_compare_rows(&before, &after),
+            vec![String::from("name"), String::from("EntityActions")]
+        );
+        assert!(changed_compare_rows(&after, &after).is_empty());
 
-fn clear_entities_from_tree(ti: &mut TreeItem) -> Option<()> {
-    let mut t: Tree = ti.tree()?;
-    ti.clear_children();
+        drop(other);
+        drop(editor);
+        let _ = fs::remove_file(&path);
+    }
 
-    Some(t.redraw())
-}
+    #[test]
+    fn snapshots_in_the_same_second_keep_their_own_file() {
+        let mut dir: PathBuf = env::temp_dir();
+        dir.push(format!("entity_creator_snapshots_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path: PathBuf = dir.join("cold_storage.db");
+        let db: Connection = Connection::open(&path).unwrap();
+        db.execute_batch(entity_creator::fixtures::SCHEMA).unwrap();
+
+        let taken: Vec<PathBuf> = (0..3)
+            .filter_map(|_| snapshot_cold_storage(&db, &path))
+            .collect();
+        assert_eq!(taken.len(), 3);
+        assert!(taken[0] != taken[1] && taken[1] != taken[2]);
+
+        // newest first, and every stamp finds its way back to its file
+        let listed: Vec<(String, u64, PathBuf)> = list_backups(&path);
+        assert_eq!(listed.len(), 3);
+        for (i, (stamp, _, snapshot)) in listed.iter().enumerate() {
+            assert_eq!(snapshot, &taken[2 - i]);
+            assert!(parse_snapshot_stamp(stamp).is_some());
+        }
 
-fn menu_regen(_: &mut MenuBar) -> () {
-    regen_enums();
-}
+        drop(db);
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-fn menu_settings(_: &mut MenuBar) -> () {
-    ()
-}
+    #[test]
+    fn search_filters_by_core_type_and_id() {
+        let db: Connection = app_db();
 
-fn new_entity(db: &Connection, eid: &String) -> () {
-    match db.execute(
-        "INSERT INTO entity_base_definitions (
-        	entity_base_id
-        ) VALUES ( 
-        	?1
-        ) ON CONFLICT (entity_base_id) DO NOTHING;",
-        (eid,),
-    ) {
-        Ok(i) => println!("success, rows effected: {}", i),
-        Err(e) => println!("error with code {:?}", e),
-    };
-}
+        let names = |entities: Vec<EntityBase>| -> Vec<String> {
+            entities.into_iter().map(|e| e.name).collect()
+        };
 
-fn update_entity(db: &Connection) -> Option<()> {
-    let eid: String = widget_from_id::<Input>("entity_base_id")?.value();
-    let package: NPackage = fetch_header_data_from_gui(&eid)?;
+        assert_eq!(
+            names(fetch_all_entity_base_data(&db, String::from("MOB"), 0).unwrap()),
+            vec!["human", "goblin"]
+        );
+        assert_eq!(
+            names(
+                fetch_specific_entity_base_data(String::from("4"), &db, String::from("MOB"))
+                    .unwrap()
+            ),
+            vec!["goblin"]
+        );
+        assert!(
+            fetch_specific_entity_base_data(String::from("1"), &db, String::from("MOB"))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(fetch_all_entity_base_data(&db, String::from("NOTHING"), 0)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            fetch_entity_category_names(&db),
+            vec![String::from("ITEM"), String::from("MOB")]
+        );
+    }
 
-    match db.execute(
-        "UPDATE entity_base_definitions SET 
-        	name = ?2, 
-        	entity_core_type_id = ?3,
-        	entity_sub_type_id = ?4
-        WHERE entity_base_id = ?1;",
-        (&package.eid, &package.name, &package.ecid, &package.esid),
-    ) {
-        Ok(i) => println!("success, rows effected: {}", i),
-        Err(e) => println!("error with code {:?}", e),
-    };
+    #[test]
+    fn search_pages_include_one_row_past_the_page() {
+        let db: Connection = app_db();
+        for n in 0..ENTITY_PAGE_SIZE {
+            db.execute(
+                "INSERT INTO entity_base_definitions (name, entity_core_type_id, entity_sub_type_id) VALUES (?1, 1, 1);",
+                [format!("item {}", n)],
+            )
+            .unwrap();
+        }
 
-    Some(())
-}
+        // 2 fixture items plus the inserted ones, ordered by id
+        let first: Vec<EntityBase> =
+            fetch_all_entity_base_data(&db, String::from("ITEM"), 0).unwrap();
+        assert_eq!(first.len(), ENTITY_PAGE_SIZE + 1);
+        assert_eq!(first[0].name, "cloth helmet");
 
-fn regen_enums() -> Option<()> {
-    let mut enum_package: String = String::new();
-    let db_path = locate_cold_storage()?;
+        let second: Vec<EntityBase> =
+            fetch_all_entity_base_data(&db, String::from("ITEM"), ENTITY_PAGE_SIZE).unwrap();
+        let second: Vec<String> = second.into_iter().map(|e| e.name).collect();
+        assert_eq!(second, vec!["item 198", "item 199"]);
+    }
 
-    let db: Connection = match Connection::open(db_path) {
-        Ok(c) => {
-            println!("Successfully opened connection");
-            c
-        }
-        Err(_) => {
-            println!("failed to open connection");
-            return None;
-        }
-    };
+    #[test]
+    fn action_costs_compare_requirements_with_the_entity() {
+        let db: Connection = app_db();
+        let eid: String = String::from("3");
+        let summary = |db: &Connection| -> Vec<(Option<bool>, String)> {
+            summarize_action_costs(&fetch_entity_action_costs(db, &eid).unwrap())
+        };
 
-    let rs_enum_tables: RecordSet = fetch_enum_tables(&db);
-    for mut enum_rows in rs_enum_tables.records {
-        match enum_rows.fields.pop() {
-            Some(f) => {
-                // "entity_actions" -> Vec["entity","actions"]
-                let enum_lowercase: Vec<String> = f
-                    .to_string()
-                    .split("_")
-                    .map(|s| s.to_string())
-                    .collect::<Vec<String>>();
-                let mut enum_capitalized: Vec<String> = Vec::new();
-
-                // "entity" -> "Entity"
-                for x in &enum_lowercase {
-                    let first: String = x[0..1].to_uppercase();
-                    let rest: String = String::from(&x[1..]);
-                    enum_capitalized.push(String::from(Vec::from([first, rest]).join("")));
-                }
-                let rs_enum_data: RecordSet = fetch_enum_values_from_table(&db, f.to_string());
-                // begin adding new enum type
-                enum_package.push_str("\nenum ");
-                enum_package.push_str(&String::from(enum_capitalized.join(""))[..]);
-                enum_package.push_str("{\n");
-
-                for row in rs_enum_data.records {
-                    let mut fields: Vec<SqlData> = row.fields;
-                    let type_name: String = fields.pop().unwrap().to_string();
-                    let type_value: String = fields.pop().unwrap().to_string();
-                    enum_package.push_str("\t");
-                    enum_package.push_str(&type_name[..]);
-                    enum_package.push_str(" = ");
-                    enum_package.push_str(&type_value[..]);
-                    enum_package.push_str(",\n");
-                }
-                enum_package.push_str("}\n");
-            }
-            None => (),
-        }
-    }
-    let _ = write_enums_to_file(enum_package);
+        // the fixture human has no STAMINA, so neither cost can be checked
+        assert_eq!(
+            summary(&db),
+            vec![
+                (None, String::from("WALK: STAMINA 1 (no value)")),
+                (None, String::from("BASH: STAMINA 4 (no value)")),
+            ]
+        );
 
-    Some(())
-}
+        db.execute(
+            "INSERT INTO entity_attributes_definitions (entity_base_id, entity_attribute_id, attribute_value) VALUES (3, 3, 2);",
+            (),
+        )
+        .unwrap();
+        assert_eq!(
+            summary(&db),
+            vec![
+                (Some(true), String::from("WALK: STAMINA 1 / 2")),
+                (Some(false), String::from("BASH: STAMINA 4 / 2")),
+            ]
+        );
+    }
 
-fn fetch_enum_tables(db: &Connection) -> RecordSet {
-    let sql: String = String::from("SELECT 'e'.'table' FROM 'enums' as 'e';");
-    query(&db, &sql[..], &[])
-}
+    #[test]
+    fn schema_steps_drop_duplicate_layers_and_run_once() {
+        let db: Connection = fixture_db().unwrap();
+        db.execute(
+            "INSERT INTO entity_composition_layers_definitions (entity_base_id, entity_composition_layer_id) VALUES (3, 2);",
+            (),
+        )
+        .unwrap();
 
-fn fetch_enum_values_from_table(db: &Connection, t: String) -> RecordSet {
-    let sql: String = Vec::from(&[
-        "SELECT 'x'.'_rowid_', 'x'.'name' FROM '",
-        &t[..],
-        "' as 'x' ORDER BY 'x'.'_rowid_' ASC;",
-    ])
-    .join("");
-    //println!("sql is: {}", &sql[..]);
-    query(&db, &sql[..], &[])
-}
+        ensure_app_schema(&db).unwrap();
+        assert_eq!(enabled_layers(&db, "3"), vec!["1", "2", "4"]);
+        assert_eq!(
+            column(&db, "PRAGMA user_version;"),
+            vec![SCHEMA_STEPS.len().to_string()]
+        );
 
-#[allow(dead_code)]
-fn print_recordset_debug(r: RecordSet) -> () {
-    for x in r.headers.column_names {
-        println!("header name: {}", x);
+        // a database set up before the steps were counted goes through all of them again
+        let rules: Vec<String> = column(&db, "SELECT * FROM entity_composition_layers_rules;");
+        db.pragma_update(None, "user_version", 0).unwrap();
+        ensure_app_schema(&db).unwrap();
+        assert_eq!(
+            column(&db, "SELECT * FROM entity_composition_layers_rules;"),
+            rules
+        );
     }
 
-    for x in r.records {
-        let x_displayed: Vec<String> = x
-            .fields
-            .into_iter()
-            .map(|e| e.to_string())
-            .collect::<Vec<String>>();
-        println!(" rec: {:?}", x_displayed);
+    #[test]
+    fn requirements_are_found_by_their_key_after_a_vacuum() {
+        let db: Connection = app_db();
+        delete_action_requirement(&db, &String::from("1"), None).unwrap();
+        let added: RQPackage = RQPackage {
+            requirement_id: None,
+            action_id: String::from("1"),
+            attribute_id: String::from("3"),
+            cost: String::from("2"),
+            row_version: None,
+        };
+        assert_eq!(save_action_requirement(&db, &added).unwrap(), None);
+
+        // VACUUM may renumber the rowids of a table without its own key, the keys stay
+        db.execute_batch("VACUUM;").unwrap();
+        assert_eq!(
+            column(
+                &db,
+                "SELECT entity_action_requirement_id FROM entity_actions_requirements_definitions ORDER BY 1;"
+            ),
+            vec!["2", "3"]
+        );
+        assert_eq!(
+            fetch_action_requirement(&db, &String::from("2"))
+                .unwrap()
+                .cost,
+            "4"
+        );
+        assert_eq!(
+            fetch_action_requirement(&db, &String::from("3"))
+                .unwrap()
+                .cost,
+            "2"
+        );
+        assert_eq!(
+            audit_log(&db)[1],
+            "entity_actions_requirements_definitions|3|||action 1, attribute 3, cost 2"
+        );
     }
-}
-
-fn write_enums_to_file(s: String) -> std::io::Result<()> {
-    fs::write("enums.gd", s.as_bytes())?;
-    Ok(())
-}
 
-fn close_treeitem(t: &mut Tree, pn: String) -> () {
-    let _ = t.close(&pn[..], false);
-}
+    #[test]
+    fn deleting_a_removed_identity_name_is_not_audited() {
+        let db: Connection = app_db();
+        db.execute(
+            "INSERT INTO entity_identities (entity_identity_type_id, name) VALUES (1, 'orcish');",
+            (),
+        )
+        .unwrap();
+        add_identity_names(&db, &String::from("1"), &[String::from("Grol")]).unwrap();
+        let indexes: Vec<String> = column(
+            &db,
+            "SELECT \"index\" FROM entity_identities_definitions WHERE name = 'Grol';",
+        );
 
-fn get_pathname(t: &Tree, ti: &TreeItem) -> Option<String> {
-    match t.item_pathname(&ti) {
-        Ok(pn) => Some(pn),
-        Err(_) => None,
+        delete_identity_names(&db, &indexes).unwrap();
+        delete_identity_names(&db, &indexes).unwrap();
+        assert_eq!(
+            audit_log(&db),
+            vec![
+                format!("entity_identities_definitions|{}|name||Grol", indexes[0]),
+                format!("entity_identities_definitions|{}|name|Grol|", indexes[0]),
+            ]
+        );
     }
-}
 
-fn update_entity_comp_layers(db: &Connection, package: CLPackage) -> () {
-    match package.cb_state {
-        true => {
-            // enable the composition layer
-            // INSERT .. ON CONFLICT DO NOTHING
-            match db.execute(
-                "INSERT INTO entity_composition_layers_definitions (
-                    entity_composition_layer_id, entity_base_id
-                ) values (
-                    ?1, ?2
-                ) ON CONFLICT(entity_base_id,entity_composition_layer_id) DO NOTHING;",
-                (&package.comp_id, &package.entity_id),
-            ) {
-                Ok(i) => println!("success, rows effected: {}", i),
-                Err(e) => println!("error with code {:?}", e),
-            }
-        }
-        false => {
-            // disable the composition layer
-            // DELETE ..
-            match db.execute(
-                "DELETE FROM entity_composition_layers_definitions WHERE entity_base_id = ?2 AND entity_composition_layer_id = ?1;",
-                (&package.comp_id, &package.entity_id),
-            ) {
-                Ok(i) => println!("success, rows effected: {}", i),
-                Err(e) => println!("error with code {:?}", e),
-            }
-        }
+    #[test]
+    fn state_names_title_case_by_character() {
+        assert_eq!(title_case("FIRE_BOLT"), "Fire Bolt");
+        assert_eq!(title_case("ÉTOURDI__ÉCLAIR"), "Étourdi Éclair");
+        assert_eq!(title_case("_"), "");
     }
-
-    ()
-}
-
-fn fetch_header_data_from_gui(eid: &String) -> Option<NPackage> {
-    Some(NPackage {
-        eid: eid.clone(),
-        name: widget_from_id::<Input>("name")?.value(),
-        ecid: widget_from_id::<Input>("entity_core_type_id")?.value(),
-        esid: widget_from_id::<Input>("entity_sub_type_id")?.value(),
-    })
 }
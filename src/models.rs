@@ -0,0 +1,514 @@
+//! Typed rows for the core cold storage tables, decoded by column name so a query only has
+//! to select the columns a model needs, in any order.
+
+use crate::db::{FromRecord, Row, RowError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityBase {
+    pub id: i64,
+    pub name: String,
+    pub core_type_id: i64,
+    pub sub_type_id: i64,
+}
+
+impl FromRecord for EntityBase {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            id: row.i64("entity_base_id")?,
+            name: row.text("name")?,
+            core_type_id: row.i64("entity_core_type_id")?,
+            sub_type_id: row.i64("entity_sub_type_id")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompositionLayer {
+    pub id: i64,
+    pub name: String,
+    // res:// path of the layer's script
+    pub resource: Option<String>,
+}
+
+impl FromRecord for CompositionLayer {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            id: row.i64("entity_composition_layer_id")?,
+            name: row.text("name")?,
+            resource: row.opt_text("resource")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityAction {
+    pub id: i64,
+    pub name: Option<String>,
+    pub action_type_id: Option<i64>,
+}
+
+impl FromRecord for EntityAction {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            id: row.i64("entity_action_id")?,
+            name: row.opt_text("name")?,
+            action_type_id: row.opt_i64("action_type_id")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityAttribute {
+    pub id: i64,
+    pub name: String,
+}
+
+impl FromRecord for EntityAttribute {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            id: row.i64("entity_attribute_id")?,
+            name: row.text("name")?,
+        })
+    }
+}
+
+// an attribute as set on one entity
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeValue {
+    pub attribute_id: i64,
+    pub name: String,
+    pub value: f64,
+}
+
+impl FromRecord for AttributeValue {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            attribute_id: row.i64("entity_attribute_id")?,
+            name: row.text("name")?,
+            value: row.f64("attribute_value")?,
+        })
+    }
+}
+
+// what an action costs, taken from one of the entity's attributes
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionRequirement {
+    pub action_id: i64,
+    pub attribute_id: i64,
+    pub cost: i64,
+}
+
+impl FromRecord for ActionRequirement {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            action_id: row.i64("entity_action_id")?,
+            attribute_id: row.i64("entity_attributes")?,
+            cost: row.i64("entity_action_cost")?,
+        })
+    }
+}
+
+// one row of the editor's audit log, before and after are NULL when there was no value
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub timestamp: String,
+    pub os_user: String,
+    pub table_name: String,
+    pub row_key: String,
+    pub column_name: Option<String>,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub entity_id: Option<String>,
+}
+
+impl FromRecord for AuditEntry {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            id: row.i64("entity_audit_log_id")?,
+            timestamp: row.text("timestamp")?,
+            os_user: row.text("os_user")?,
+            table_name: row.text("table_name")?,
+            row_key: row.text("row_key")?,
+            column_name: row.opt_text("column_name")?,
+            before: row.opt_text("before_value")?,
+            after: row.opt_text("after_value")?,
+            entity_id: row.opt_text("entity_base_id")?,
+        })
+    }
+}
+
+// one column of a table as PRAGMA table_info reports it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableColumn {
+    pub index: i64,
+    pub name: String,
+}
+
+impl FromRecord for TableColumn {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            index: row.i64("cid")?,
+            name: row.text("name")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityCoreType {
+    pub id: i64,
+    pub name: String,
+}
+
+impl FromRecord for EntityCoreType {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            id: row.i64("entity_core_type_id")?,
+            name: row.text("name")?,
+        })
+    }
+}
+
+// a template along with the names of the types it creates, None when a type was removed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    pub id: i64,
+    pub name: String,
+    pub core_type_id: i64,
+    pub sub_type_id: i64,
+    pub core_type: Option<String>,
+    pub sub_type: Option<String>,
+}
+
+impl FromRecord for Template {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            id: row.i64("entity_template_id")?,
+            name: row.text("name")?,
+            core_type_id: row.i64("entity_core_type_id")?,
+            sub_type_id: row.i64("entity_sub_type_id")?,
+            core_type: row.opt_text("core_type_name")?,
+            sub_type: row.opt_text("sub_type_name")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Avatar {
+    pub id: i64,
+    pub name: String,
+}
+
+impl FromRecord for Avatar {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            id: row.i64("entity_avatar_id")?,
+            name: row.text("name")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityState {
+    pub id: i64,
+    pub name: String,
+}
+
+impl FromRecord for EntityState {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            id: row.i64("entity_state_id")?,
+            name: row.text("name")?,
+        })
+    }
+}
+
+// how a state moves one attribute, and for how long
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateModifier {
+    pub index: i64,
+    pub state_id: i64,
+    pub attribute_id: i64,
+    pub attribute_name: Option<String>,
+    pub magnitude: i64,
+    pub duration: i64,
+    pub row_version: i64,
+}
+
+impl FromRecord for StateModifier {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            index: row.i64("index")?,
+            state_id: row.i64("entity_state_id")?,
+            attribute_id: row.i64("entity_attribute_id")?,
+            attribute_name: row.opt_text("attribute_name")?,
+            magnitude: row.i64("magnitude")?,
+            duration: row.i64("duration")?,
+            row_version: row.i64("row_version")?,
+        })
+    }
+}
+
+// an identity type and how many names it holds
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdentityType {
+    pub id: i64,
+    pub name: String,
+    pub name_count: i64,
+}
+
+impl FromRecord for IdentityType {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            id: row.i64("entity_identity_type_id")?,
+            name: row.text("name")?,
+            name_count: row.i64("name_count")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdentityName {
+    pub index: i64,
+    pub name: String,
+}
+
+impl FromRecord for IdentityName {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            index: row.i64("index")?,
+            name: row.text("name")?,
+        })
+    }
+}
+
+// an action with the name of its type
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionSummary {
+    pub id: i64,
+    pub name: Option<String>,
+    pub action_type: Option<String>,
+}
+
+impl FromRecord for ActionSummary {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            id: row.i64("entity_action_id")?,
+            name: row.opt_text("name")?,
+            action_type: row.opt_text("action_type_name")?,
+        })
+    }
+}
+
+// a requirement row as stored, every column but its key may be NULL
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequirementDefinition {
+    pub requirement_id: i64,
+    pub action_id: Option<i64>,
+    pub attribute_id: Option<i64>,
+    pub attribute_name: Option<String>,
+    pub cost: Option<i64>,
+    pub row_version: i64,
+}
+
+impl FromRecord for RequirementDefinition {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            requirement_id: row.i64("entity_action_requirement_id")?,
+            action_id: row.opt_i64("entity_action_id")?,
+            attribute_id: row.opt_i64("entity_attributes")?,
+            attribute_name: row.opt_text("attribute_name")?,
+            cost: row.opt_i64("entity_action_cost")?,
+            row_version: row.i64("row_version")?,
+        })
+    }
+}
+
+// one requirement of an entity's action next to the entity's own value of that attribute,
+// actions without requirements come back with everything but the action empty
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionCost {
+    pub action_id: i64,
+    pub action_name: Option<String>,
+    pub attribute_name: Option<String>,
+    pub cost: Option<i64>,
+    pub attribute_value: Option<f64>,
+}
+
+impl FromRecord for ActionCost {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            action_id: row.i64("entity_action_id")?,
+            action_name: row.opt_text("action_name")?,
+            attribute_name: row.opt_text("attribute_name")?,
+            cost: row.opt_i64("entity_action_cost")?,
+            attribute_value: row.opt_f64("attribute_value")?,
+        })
+    }
+}
+
+// a type an action can be linked to and how many times it is
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkOption {
+    pub type_id: i64,
+    pub name: String,
+    pub link_count: i64,
+}
+
+impl FromRecord for LinkOption {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            type_id: row.i64("type_id")?,
+            name: row.text("name")?,
+            link_count: row.i64("link_count")?,
+        })
+    }
+}
+
+// an action and the callable behind it, None for both when it has none yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallableLocation {
+    pub action_id: i64,
+    pub action_name: Option<String>,
+    pub location: Option<String>,
+    pub row_version: Option<i64>,
+}
+
+impl FromRecord for CallableLocation {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            action_id: row.i64("entity_action_id")?,
+            action_name: row.opt_text("name")?,
+            location: row.opt_text("callable_location")?,
+            row_version: row.opt_i64("row_version")?,
+        })
+    }
+}
+
+// a composition layer with the version it was read at, for screens that edit it
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerResource {
+    pub id: i64,
+    pub name: String,
+    pub resource: Option<String>,
+    pub row_version: i64,
+}
+
+impl FromRecord for LayerResource {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            id: row.i64("entity_composition_layer_id")?,
+            name: row.text("name")?,
+            resource: row.opt_text("resource")?,
+            row_version: row.i64("row_version")?,
+        })
+    }
+}
+
+// layer_id requires or conflicts with related_layer_id
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompositionLayerRule {
+    pub id: i64,
+    pub layer_id: i64,
+    pub layer_name: Option<String>,
+    pub relation: String,
+    pub related_layer_id: i64,
+    pub related_layer_name: Option<String>,
+}
+
+impl FromRecord for CompositionLayerRule {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            id: row.i64("entity_composition_layers_rule_id")?,
+            layer_id: row.i64("entity_composition_layer_id")?,
+            layer_name: row.opt_text("layer_name")?,
+            relation: row.text("relation")?,
+            related_layer_id: row.i64("related_layer_id")?,
+            related_layer_name: row.opt_text("related_layer_name")?,
+        })
+    }
+}
+
+// an entity breaking one composition layer rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleViolation {
+    pub entity_id: i64,
+    pub entity_name: String,
+    pub layer_name: Option<String>,
+    pub relation: String,
+    pub related_layer_name: Option<String>,
+}
+
+impl FromRecord for RuleViolation {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            entity_id: row.i64("entity_base_id")?,
+            entity_name: row.text("name")?,
+            layer_name: row.opt_text("layer_name")?,
+            relation: row.text("relation")?,
+            related_layer_name: row.opt_text("related_layer_name")?,
+        })
+    }
+}
+
+// a layer pulled in by one of an entity's actions
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionLayer {
+    pub layer_id: i64,
+    pub action_name: Option<String>,
+}
+
+impl FromRecord for ActionLayer {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            layer_id: row.i64("entity_composition_layer_id")?,
+            action_name: row.opt_text("name")?,
+        })
+    }
+}
+
+// how many entities, actions and templates reference one composition layer
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayerUsage {
+    pub entities: i64,
+    pub actions: i64,
+    pub templates: i64,
+}
+
+impl FromRecord for LayerUsage {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            entities: row.i64("entities")?,
+            actions: row.i64("actions")?,
+            templates: row.i64("templates")?,
+        })
+    }
+}
+
+// a single integer read on its own, selected `as 'value'`
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntValue {
+    pub value: i64,
+}
+
+impl FromRecord for IntValue {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            value: row.i64("value")?,
+        })
+    }
+}
+
+// a single value read on its own as text, selected `as 'value'`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextValue {
+    pub value: Option<String>,
+}
+
+impl FromRecord for TextValue {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            value: row.opt_text("value")?,
+        })
+    }
+}
@@ -0,0 +1,308 @@
+//! Access to entities and the tables hanging off them, returning typed models.
+//!
+//! Reads work on the game's schema as it is. Writes also need the editor's own tables from
+//! `schema::ensure_app_schema`, since every one of them leaves an audit entry behind.
+
+use crate::audit::{record_audit, AuditPackage};
+use crate::db::{query_as, query_one_as, read_failed, savepoint, RowError};
+use crate::models::{
+    ActionRequirement, AttributeValue, CompositionLayer, EntityAction, EntityAttribute, EntityBase,
+    IntValue,
+};
+use rusqlite::*;
+
+pub struct Repository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> Repository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { conn }
+    }
+
+    pub fn entities(&self) -> Result<Vec<EntityBase>, RowError> {
+        let x: &[(&str, &dyn ToSql)] = &[];
+        query_as(
+            self.conn,
+            "SELECT 'e'.'entity_base_id', 'e'.'name', 'e'.'entity_core_type_id', 'e'.'entity_sub_type_id' FROM 'entity_base_definitions' as 'e' ORDER BY 'e'.'entity_base_id' ASC;",
+            x,
+        )
+    }
+
+    pub fn entity(&self, entity_id: i64) -> Result<Option<EntityBase>, RowError> {
+        let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": entity_id };
+        let mut entities: Vec<EntityBase> = query_as(
+            self.conn,
+            "SELECT 'e'.'entity_base_id', 'e'.'name', 'e'.'entity_core_type_id', 'e'.'entity_sub_type_id' FROM 'entity_base_definitions' as 'e' WHERE 'e'.'entity_base_id' = :eid;",
+            x,
+        )?;
+
+        Ok(entities.pop())
+    }
+
+    pub fn composition_layers(&self) -> Result<Vec<CompositionLayer>, RowError> {
+        let x: &[(&str, &dyn ToSql)] = &[];
+        query_as(
+            self.conn,
+            "SELECT 'c'.'entity_composition_layer_id', 'c'.'name', 'c'.'resource' FROM 'entity_composition_layers' as 'c' ORDER BY 'c'.'entity_composition_layer_id' ASC;",
+            x,
+        )
+    }
+
+    pub fn entity_composition_layers(
+        &self,
+        entity_id: i64,
+    ) -> Result<Vec<CompositionLayer>, RowError> {
+        let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": entity_id };
+        query_as(
+            self.conn,
+            "SELECT 'c'.'entity_composition_layer_id', 'c'.'name', 'c'.'resource' FROM 'entity_composition_layers_definitions' as 'd' INNER JOIN 'entity_composition_layers' as 'c' ON 'd'.'entity_composition_layer_id' = 'c'.'entity_composition_layer_id' WHERE 'd'.'entity_base_id' = :eid ORDER BY 'c'.'entity_composition_layer_id' ASC;",
+            x,
+        )
+    }
+
+    pub fn actions(&self) -> Result<Vec<EntityAction>, RowError> {
+        let x: &[(&str, &dyn ToSql)] = &[];
+        query_as(
+            self.conn,
+            "SELECT 'a'.'entity_action_id', 'a'.'name', 'a'.'action_type_id' FROM 'entity_actions' as 'a' ORDER BY 'a'.'entity_action_id' ASC;",
+            x,
+        )
+    }
+
+    pub fn entity_actions(&self, entity_id: i64) -> Result<Vec<EntityAction>, RowError> {
+        let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": entity_id };
+        query_as(
+            self.conn,
+            "SELECT 'a'.'entity_action_id', 'a'.'name', 'a'.'action_type_id' FROM 'entity_actions_definitions' as 'd' INNER JOIN 'entity_actions' as 'a' ON 'd'.'entity_action_id' = 'a'.'entity_action_id' WHERE 'd'.'entity_base_id' = :eid ORDER BY 'a'.'entity_action_id' ASC;",
+            x,
+        )
+    }
+
+    pub fn action_composition_layers(
+        &self,
+        action_id: i64,
+    ) -> Result<Vec<CompositionLayer>, RowError> {
+        // layers an action needs on whatever entity performs it
+        let x: &[(&str, &dyn ToSql)] = named_params! { ":aid": action_id };
+        query_as(
+            self.conn,
+            "SELECT 'c'.'entity_composition_layer_id', 'c'.'name', 'c'.'resource' FROM 'v_entity_action_composition_layers' as 'v' INNER JOIN 'entity_composition_layers' as 'c' ON 'v'.'entity_composition_layer_id' = 'c'.'entity_composition_layer_id' WHERE 'v'.'entity_action_id' = :aid ORDER BY 'c'.'entity_composition_layer_id' ASC;",
+            x,
+        )
+    }
+
+    pub fn action_requirements(&self, action_id: i64) -> Result<Vec<ActionRequirement>, RowError> {
+        let x: &[(&str, &dyn ToSql)] = named_params! { ":aid": action_id };
+        query_as(
+            self.conn,
+            "SELECT 'r'.'entity_action_id', 'r'.'entity_attributes', 'r'.'entity_action_cost' FROM 'entity_actions_requirements_definitions' as 'r' WHERE 'r'.'entity_action_id' = :aid ORDER BY 'r'.'rowid' ASC;",
+            x,
+        )
+    }
+
+    pub fn attributes(&self) -> Result<Vec<EntityAttribute>, RowError> {
+        let x: &[(&str, &dyn ToSql)] = &[];
+        query_as(
+            self.conn,
+            "SELECT 'a'.'entity_attribute_id', 'a'.'name' FROM 'entity_attributes' as 'a' ORDER BY 'a'.'entity_attribute_id' ASC;",
+            x,
+        )
+    }
+
+    pub fn entity_attribute_values(&self, entity_id: i64) -> Result<Vec<AttributeValue>, RowError> {
+        let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": entity_id };
+        query_as(
+            self.conn,
+            "SELECT 'd'.'entity_attribute_id', 'a'.'name', 'd'.'attribute_value' FROM 'entity_attributes_definitions' as 'd' INNER JOIN 'entity_attributes' as 'a' ON 'd'.'entity_attribute_id' = 'a'.'entity_attribute_id' WHERE 'd'.'entity_base_id' = :eid ORDER BY 'd'.'entity_attribute_id' ASC;",
+            x,
+        )
+    }
+
+    pub fn entity_row_version(&self, entity_id: i64) -> Result<Option<i64>, RowError> {
+        // nothing when the entity is gone
+        let x: &[(&str, &dyn ToSql)] = named_params! { ":eid": entity_id };
+        let version: Option<IntValue> = query_one_as(
+            self.conn,
+            "SELECT 'e'.'row_version' as 'value' FROM 'entity_base_definitions' as 'e' WHERE 'e'.'entity_base_id' = :eid;",
+            x,
+        )?;
+
+        Ok(version.map(|v| v.value))
+    }
+
+    pub fn create_entity(&self, entity: &EntityBase) -> Result<bool, Error> {
+        // returns whether it was created, an id that is already taken is left alone
+        savepoint(self.conn, || {
+            let created: usize = self.conn.execute(
+                "INSERT INTO entity_base_definitions (
+                    entity_base_id, name, entity_core_type_id, entity_sub_type_id
+                ) VALUES (
+                    ?1, ?2, ?3, ?4
+                ) ON CONFLICT (entity_base_id) DO NOTHING;",
+                (
+                    entity.id,
+                    &entity.name,
+                    entity.core_type_id,
+                    entity.sub_type_id,
+                ),
+            )?;
+            self.audit_created(entity.id, created)
+        })
+    }
+
+    pub fn create_blank_entity(&self, entity_id: i64) -> Result<bool, Error> {
+        // only the id, the rest is filled in afterwards
+        savepoint(self.conn, || {
+            let created: usize = self.conn.execute(
+                "INSERT INTO entity_base_definitions (
+                    entity_base_id
+                ) VALUES (
+                    ?1
+                ) ON CONFLICT (entity_base_id) DO NOTHING;",
+                (entity_id,),
+            )?;
+            self.audit_created(entity_id, created)
+        })
+    }
+
+    fn audit_created(&self, entity_id: i64, created: usize) -> Result<bool, Error> {
+        if created == 0 {
+            return Ok(false);
+        }
+        record_audit(
+            self.conn,
+            AuditPackage {
+                table_name: "entity_base_definitions",
+                row_key: entity_id.to_string(),
+                column_name: None,
+                before: None,
+                after: Some(String::from("created")),
+                entity_id: Some(entity_id.to_string()),
+            },
+        )?;
+
+        Ok(true)
+    }
+
+    pub fn update_entity(
+        &self,
+        entity: &EntityBase,
+        seen: Option<i64>,
+    ) -> Result<Option<i64>, Error> {
+        // returns the entity's current version when it moved on from `seen`, nothing is
+        // written then. with no `seen` the row is written whatever its version
+        let before: Option<EntityBase> = self.entity(entity.id).map_err(read_failed)?;
+
+        let written: usize = savepoint(self.conn, || {
+            let written: usize = self.conn.execute(
+                "UPDATE entity_base_definitions SET
+                    name = ?2,
+                    entity_core_type_id = ?3,
+                    entity_sub_type_id = ?4
+                WHERE entity_base_id = ?1 AND (?5 IS NULL OR row_version = ?5);",
+                (
+                    entity.id,
+                    &entity.name,
+                    entity.core_type_id,
+                    entity.sub_type_id,
+                    seen,
+                ),
+            )?;
+            if let (true, Some(before)) = (written > 0, &before) {
+                self.audit_entity_changes(before, entity)?;
+            }
+            Ok(written)
+        })?;
+
+        match (written, seen) {
+            (0, Some(seen)) => match self.entity_row_version(entity.id).map_err(read_failed)? {
+                Some(current) if current != seen => Ok(Some(current)),
+                _ => Ok(None),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    fn audit_entity_changes(&self, before: &EntityBase, after: &EntityBase) -> Result<(), Error> {
+        let columns: [(&'static str, String, String); 3] = [
+            ("name", before.name.clone(), after.name.clone()),
+            (
+                "entity_core_type_id",
+                before.core_type_id.to_string(),
+                after.core_type_id.to_string(),
+            ),
+            (
+                "entity_sub_type_id",
+                before.sub_type_id.to_string(),
+                after.sub_type_id.to_string(),
+            ),
+        ];
+
+        for (column, old, new) in columns {
+            if old != new {
+                record_audit(
+                    self.conn,
+                    AuditPackage {
+                        table_name: "entity_base_definitions",
+                        row_key: after.id.to_string(),
+                        column_name: Some(column),
+                        before: Some(old),
+                        after: Some(new),
+                        entity_id: Some(after.id.to_string()),
+                    },
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn set_entity_composition_layer(
+        &self,
+        entity_id: i64,
+        layer_id: i64,
+        enabled: bool,
+    ) -> Result<bool, Error> {
+        // returns whether anything changed, a layer already in the wanted state is left alone
+        savepoint(self.conn, || {
+            let changed: usize = match enabled {
+                true => self.conn.execute(
+                    "INSERT INTO entity_composition_layers_definitions (
+                        entity_composition_layer_id, entity_base_id
+                    ) VALUES (
+                        ?1, ?2
+                    ) ON CONFLICT (entity_base_id, entity_composition_layer_id) DO NOTHING;",
+                    (layer_id, entity_id),
+                )?,
+                false => self.conn.execute(
+                    "DELETE FROM entity_composition_layers_definitions WHERE entity_base_id = ?2 AND entity_composition_layer_id = ?1;",
+                    (layer_id, entity_id),
+                )?,
+            };
+            if changed == 0 {
+                return Ok(false);
+            }
+
+            // the layer id sits in "after" when enabled and in "before" when disabled
+            let layer: Option<String> = Some(layer_id.to_string());
+            let (before, after): (Option<String>, Option<String>) = match enabled {
+                true => (None, layer),
+                false => (layer, None),
+            };
+            record_audit(
+                self.conn,
+                AuditPackage {
+                    table_name: "entity_composition_layers_definitions",
+                    row_key: format!("{}:{}", entity_id, layer_id),
+                    column_name: Some("entity_composition_layer_id"),
+                    before,
+                    after,
+                    entity_id: Some(entity_id.to_string()),
+                },
+            )?;
+
+            Ok(true)
+        })
+    }
+}
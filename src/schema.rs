@@ -0,0 +1,303 @@
+//! The editor's own tables, columns and triggers, set up on top of the game's schema.
+
+use crate::db::{query_as, query_one_as, read_failed, savepoint};
+use crate::models::{IntValue, TableColumn, TextValue};
+use rusqlite::*;
+
+// tables whose rows carry a row_version, with the column each is keyed by
+pub const VERSIONED_TABLES: [(&str, &str); 5] = [
+    ("entity_base_definitions", "entity_base_id"),
+    ("entity_composition_layers", "entity_composition_layer_id"),
+    ("entity_states_definitions", "index"),
+    (
+        "entity_actions_requirements_definitions",
+        "entity_action_requirement_id",
+    ),
+    ("entity_actions_callables", "entity_action_id"),
+];
+// tables of rows that belong to one entity, writing any of them moves that entity's row_version
+pub const ENTITY_CHILD_TABLES: [&str; 6] = [
+    "entity_composition_layers_definitions",
+    "entity_attributes_definitions",
+    "entity_actions_definitions",
+    "entity_avatars_definitions",
+    "entity_identity_types_definitions",
+    "entity_templates_definitions",
+];
+
+pub type SchemaStep = fn(&Connection) -> Result<(), Error>;
+
+// the editor's own tables, columns and triggers, one step per entry and only ever appended
+// to. PRAGMA user_version counts the steps a database has been through, so each runs once.
+// databases set up before the count was kept start at 0, which is why every step can be
+// run again over its own result
+pub const SCHEMA_STEPS: [(&str, SchemaStep); 9] = [
+    ("audit log", create_audit_log),
+    ("one row per entity and layer", unique_entity_comp_layers),
+    ("templates", create_template_tables),
+    ("identity types", create_identity_type_links),
+    ("layer rules", create_comp_layer_rules),
+    ("avatar images and layer order", add_editor_columns),
+    ("row versions", add_row_versions),
+    ("entity versions", add_entity_version_triggers),
+    ("requirement keys", add_requirement_keys),
+];
+
+pub fn ensure_app_schema(db: &Connection) -> Result<(), (&'static str, Error)> {
+    // returns the step that failed, everything before it stays done
+    let x: &[(&str, &dyn ToSql)] = &[];
+    let done: Option<IntValue> = query_one_as(
+        db,
+        "SELECT user_version as 'value' FROM pragma_user_version();",
+        x,
+    )
+    .map_err(|e| ("schema version", read_failed(e)))?;
+    let done: usize = done.map_or(0, |v| v.value.max(0) as usize);
+
+    for (step, (name, migrate)) in SCHEMA_STEPS.iter().enumerate().skip(done) {
+        // a step and its count land together, a failed step leaves no trace
+        savepoint(db, || {
+            migrate(db)?;
+            db.pragma_update(None, "user_version", step as i64 + 1)
+        })
+        .map_err(|e| (*name, e))?;
+    }
+
+    Ok(())
+}
+
+fn create_audit_log(db: &Connection) -> Result<(), Error> {
+    db.execute_batch(
+        r#"CREATE TABLE IF NOT EXISTS "entity_audit_log" (
+            "entity_audit_log_id"	INTEGER NOT NULL UNIQUE,
+            "timestamp"	TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            "os_user"	TEXT NOT NULL,
+            "table_name"	TEXT NOT NULL,
+            "row_key"	TEXT NOT NULL,
+            "column_name"	TEXT,
+            "before_value"	TEXT,
+            "after_value"	TEXT,
+            "entity_base_id"	INTEGER,
+            PRIMARY KEY("entity_audit_log_id" AUTOINCREMENT)
+        );"#,
+    )
+}
+
+fn unique_entity_comp_layers(db: &Connection) -> Result<(), Error> {
+    // backs the ON CONFLICT clause used when enabling a comp layer. a layer enabled twice
+    // before the index existed is the same layer, so only its first row is kept
+    db.execute_batch(
+        r#"DELETE FROM "entity_composition_layers_definitions" WHERE rowid NOT IN (
+            SELECT MIN(rowid) FROM "entity_composition_layers_definitions"
+            GROUP BY "entity_base_id", "entity_composition_layer_id"
+        );
+        CREATE UNIQUE INDEX IF NOT EXISTS "entity_composition_layers_definitions_unique"
+            ON "entity_composition_layers_definitions" ("entity_base_id", "entity_composition_layer_id");"#,
+    )
+}
+
+fn create_template_tables(db: &Connection) -> Result<(), Error> {
+    db.execute_batch(
+        r#"CREATE TABLE IF NOT EXISTS "entity_templates" (
+            "entity_template_id"	INTEGER NOT NULL UNIQUE,
+            "name"	TEXT NOT NULL UNIQUE,
+            "entity_core_type_id"	INTEGER NOT NULL,
+            "entity_sub_type_id"	INTEGER NOT NULL,
+            PRIMARY KEY("entity_template_id" AUTOINCREMENT),
+            FOREIGN KEY("entity_core_type_id") REFERENCES "entity_core_types"("entity_core_type_id"),
+            FOREIGN KEY("entity_sub_type_id") REFERENCES "entity_sub_types"("entity_sub_type_id")
+        );
+        CREATE TABLE IF NOT EXISTS "entity_templates_composition_layers" (
+            "entity_template_id"	INTEGER NOT NULL,
+            "entity_composition_layer_id"	INTEGER NOT NULL,
+            UNIQUE("entity_template_id", "entity_composition_layer_id"),
+            FOREIGN KEY("entity_template_id") REFERENCES "entity_templates"("entity_template_id"),
+            FOREIGN KEY("entity_composition_layer_id") REFERENCES "entity_composition_layers"("entity_composition_layer_id")
+        );
+        CREATE TABLE IF NOT EXISTS "entity_templates_attributes" (
+            "entity_template_id"	INTEGER NOT NULL,
+            "entity_attribute_id"	INTEGER NOT NULL,
+            "attribute_value"	REAL NOT NULL,
+            UNIQUE("entity_template_id", "entity_attribute_id"),
+            FOREIGN KEY("entity_template_id") REFERENCES "entity_templates"("entity_template_id"),
+            FOREIGN KEY("entity_attribute_id") REFERENCES "entity_attributes"("entity_attribute_id")
+        );
+        CREATE TABLE IF NOT EXISTS "entity_templates_actions" (
+            "entity_template_id"	INTEGER NOT NULL,
+            "entity_action_id"	INTEGER NOT NULL,
+            UNIQUE("entity_template_id", "entity_action_id"),
+            FOREIGN KEY("entity_template_id") REFERENCES "entity_templates"("entity_template_id"),
+            FOREIGN KEY("entity_action_id") REFERENCES "entity_actions"("entity_action_id")
+        );
+        CREATE TABLE IF NOT EXISTS "entity_templates_definitions" (
+            "entity_base_id"	INTEGER NOT NULL UNIQUE,
+            "entity_template_id"	INTEGER NOT NULL,
+            PRIMARY KEY("entity_base_id"),
+            FOREIGN KEY("entity_base_id") REFERENCES "entity_base_definitions"("entity_base_id"),
+            FOREIGN KEY("entity_template_id") REFERENCES "entity_templates"("entity_template_id")
+        );"#,
+    )
+}
+
+fn create_identity_type_links(db: &Connection) -> Result<(), Error> {
+    db.execute_batch(
+        r#"CREATE TABLE IF NOT EXISTS "entity_identity_types_definitions" (
+            "entity_base_id"	INTEGER NOT NULL UNIQUE,
+            "entity_identity_type_id"	INTEGER NOT NULL,
+            PRIMARY KEY("entity_base_id"),
+            FOREIGN KEY("entity_base_id") REFERENCES "entity_base_definitions"("entity_base_id"),
+            FOREIGN KEY("entity_identity_type_id") REFERENCES "entity_identities"("entity_identity_type_id")
+        );"#,
+    )
+}
+
+fn create_comp_layer_rules(db: &Connection) -> Result<(), Error> {
+    // the default layer rules are only seeded when the rules table is first created,
+    // so rules removed by hand stay removed
+    let x: &[(&str, &dyn ToSql)] = &[];
+    let rules_table: Option<TextValue> = query_one_as(
+        db,
+        "SELECT name as 'value' FROM sqlite_schema WHERE type = 'table' AND name = 'entity_composition_layers_rules';",
+        x,
+    )
+    .map_err(read_failed)?;
+    if rules_table.is_some() {
+        return Ok(());
+    }
+
+    db.execute_batch(
+        r#"CREATE TABLE IF NOT EXISTS "entity_composition_layers_rules" (
+            "entity_composition_layers_rule_id"	INTEGER NOT NULL UNIQUE,
+            "entity_composition_layer_id"	INTEGER NOT NULL,
+            "relation"	TEXT NOT NULL CHECK("relation" IN ('requires', 'conflicts')),
+            "related_layer_id"	INTEGER NOT NULL,
+            PRIMARY KEY("entity_composition_layers_rule_id" AUTOINCREMENT),
+            UNIQUE("entity_composition_layer_id", "relation", "related_layer_id"),
+            FOREIGN KEY("entity_composition_layer_id") REFERENCES "entity_composition_layers"("entity_composition_layer_id"),
+            FOREIGN KEY("related_layer_id") REFERENCES "entity_composition_layers"("entity_composition_layer_id")
+        );"#,
+    )?;
+
+    // every EntityAction* layer is an add-on to EntityActions
+    db.execute(
+        "INSERT OR IGNORE INTO entity_composition_layers_rules (
+            entity_composition_layer_id, relation, related_layer_id
+        ) SELECT 'c'.'entity_composition_layer_id', 'requires', 'a'.'entity_composition_layer_id'
+        FROM 'entity_composition_layers' as 'c', 'entity_composition_layers' as 'a'
+        WHERE 'a'.'name' = 'EntityActions' AND 'c'.'name' LIKE 'EntityAction%' AND 'c'.'name' != 'EntityActions';",
+        (),
+    )?;
+
+    Ok(())
+}
+
+fn add_editor_columns(db: &Connection) -> Result<(), Error> {
+    // avatars point at the image they were created from and carry a copy of its bytes
+    ensure_column(db, "entity_avatars", "resource", "TEXT")?;
+    ensure_column(db, "entity_avatars", "image", "BLOB")?;
+    // layers are listed by this when set, falling back to their id
+    ensure_column(db, "entity_composition_layers", "sort_order", "INTEGER")
+}
+
+fn add_row_versions(db: &Connection) -> Result<(), Error> {
+    // every update bumps row_version, including ones made outside the editor, so a write can
+    // check the row is still at the version it was read at
+    for (table, _) in VERSIONED_TABLES {
+        ensure_column(db, table, "row_version", "INTEGER NOT NULL DEFAULT 0")?;
+        db.execute_batch(
+            &format!(
+                "CREATE TRIGGER IF NOT EXISTS \"{table}_row_version\" AFTER UPDATE ON \"{table}\"
+                FOR EACH ROW WHEN NEW.row_version = OLD.row_version
+                BEGIN
+                    UPDATE \"{table}\" SET row_version = OLD.row_version + 1 WHERE rowid = NEW.rowid;
+                END;",
+                table = table,
+            )[..],
+        )?;
+    }
+
+    Ok(())
+}
+
+fn add_entity_version_triggers(db: &Connection) -> Result<(), Error> {
+    // rows hanging off an entity move the entity's own row_version, so one check before a
+    // write covers everything the canvas shows
+    for table in ENTITY_CHILD_TABLES {
+        for (event, row) in [
+            ("INSERT", "NEW.entity_base_id"),
+            ("UPDATE", "NEW.entity_base_id, OLD.entity_base_id"),
+            ("DELETE", "OLD.entity_base_id"),
+        ] {
+            db.execute_batch(
+                &format!(
+                    "CREATE TRIGGER IF NOT EXISTS \"{table}_{event}_entity_version\" AFTER {event} ON \"{table}\"
+                    FOR EACH ROW
+                    BEGIN
+                        UPDATE entity_base_definitions SET row_version = row_version + 1 WHERE entity_base_id IN ({row});
+                    END;",
+                    table = table,
+                    event = event.to_lowercase(),
+                    row = row,
+                )[..],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn add_requirement_keys(db: &Connection) -> Result<(), Error> {
+    // requirements had no key of their own and a VACUUM may renumber rowids. keys start out
+    // as the rowid they stand in for, so audit entries written before keep pointing at their
+    // row. rows inserted without one, by the editor or anyone else, get the next free key
+    ensure_column(
+        db,
+        "entity_actions_requirements_definitions",
+        "entity_action_requirement_id",
+        "INTEGER",
+    )?;
+    db.execute_batch(
+        r#"UPDATE "entity_actions_requirements_definitions"
+            SET "entity_action_requirement_id" = rowid + (
+                SELECT COALESCE(MAX("entity_action_requirement_id"), 0) FROM "entity_actions_requirements_definitions"
+            )
+            WHERE "entity_action_requirement_id" IS NULL;
+        CREATE UNIQUE INDEX IF NOT EXISTS "entity_actions_requirements_definitions_key"
+            ON "entity_actions_requirements_definitions" ("entity_action_requirement_id");
+        CREATE TRIGGER IF NOT EXISTS "entity_actions_requirements_definitions_key"
+            AFTER INSERT ON "entity_actions_requirements_definitions"
+            FOR EACH ROW WHEN NEW."entity_action_requirement_id" IS NULL
+            BEGIN
+                UPDATE "entity_actions_requirements_definitions" SET "entity_action_requirement_id" = (
+                    SELECT COALESCE(MAX("entity_action_requirement_id"), 0) + 1 FROM "entity_actions_requirements_definitions"
+                ) WHERE rowid = NEW.rowid;
+            END;"#,
+    )
+}
+
+fn ensure_column(
+    db: &Connection,
+    table: &str,
+    column: &str,
+    declaration: &str,
+) -> Result<(), Error> {
+    let table_info: String = String::from_iter(["PRAGMA \"main\".TABLE_INFO(\"", table, "\");"]);
+    let columns: Vec<TableColumn> = query_as(db, &table_info[..], &[]).map_err(read_failed)?;
+
+    if columns.iter().any(|c| c.name == column) {
+        return Ok(());
+    }
+
+    let alter: String = String::from_iter([
+        "ALTER TABLE \"",
+        table,
+        "\" ADD COLUMN \"",
+        column,
+        "\" ",
+        declaration,
+        ";",
+    ]);
+    db.execute(&alter[..], ())?;
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+-- a small, fixed world loaded on top of schema.sql for tests. ids are spelled out so
+-- tests and snapshots can refer to them.
+
+INSERT INTO entity_core_types (entity_core_type_id, name) VALUES
+    (1, 'ITEM'),
+    (2, 'MOB');
+
+INSERT INTO entity_sub_types (entity_sub_type_id, name) VALUES
+    (1, 'HELMET'),
+    (2, 'SWORD'),
+    (3, 'HUMANOID');
+
+INSERT INTO entity_attributes (entity_attribute_id, name) VALUES
+    (1, 'STRENGTH'),
+    (2, 'HEALTH'),
+    (3, 'STAMINA');
+
+INSERT INTO entity_composition_layers (entity_composition_layer_id, name, resource) VALUES
+    (1, 'EntityActions', 'res://scripts/entity_actions/entity_actions.gd'),
+    (2, 'EntityAttributes', 'res://scripts/entity/entity_attributes.gd'),
+    (3, 'EntityIdentity', 'res://scripts/entity/entity_identity.gd'),
+    (4, 'EntityActionRequirement', 'res://scripts/entity_actions/entity_action_requirement.gd');
+
+INSERT INTO entity_actions_types (action_type_id, name) VALUES
+    (1, 'COMBAT'),
+    (2, 'MOVEMENT');
+
+INSERT INTO entity_actions (entity_action_id, name, action_type_id) VALUES
+    (1, 'WALK', 2),
+    (2, 'BASH', 1);
+
+INSERT INTO entity_actions_composition_definitions (entity_action_id, entity_composition_layer_id) VALUES
+    (1, 4),
+    (2, 4);
+
+INSERT INTO entity_actions_requirements_definitions (entity_action_id, entity_attributes, entity_action_cost) VALUES
+    (1, 3, 1),
+    (2, 3, 4);
+
+INSERT INTO entity_actions_targets_types (entity_actions_targets_types_id, name) VALUES
+    (1, 'SELF'),
+    (2, 'OTHER');
+
+INSERT INTO entity_base_definitions (entity_base_id, name, entity_core_type_id, entity_sub_type_id) VALUES
+    (1, 'cloth helmet', 1, 1),
+    (2, 'iron sword', 1, 2),
+    (3, 'human', 2, 3),
+    (4, 'goblin', 2, 3);
+
+INSERT INTO entity_composition_layers_definitions (entity_base_id, entity_composition_layer_id) VALUES
+    (3, 1),
+    (3, 2),
+    (3, 4);
+
+INSERT INTO entity_attributes_definitions (entity_base_id, entity_attribute_id, attribute_value) VALUES
+    (3, 1, 5),
+    (3, 2, 10),
+    (4, 2, 4);
+
+INSERT INTO entity_actions_definitions (entity_base_id, entity_action_id) VALUES
+    (3, 1),
+    (3, 2);
+
+INSERT INTO enums ("index", "table") VALUES
+    (1, 'entity_actions'),
+    (2, 'entity_actions_targets_types'),
+    (3, 'entity_attributes'),
+    (4, 'entity_composition_layers'),
+    (5, 'entity_core_types');
@@ -0,0 +1,225 @@
+-- the cold storage schema as shipped with the game, without any rows.
+-- regenerate from a real cold_storage.db when the game adds tables.
+
+CREATE TABLE "entity_avatars" (
+	"entity_avatar_id"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT NOT NULL,
+	PRIMARY KEY("entity_avatar_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_avatars_definitions" (
+	"entity_base_id"	INTEGER NOT NULL UNIQUE,
+	"entity_avatar_id"	INTEGER,
+	FOREIGN KEY("entity_avatar_id") REFERENCES "entity_avatars"("entity_avatar_id"),
+	PRIMARY KEY("entity_base_id")
+);
+
+CREATE TABLE "entity_states" (
+	"entity_state_id"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT NOT NULL,
+	PRIMARY KEY("entity_state_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_attributes" (
+	"entity_attribute_id"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT NOT NULL,
+	PRIMARY KEY("entity_attribute_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_states_definitions" (
+	"index"	INTEGER NOT NULL UNIQUE,
+	"entity_state_id"	INTEGER NOT NULL,
+	"entity_attribute_id"	INTEGER NOT NULL,
+	"magnitude"	INTEGER NOT NULL,
+	"duration"	INTEGER NOT NULL,
+	FOREIGN KEY("entity_attribute_id") REFERENCES "entity_attributes"("entity_attribute_id"),
+	FOREIGN KEY("entity_state_id") REFERENCES "entity_states"("entity_state_id"),
+	PRIMARY KEY("index" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_sub_types" (
+	"entity_sub_type_id"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT NOT NULL UNIQUE,
+	PRIMARY KEY("entity_sub_type_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_core_types" (
+	"entity_core_type_id"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT NOT NULL UNIQUE,
+	PRIMARY KEY("entity_core_type_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_base_definitions" (
+	"entity_base_id"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT NOT NULL UNIQUE,
+	"entity_core_type_id"	INTEGER NOT NULL,
+	"entity_sub_type_id"	INTEGER NOT NULL,
+	PRIMARY KEY("entity_base_id" AUTOINCREMENT),
+	FOREIGN KEY("entity_sub_type_id") REFERENCES "entity_sub_types"("entity_sub_type_id"),
+	FOREIGN KEY("entity_core_type_id") REFERENCES "entity_core_types"("entity_core_type_id")
+);
+
+CREATE TABLE "entity_identities" (
+	"entity_identity_type_id"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT NOT NULL,
+	PRIMARY KEY("entity_identity_type_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_identities_definitions" (
+	"index"	INTEGER NOT NULL UNIQUE,
+	"entity_identity_type_id"	INTEGER NOT NULL,
+	"name"	TEXT NOT NULL,
+	PRIMARY KEY("index" AUTOINCREMENT),
+	FOREIGN KEY("entity_identity_type_id") REFERENCES "entity_identities"("entity_identity_type_id")
+);
+
+CREATE TABLE "entity_composition_layers_definitions" (
+	"index"	INTEGER NOT NULL UNIQUE,
+	"entity_base_id"	INTEGER NOT NULL,
+	"entity_composition_layer_id"	INTEGER NOT NULL,
+	FOREIGN KEY("entity_base_id") REFERENCES "entity_base_definitions"("entity_base_id"),
+	FOREIGN KEY("entity_composition_layer_id") REFERENCES "entity_composition_layers"("entity_composition_layer_id"),
+	PRIMARY KEY("index" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_actions" (
+	"entity_action_id"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT,
+	"action_type_id"	INTEGER,
+	FOREIGN KEY("action_type_id") REFERENCES "entity_actions_types"("action_type_id"),
+	PRIMARY KEY("entity_action_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_actions_types" (
+	"action_type_id"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT NOT NULL UNIQUE,
+	PRIMARY KEY("action_type_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_actions_modals" (
+	"entity_actions_modals_id"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT NOT NULL UNIQUE,
+	PRIMARY KEY("entity_actions_modals_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_actions_states" (
+	"entity_actions_states_id"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT NOT NULL UNIQUE,
+	PRIMARY KEY("entity_actions_states_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_actions_attributes" (
+	"entity_actions_attributes_id"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT,
+	PRIMARY KEY("entity_actions_attributes_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_attributes_definitions" (
+	"entity_attributes_definitions_id"	INTEGER NOT NULL UNIQUE,
+	"entity_base_id"	INTEGER NOT NULL,
+	"entity_attribute_id"	INTEGER NOT NULL,
+	"attribute_value"	REAL NOT NULL,
+	PRIMARY KEY("entity_attributes_definitions_id" AUTOINCREMENT),
+	FOREIGN KEY("entity_base_id") REFERENCES "entity_base_definitions"("entity_base_id"),
+	FOREIGN KEY("entity_attribute_id") REFERENCES "entity_attributes"("entity_attribute_id")
+);
+
+CREATE TABLE "entity_actions_targets_types" (
+	"entity_actions_targets_types_id"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT NOT NULL,
+	PRIMARY KEY("entity_actions_targets_types_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_actions_attributes_definitions" (
+	"entity_actions_attributes_definitions_id"	INTEGER NOT NULL UNIQUE,
+	"entity_actions_attributes_id"	INTEGER,
+	"entity_action_id"	INTEGER,
+	"entity_actions_attribute_value"	INTEGER,
+	FOREIGN KEY("entity_actions_attributes_id") REFERENCES "entity_actions_attributes"("entity_actions_attributes_id"),
+	FOREIGN KEY("entity_action_id") REFERENCES "entity_actions"("entity_action_id"),
+	PRIMARY KEY("entity_actions_attributes_definitions_id" AUTOINCREMENT)
+);
+
+CREATE TABLE "entity_actions_composition_definitions" (
+	"entity_action_id"	INTEGER NOT NULL,
+	"entity_composition_layer_id"	INTEGER NOT NULL,
+	FOREIGN KEY("entity_composition_layer_id") REFERENCES "entity_composition_layers"("entity_composition_layer_id"),
+	FOREIGN KEY("entity_action_id") REFERENCES "entity_actions"("entity_action_id")
+);
+
+CREATE TABLE "entity_composition_layers" (
+	"entity_composition_layer_id"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT NOT NULL,
+	"resource"	TEXT,
+	PRIMARY KEY("entity_composition_layer_id" AUTOINCREMENT)
+);
+
+CREATE VIEW 'v_entity_action_composition_layers' AS
+SELECT
+	'e'.'entity_action_id', 
+	'e'.'entity_composition_layer_id', 
+	'c'.'name' 
+
+FROM 'entity_actions_composition_definitions' as 'e'
+LEFT JOIN 'entity_composition_layers' as 'c'
+ON 'e'.'entity_composition_layer_id' = 'c'.'entity_composition_layer_id';
+
+CREATE TABLE "entity_actions_modals_definitions" (
+	"entity_action_id"	INTEGER,
+	"entity_actions_modals_id"	INTEGER,
+	FOREIGN KEY("entity_actions_modals_id") REFERENCES "entity_actions_modals"("entity_actions_modals_id"),
+	FOREIGN KEY("entity_action_id") REFERENCES "entity_actions"("entity_action_id")
+);
+
+CREATE TABLE "entity_actions_requirements_definitions" (
+	"entity_action_id"	INTEGER,
+	"entity_attributes"	INTEGER,
+	"entity_action_cost"	INTEGER,
+	FOREIGN KEY("entity_action_id") REFERENCES "entity_actions"("entity_action_id"),
+	FOREIGN KEY("entity_attributes") REFERENCES "entity_attributes"("entity_attribute_id")
+);
+
+CREATE TABLE "entity_actions_targets_definitions" (
+	"entity_action_id"	INTEGER,
+	"entity_actions_targets_types_id"	INTEGER,
+	FOREIGN KEY("entity_action_id") REFERENCES "entity_actions"("entity_action_id"),
+	FOREIGN KEY("entity_actions_targets_types_id") REFERENCES "entity_actions_targets_types"("entity_actions_targets_types_id")
+);
+
+CREATE TABLE "entity_actions_definitions" (
+	"entity_base_id"	INTEGER NOT NULL,
+	"entity_action_id"	INTEGER NOT NULL,
+	FOREIGN KEY("entity_action_id") REFERENCES "entity_actions"("entity_action_id"),
+	FOREIGN KEY("entity_base_id") REFERENCES "entity_base_definitions"("entity_base_id")
+);
+
+CREATE TABLE "entity_actions_callables" (
+	"entity_action_id"	INTEGER NOT NULL UNIQUE,
+	"callable_location"	TEXT NOT NULL,
+	PRIMARY KEY("entity_action_id"),
+	FOREIGN KEY("entity_action_id") REFERENCES "entity_actions"("entity_action_id")
+);
+
+CREATE TABLE "entity_permissions" (
+	"index"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT NOT NULL UNIQUE,
+	PRIMARY KEY("index" AUTOINCREMENT)
+);
+
+CREATE TABLE "enums" (
+	"index"	INTEGER NOT NULL UNIQUE,
+	"table"	TEXT NOT NULL,
+	PRIMARY KEY("index" AUTOINCREMENT)
+);
+
+CREATE TABLE "unit_control_style" (
+	"index"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT,
+	PRIMARY KEY("index" AUTOINCREMENT)
+);
+
+CREATE TABLE "camera_control_style" (
+	"index"	INTEGER NOT NULL UNIQUE,
+	"name"	TEXT NOT NULL,
+	PRIMARY KEY("index" AUTOINCREMENT)
+);
@@ -0,0 +1,44 @@
+use rusqlite::Connection;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// a throwaway copy of test_data/cold_storage.db, removed again when dropped
+pub struct TempColdStorage {
+    dir: PathBuf,
+    pub conn: Connection,
+}
+
+impl TempColdStorage {
+    pub fn new(name: &str) -> Self {
+        let nanos: u128 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let mut dir: PathBuf = std::env::temp_dir();
+        dir.push(format!(
+            "entity_creator_{}_{}_{}",
+            name,
+            std::process::id(),
+            nanos
+        ));
+        fs::create_dir_all(&dir).expect("create temp dir");
+
+        let mut source: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        source.push("test_data");
+        source.push("cold_storage.db");
+        let mut path: PathBuf = dir.clone();
+        path.push("cold_storage.db");
+        fs::copy(&source, &path).expect("copy test_data/cold_storage.db");
+
+        let conn: Connection = Connection::open(&path).expect("open temp cold storage");
+
+        Self { dir, conn }
+    }
+}
+
+impl Drop for TempColdStorage {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::TempColdStorage;
+use entity_creator::{query_as, EntityAttribute, FromRecord, Row, RowError};
+
+// decodes only the columns it names, so extra or reordered columns don't matter
+#[derive(Debug, PartialEq)]
+struct NamedId {
+    id: i64,
+    name: String,
+}
+
+impl FromRecord for NamedId {
+    fn from_row(row: &Row) -> Result<Self, RowError> {
+        Ok(Self {
+            id: row.i64("id")?,
+            name: row.text("name")?,
+        })
+    }
+}
+
+#[test]
+fn decodes_by_column_name_in_any_order() {
+    let db: TempColdStorage = TempColdStorage::new("decodes_by_column_name_in_any_order");
+
+    let rows: Vec<NamedId> = query_as(
+        &db.conn,
+        "SELECT 'a'.'name', 'extra' as 'unused', 'a'.'entity_attribute_id' as 'id' FROM 'entity_attributes' as 'a' ORDER BY 'a'.'entity_attribute_id' ASC;",
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        rows[0],
+        NamedId {
+            id: 1,
+            name: String::from("STRENGTH"),
+        }
+    );
+}
+
+#[test]
+fn reports_a_missing_column() {
+    let db: TempColdStorage = TempColdStorage::new("reports_a_missing_column");
+
+    let rows: Result<Vec<EntityAttribute>, RowError> = query_as(
+        &db.conn,
+        "SELECT 'a'.'name' FROM 'entity_attributes' as 'a';",
+        &[],
+    );
+    assert_eq!(
+        rows,
+        Err(RowError::MissingColumn(String::from("entity_attribute_id")))
+    );
+}
+
+#[test]
+fn reports_a_column_of_the_wrong_type() {
+    let db: TempColdStorage = TempColdStorage::new("reports_a_column_of_the_wrong_type");
+
+    let rows: Result<Vec<NamedId>, RowError> = query_as(
+        &db.conn,
+        "SELECT 'a'.'name' as 'id', 'a'.'name' FROM 'entity_attributes' as 'a';",
+        &[],
+    );
+    assert_eq!(
+        rows,
+        Err(RowError::WrongType {
+            column: String::from("id"),
+            expected: "an integer",
+        })
+    );
+}
+
+#[test]
+fn reports_sql_errors_instead_of_an_empty_result() {
+    let db: TempColdStorage = TempColdStorage::new("reports_sql_errors_instead_of_an_empty_result");
+
+    let rows: Result<Vec<NamedId>, RowError> =
+        query_as(&db.conn, "SELECT id, name FROM no_such_table;", &[]);
+    assert!(matches!(rows, Err(RowError::Sql(_))));
+}
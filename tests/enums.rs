@@ -0,0 +1,61 @@
+use entity_creator::enums::{enum_name, generate_enums};
+use entity_creator::fixtures::{empty_db, fixture_db};
+use rusqlite::*;
+use std::fs;
+use std::path::PathBuf;
+
+// compares against tests/snapshots/<name>, or rewrites it when UPDATE_SNAPSHOTS is set
+fn assert_snapshot(name: &str, actual: &str) {
+    let mut path: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests");
+    path.push("snapshots");
+    path.push(name);
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, actual).expect("write snapshot");
+        return;
+    }
+
+    let expected: String = fs::read_to_string(&path).expect("read snapshot");
+    assert_eq!(
+        actual, expected,
+        "generated output no longer matches tests/snapshots/{}, rerun with UPDATE_SNAPSHOTS=1 if the change is intended",
+        name
+    );
+}
+
+#[test]
+fn enum_names_are_pascal_case() {
+    assert_eq!(enum_name("entity_actions"), "EntityActions");
+    assert_eq!(
+        enum_name("entity_actions_targets_types"),
+        "EntityActionsTargetsTypes"
+    );
+    assert_eq!(enum_name("enums"), "Enums");
+}
+
+#[test]
+fn no_listed_tables_means_no_enums() {
+    let db: Connection = empty_db().unwrap();
+
+    assert_eq!(generate_enums(&db), "");
+}
+
+#[test]
+fn enums_match_snapshot() {
+    let db: Connection = fixture_db().unwrap();
+
+    assert_snapshot("enums.gd", &generate_enums(&db));
+}
+
+#[test]
+fn new_rows_show_up_in_their_enum() {
+    let db: Connection = fixture_db().unwrap();
+    db.execute(
+        "INSERT INTO entity_actions (entity_action_id, name, action_type_id) VALUES (3, 'SPRINT', 2);",
+        (),
+    )
+    .unwrap();
+
+    assert_snapshot("enums_after_new_action.gd", &generate_enums(&db));
+}
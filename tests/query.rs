@@ -0,0 +1,138 @@
+use entity_creator::db::{
+    blob_summary, fetch_blob_ref, query, savepoint, write_blob, BlobRef, RecordSet, SqlData,
+};
+use entity_creator::fixtures::{empty_db, fixture_db};
+use rusqlite::*;
+
+#[test]
+fn schema_loads_into_memory() {
+    let db: Connection = empty_db().unwrap();
+
+    let rs: RecordSet = query(
+        &db,
+        "SELECT name FROM sqlite_schema WHERE type = 'table' AND name = 'entity_base_definitions';",
+        &[],
+    );
+    assert_eq!(rs.records.len(), 1);
+
+    let rs: RecordSet = query(&db, "SELECT * FROM entity_base_definitions;", &[]);
+    assert!(rs.records.is_empty());
+}
+
+#[test]
+fn query_reports_headers_and_typed_fields() {
+    let db: Connection = fixture_db().unwrap();
+
+    let rs: RecordSet = query(
+        &db,
+        "SELECT 'e'.'entity_base_id', 'e'.'name', 'a'.'attribute_value', NULL as 'missing', x'0102' as 'bytes' FROM 'entity_base_definitions' as 'e' INNER JOIN 'entity_attributes_definitions' as 'a' ON 'e'.'entity_base_id' = 'a'.'entity_base_id' WHERE 'e'.'entity_base_id' = 3 ORDER BY 'a'.'entity_attribute_id' ASC;",
+        &[],
+    );
+    assert_eq!(rs.headers.column_count, 5);
+    assert_eq!(
+        rs.headers.column_names,
+        vec![
+            "entity_base_id",
+            "name",
+            "attribute_value",
+            "missing",
+            "bytes"
+        ]
+    );
+    assert_eq!(rs.records.len(), 2);
+
+    let fields: &Vec<SqlData> = &rs.records[0].fields;
+    assert!(matches!(fields[0], SqlData::Integer(3)));
+    assert!(matches!(&fields[1], SqlData::Text(t) if t == "human"));
+    assert!(matches!(fields[2], SqlData::Real(v) if v == 5.0));
+    assert!(matches!(fields[3], SqlData::Null));
+    assert!(matches!(&fields[4], SqlData::Blob(b) if b == &vec![1u8, 2]));
+
+    let shown: Vec<String> = fields.iter().map(|f| f.to_string()).collect();
+    assert_eq!(shown, vec!["3", "human", "5", "", "<2 bytes: 01 02>"]);
+}
+
+#[test]
+fn query_binds_named_params() {
+    let db: Connection = fixture_db().unwrap();
+
+    let x: &[(&str, &dyn ToSql)] = named_params! { ":ect": "MOB" };
+    let rs: RecordSet = query(
+        &db,
+        "SELECT 'e'.'name' FROM 'entity_base_definitions' as 'e' WHERE 'e'.'entity_core_type_id' IN (SELECT 'c'.'entity_core_type_id' FROM 'entity_core_types' as 'c' WHERE 'c'.'name' = :ect) ORDER BY 'e'.'entity_base_id' ASC;",
+        x,
+    );
+    let names: Vec<String> = rs.records.iter().map(|r| r.fields[0].to_string()).collect();
+    assert_eq!(names, vec!["human", "goblin"]);
+}
+
+#[test]
+fn query_comes_back_empty_on_bad_sql() {
+    let db: Connection = fixture_db().unwrap();
+
+    let rs: RecordSet = query(&db, "SELECT nothing FROM nowhere;", &[]);
+    assert!(rs.records.is_empty());
+    assert_eq!(rs.headers.column_count, 0);
+}
+
+#[test]
+fn savepoints_roll_back_only_their_own_writes() {
+    let db: Connection = fixture_db().unwrap();
+    let names = |db: &Connection| -> Vec<String> {
+        query(
+            db,
+            "SELECT 'e'.'name' FROM 'entity_states' as 'e' ORDER BY 'e'.'entity_state_id' ASC;",
+            &[],
+        )
+        .records
+        .iter()
+        .map(|r| r.fields[0].to_string())
+        .collect()
+    };
+    let before: Vec<String> = names(&db);
+
+    // the inner failure takes its own insert with it, the outer one still lands
+    let result: Result<()> = savepoint(&db, || {
+        db.execute("INSERT INTO entity_states (name) VALUES ('kept');", ())?;
+        let inner: Result<()> = savepoint(&db, || {
+            db.execute("INSERT INTO entity_states (name) VALUES ('dropped');", ())?;
+            db.execute("INSERT INTO nowhere VALUES (1);", ())?;
+            Ok(())
+        });
+        assert!(inner.is_err());
+        Ok(())
+    });
+    assert!(result.is_ok());
+    assert_eq!(names(&db), [&before[..], &[String::from("kept")]].concat());
+
+    let result: Result<()> = savepoint(&db, || {
+        db.execute("INSERT INTO entity_states (name) VALUES ('dropped');", ())?;
+        Err(Error::StatementChangedRows(0))
+    });
+    assert!(result.is_err());
+    assert_eq!(names(&db).len(), before.len() + 1);
+    assert!(db.is_autocommit());
+}
+
+#[test]
+fn blobs_stream_without_going_through_a_row() {
+    let db: Connection = empty_db().unwrap();
+    db.execute_batch(
+        "CREATE TABLE images (image_id INTEGER PRIMARY KEY, image BLOB);
+        INSERT INTO images (image_id, image) VALUES (1, NULL), (2, NULL);",
+    )
+    .unwrap();
+
+    let bytes: Vec<u8> = (0..40u8).collect();
+    write_blob(&db, "images", "image", 2, &bytes).unwrap();
+
+    let image: BlobRef = fetch_blob_ref(&db, "images", "image", 2).unwrap().unwrap();
+    assert_eq!(image.len, 40);
+    assert_eq!(image.read(&db).unwrap(), bytes);
+    assert_eq!(image.read_head(&db, 4).unwrap(), vec![0, 1, 2, 3]);
+    assert_eq!(image.summary(&db).unwrap(), blob_summary(&bytes));
+
+    // NULL and missing rows have nothing to stream
+    assert_eq!(fetch_blob_ref(&db, "images", "image", 1).unwrap(), None);
+    assert_eq!(fetch_blob_ref(&db, "images", "image", 3).unwrap(), None);
+}
@@ -0,0 +1,209 @@
+mod common;
+
+use common::TempColdStorage;
+use entity_creator::audit::session_audit_ids_after;
+use entity_creator::enums::generate_enums;
+use entity_creator::schema::ensure_app_schema;
+use entity_creator::{CompositionLayer, EntityBase, Repository};
+use rusqlite::Connection;
+
+#[test]
+fn lists_every_entity() {
+    let db: TempColdStorage = TempColdStorage::new("lists_every_entity");
+    let repo: Repository = Repository::new(&db.conn);
+
+    let entities: Vec<EntityBase> = repo.entities().unwrap();
+    assert_eq!(entities.len(), 8);
+    assert_eq!(
+        entities[0],
+        EntityBase {
+            id: 1,
+            name: String::from("cloth helmet"),
+            core_type_id: 1,
+            sub_type_id: 1,
+        }
+    );
+}
+
+#[test]
+fn finds_an_entity_by_id() {
+    let db: TempColdStorage = TempColdStorage::new("finds_an_entity_by_id");
+    let repo: Repository = Repository::new(&db.conn);
+
+    let human: EntityBase = repo.entity(8).unwrap().expect("entity 8");
+    assert_eq!(human.name, "human");
+    assert_eq!(human.core_type_id, 3);
+    assert!(repo.entity(9999).unwrap().is_none());
+}
+
+#[test]
+fn reads_composition_layers() {
+    let db: TempColdStorage = TempColdStorage::new("reads_composition_layers");
+    let repo: Repository = Repository::new(&db.conn);
+
+    let layers: Vec<CompositionLayer> = repo.composition_layers().unwrap();
+    assert_eq!(layers.len(), 11);
+    assert_eq!(layers[0].name, "EntityActions");
+    assert_eq!(
+        layers[0].resource.as_deref(),
+        Some("res://scripts/entity_actions/entity_actions.gd")
+    );
+
+    let names: Vec<String> = repo
+        .entity_composition_layers(8)
+        .unwrap()
+        .into_iter()
+        .map(|l| l.name)
+        .collect();
+    assert!(names.contains(&String::from("EntityIdentity")));
+    assert!(repo.entity_composition_layers(9999).unwrap().is_empty());
+}
+
+#[test]
+fn reads_actions_and_what_they_need() {
+    let db: TempColdStorage = TempColdStorage::new("reads_actions_and_what_they_need");
+    let repo: Repository = Repository::new(&db.conn);
+
+    let actions = repo.actions().unwrap();
+    let walk = &actions[0];
+    assert_eq!(walk.name.as_deref(), Some("WALK"));
+    assert_eq!(walk.action_type_id, Some(2));
+
+    let entity_actions: Vec<i64> = repo
+        .entity_actions(8)
+        .unwrap()
+        .iter()
+        .map(|a| a.id)
+        .collect();
+    assert_eq!(entity_actions, vec![1, 11]);
+
+    let layer_ids: Vec<i64> = repo
+        .action_composition_layers(1)
+        .unwrap()
+        .iter()
+        .map(|l| l.id)
+        .collect();
+    assert_eq!(layer_ids, vec![7, 8, 9, 10, 11]);
+
+    let requirements = repo.action_requirements(1).unwrap();
+    assert_eq!(requirements.len(), 1);
+    assert_eq!(requirements[0].attribute_id, 16);
+    assert_eq!(requirements[0].cost, 1);
+}
+
+#[test]
+fn reads_attribute_values() {
+    let db: TempColdStorage = TempColdStorage::new("reads_attribute_values");
+    let repo: Repository = Repository::new(&db.conn);
+
+    assert_eq!(repo.attributes().unwrap()[0].name, "STRENGTH");
+
+    let values = repo.entity_attribute_values(8).unwrap();
+    assert_eq!(values.len(), 3);
+    assert_eq!(values[0].name, "STRENGTH");
+    assert_eq!(values[0].value, 5.0);
+}
+
+#[test]
+fn writes_stay_in_the_temp_copy() {
+    let db: TempColdStorage = TempColdStorage::new("writes_stay_in_the_temp_copy");
+    db.conn
+        .execute(
+            "UPDATE entity_base_definitions SET name = 'renamed' WHERE entity_base_id = 1;",
+            (),
+        )
+        .expect("update");
+
+    let repo: Repository = Repository::new(&db.conn);
+    assert_eq!(repo.entity(1).unwrap().expect("entity 1").name, "renamed");
+
+    let fresh: TempColdStorage = TempColdStorage::new("writes_stay_in_the_temp_copy_fresh");
+    let repo: Repository = Repository::new(&fresh.conn);
+    assert_eq!(
+        repo.entity(1).unwrap().expect("entity 1").name,
+        "cloth helmet"
+    );
+}
+
+#[test]
+fn entity_writes_are_versioned_and_audited() {
+    let db: TempColdStorage = TempColdStorage::new("entity_writes_are_versioned_and_audited");
+    ensure_app_schema(&db.conn).unwrap();
+    let repo: Repository = Repository::new(&db.conn);
+
+    let orc: EntityBase = EntityBase {
+        id: 100,
+        name: String::from("orc"),
+        core_type_id: 3,
+        sub_type_id: 1,
+    };
+    assert!(repo.create_entity(&orc).unwrap());
+    assert!(!repo.create_entity(&orc).unwrap());
+
+    let seen: i64 = repo.entity_row_version(100).unwrap().expect("entity 100");
+    let renamed: EntityBase = EntityBase {
+        name: String::from("orc chief"),
+        ..orc.clone()
+    };
+    assert_eq!(repo.update_entity(&renamed, Some(seen)).unwrap(), None);
+    assert_eq!(repo.entity(100).unwrap(), Some(renamed.clone()));
+
+    // the rename moved the version on, so a write still holding `seen` is turned away
+    let current: Option<i64> = repo.entity_row_version(100).unwrap();
+    assert_eq!(repo.update_entity(&orc, Some(seen)).unwrap(), current);
+    assert_eq!(repo.entity(100).unwrap(), Some(renamed));
+
+    assert!(repo.set_entity_composition_layer(100, 2, true).unwrap());
+    assert!(!repo.set_entity_composition_layer(100, 2, true).unwrap());
+    assert_eq!(repo.entity_composition_layers(100).unwrap()[0].id, 2);
+    assert!(repo.set_entity_composition_layer(100, 2, false).unwrap());
+    assert!(repo.entity_composition_layers(100).unwrap().is_empty());
+
+    let audited: i64 = db
+        .conn
+        .query_row(
+            "SELECT COUNT(*) FROM entity_audit_log WHERE entity_base_id = 100;",
+            (),
+            |row| row.get(0),
+        )
+        .unwrap();
+    // created, renamed, layer on and layer off
+    assert_eq!(audited, 4);
+}
+
+#[test]
+fn session_audit_ids_are_only_this_connections() {
+    let db: TempColdStorage = TempColdStorage::new("session_audit_ids_are_only_this_connections");
+    ensure_app_schema(&db.conn).unwrap();
+    let other: Connection = Connection::open(db.conn.path().expect("file backed")).unwrap();
+
+    let orc: EntityBase = EntityBase {
+        id: 100,
+        name: String::from("orc"),
+        core_type_id: 3,
+        sub_type_id: 1,
+    };
+    assert!(Repository::new(&db.conn).create_entity(&orc).unwrap());
+    let goblin: EntityBase = EntityBase {
+        id: 101,
+        name: String::from("goblin"),
+        ..orc.clone()
+    };
+    assert!(Repository::new(&other).create_entity(&goblin).unwrap());
+
+    let ours: Vec<i64> = session_audit_ids_after(&db.conn, 0).unwrap();
+    assert_eq!(ours.len(), 1);
+    assert_eq!(session_audit_ids_after(&db.conn, ours[0]).unwrap(), vec![]);
+    let theirs: Vec<i64> = session_audit_ids_after(&other, 0).unwrap();
+    assert_eq!(theirs.len(), 1);
+    assert!(theirs[0] > ours[0]);
+}
+
+#[test]
+fn generates_an_enum_per_listed_table() {
+    let db: TempColdStorage = TempColdStorage::new("generates_an_enum_per_listed_table");
+
+    let enums: String = generate_enums(&db.conn);
+    assert!(enums.contains("\nenum EntityActions{\n\tWALK = 1,\n\tJUMP = 2,\n"));
+    assert!(enums.contains("\nenum EntityActionsTargetsTypes{\n\tSELF = 1,\n"));
+}
@@ -0,0 +1,28 @@
+
+enum EntityActions{
+	WALK = 1,
+	BASH = 2,
+}
+
+enum EntityActionsTargetsTypes{
+	SELF = 1,
+	OTHER = 2,
+}
+
+enum EntityAttributes{
+	STRENGTH = 1,
+	HEALTH = 2,
+	STAMINA = 3,
+}
+
+enum EntityCompositionLayers{
+	EntityActions = 1,
+	EntityAttributes = 2,
+	EntityIdentity = 3,
+	EntityActionRequirement = 4,
+}
+
+enum EntityCoreTypes{
+	ITEM = 1,
+	MOB = 2,
+}